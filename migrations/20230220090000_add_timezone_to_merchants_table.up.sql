@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE merchants ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'Asia/Jakarta';
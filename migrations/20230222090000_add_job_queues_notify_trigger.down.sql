@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS job_queues_status_priority_idx;
+DROP TRIGGER IF EXISTS job_queues_notify ON job_queues;
+DROP FUNCTION IF EXISTS notify_job_queues();
@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- wake up idle workers whenever a job becomes available
+CREATE OR REPLACE FUNCTION notify_job_queues() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status = 'pending' THEN
+        PERFORM pg_notify('job_queues', NEW.id::text);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER job_queues_notify
+AFTER INSERT OR UPDATE OF status ON job_queues
+FOR EACH ROW EXECUTE PROCEDURE notify_job_queues();
+
+CREATE INDEX job_queues_status_priority_idx ON job_queues (status, priority, created_at);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE job_schedules DROP COLUMN end_at;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE job_schedules ADD COLUMN end_at TIMESTAMP;
+-- time after which a recurring job should not run anymore
@@ -0,0 +1,43 @@
+-- Add up migration script here
+CREATE TABLE job_runs (
+    id SERIAL PRIMARY KEY,
+    -- unique ID for the run
+    job_queue_id INTEGER NOT NULL,
+    -- queued job this run executed, retries of the same job share it
+    job_schedule_id INTEGER,
+    job_type VARCHAR(255) NOT NULL,
+    merchant_id uuid,
+    customer_id uuid,
+    invoice_id uuid,
+    status VARCHAR(255) NOT NULL,
+    -- status of the run (in_progress, completed, failed)
+    error TEXT,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP,
+    FOREIGN KEY (job_queue_id) REFERENCES job_queues(id) ON DELETE CASCADE,
+    FOREIGN KEY (job_schedule_id) REFERENCES job_schedules(id) ON DELETE SET NULL
+);
+
+CREATE INDEX job_runs_merchant_customer_idx ON job_runs (merchant_id, customer_id);
+CREATE INDEX job_runs_invoice_idx ON job_runs (invoice_id);
+
+CREATE TABLE deliveries (
+    id SERIAL PRIMARY KEY,
+    job_run_id INTEGER NOT NULL,
+    job_queue_id INTEGER NOT NULL,
+    customer_contact_channel_id uuid NOT NULL,
+    channel VARCHAR(255) NOT NULL,
+    -- contact channel name (whatsapp, email, telegram)
+    recipient VARCHAR(255) NOT NULL,
+    status VARCHAR(255) NOT NULL,
+    -- outcome of the attempt (sent, failed)
+    provider_message_id VARCHAR(255),
+    provider_response TEXT,
+    error TEXT,
+    attempted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (job_run_id) REFERENCES job_runs(id) ON DELETE CASCADE,
+    FOREIGN KEY (job_queue_id) REFERENCES job_queues(id) ON DELETE CASCADE
+);
+
+CREATE INDEX deliveries_job_queue_idx ON deliveries (job_queue_id, status);
+CREATE INDEX deliveries_job_run_idx ON deliveries (job_run_id);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS job_deferrals;
+ALTER TABLE job_queues DROP COLUMN IF EXISTS available_at;
+DROP TABLE IF EXISTS merchant_holidays;
+DROP TABLE IF EXISTS merchant_sending_windows;
@@ -0,0 +1,43 @@
+-- Add up migration script here
+CREATE TABLE merchant_sending_windows (
+    id SERIAL PRIMARY KEY,
+    merchant_id uuid NOT NULL,
+    day_of_week SMALLINT NOT NULL,
+    -- ISO day of week, 1 = Monday ... 7 = Sunday
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    -- wall-clock times in the merchant's timezone, end_time is exclusive
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE,
+    CHECK (day_of_week BETWEEN 1 AND 7),
+    CHECK (start_time < end_time)
+);
+
+CREATE INDEX merchant_sending_windows_merchant_idx ON merchant_sending_windows (merchant_id);
+
+CREATE TABLE merchant_holidays (
+    id SERIAL PRIMARY KEY,
+    merchant_id uuid NOT NULL,
+    date DATE NOT NULL,
+    -- whole day in the merchant's timezone
+    description VARCHAR(255),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE,
+    UNIQUE (merchant_id, date)
+);
+
+ALTER TABLE job_queues ADD COLUMN available_at TIMESTAMP NOT NULL DEFAULT NOW();
+-- workers don't pick the job before this time
+
+CREATE TABLE job_deferrals (
+    id SERIAL PRIMARY KEY,
+    job_queue_id INTEGER NOT NULL,
+    reason VARCHAR(255) NOT NULL,
+    deferred_from TIMESTAMP NOT NULL,
+    deferred_until TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (job_queue_id) REFERENCES job_queues(id) ON DELETE CASCADE
+);
+
+CREATE INDEX job_deferrals_job_queue_idx ON job_deferrals (job_queue_id);
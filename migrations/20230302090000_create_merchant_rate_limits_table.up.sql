@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE merchant_rate_limits (
+    id SERIAL PRIMARY KEY,
+    merchant_id uuid NOT NULL,
+    channel VARCHAR(255) NOT NULL,
+    -- contact channel name (whatsapp, email, telegram)
+    per_minute INTEGER NOT NULL,
+    burst INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE,
+    UNIQUE (merchant_id, channel),
+    CHECK (per_minute > 0 AND burst > 0)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE job_runs DROP COLUMN IF EXISTS reached_count;
+ALTER TABLE job_runs DROP COLUMN IF EXISTS recipient_count;
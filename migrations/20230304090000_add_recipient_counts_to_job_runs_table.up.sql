@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE job_runs ADD COLUMN recipient_count INTEGER;
+-- customers targeted by the run, resolved when a tag reminder runs
+ALTER TABLE job_runs ADD COLUMN reached_count INTEGER;
+-- customers whose channels all received the message
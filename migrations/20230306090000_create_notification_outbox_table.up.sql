@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE notification_outbox (
+    id SERIAL PRIMARY KEY,
+    channel VARCHAR(255) NOT NULL,
+    -- contact channel name (whatsapp, email, telegram)
+    recipient VARCHAR(255) NOT NULL,
+    additional_value VARCHAR(255),
+    subject VARCHAR(255),
+    sender_name VARCHAR(255),
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
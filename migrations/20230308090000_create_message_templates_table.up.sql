@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE message_templates (
+    id SERIAL PRIMARY KEY,
+    merchant_id uuid NOT NULL,
+    event_type VARCHAR(255) NOT NULL,
+    -- invoice or reminder
+    channel VARCHAR(255) NOT NULL DEFAULT 'all',
+    -- contact channel name (whatsapp, email, telegram) or all
+    subject VARCHAR(255),
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE,
+    UNIQUE (merchant_id, event_type, channel)
+);
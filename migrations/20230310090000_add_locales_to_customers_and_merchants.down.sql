@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE customers DROP COLUMN IF EXISTS locale;
+ALTER TABLE merchants DROP COLUMN IF EXISTS default_locale;
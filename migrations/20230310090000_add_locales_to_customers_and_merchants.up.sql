@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE merchants ADD COLUMN default_locale VARCHAR(8) NOT NULL DEFAULT 'id';
+-- id or en, used for customers without a locale
+ALTER TABLE customers ADD COLUMN locale VARCHAR(8);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE notification_outbox DROP COLUMN attachments;
+ALTER TABLE notification_outbox DROP COLUMN html;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE notification_outbox ADD COLUMN html TEXT;
+ALTER TABLE notification_outbox ADD COLUMN attachments TEXT[] NOT NULL DEFAULT '{}';
+-- filenames only, the content is not stored
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE customer_contact_channels DROP COLUMN opted_out_at;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE customer_contact_channels ADD COLUMN opted_out_at TIMESTAMP;
+-- set when the customer unsubscribes from the channel, nothing is sent to it until cleared
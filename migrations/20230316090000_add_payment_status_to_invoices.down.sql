@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX invoices_customer_status_idx;
+ALTER TABLE invoices DROP COLUMN paid_at;
+ALTER TABLE invoices DROP COLUMN status;
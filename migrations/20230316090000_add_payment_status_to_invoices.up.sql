@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE invoices ADD COLUMN status VARCHAR(32) NOT NULL DEFAULT 'unpaid';
+-- status of the payment (unpaid, paid, expired), updated by the xendit callback
+ALTER TABLE invoices ADD COLUMN paid_at TIMESTAMP;
+CREATE INDEX invoices_customer_status_idx ON invoices (customer_id, status);
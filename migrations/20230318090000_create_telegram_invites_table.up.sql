@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE telegram_invites (
+    id SERIAL PRIMARY KEY,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    -- payload of the t.me/<bot>?start=<token> link
+    merchant_id UUID NOT NULL REFERENCES merchants(id),
+    customer_id UUID NOT NULL REFERENCES customers(id),
+    chat_id BIGINT,
+    -- chat that accepted the invite
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_telegram_chat_id_idx;
+ALTER TABLE users DROP COLUMN telegram_chat_id;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN telegram_chat_id BIGINT;
+-- chat that gets the merchant alerts of the user and can use the merchant bot commands
+CREATE UNIQUE INDEX users_telegram_chat_id_idx ON users (telegram_chat_id);
@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE conversation_messages (
+    id uuid DEFAULT uuid_generate_v4(),
+    customer_contact_channel_id uuid,
+    -- channel the message matched, NULL for numbers no customer has
+    customer_id uuid,
+    channel VARCHAR(32) NOT NULL,
+    direction VARCHAR(16) NOT NULL,
+    -- inbound from the customer or outbound to them
+    address VARCHAR(255) NOT NULL,
+    -- number or chat of the customer on the channel
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id),
+    FOREIGN KEY (customer_contact_channel_id) REFERENCES customer_contact_channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (customer_id) REFERENCES customers(id) ON DELETE CASCADE
+);
+
+CREATE INDEX conversation_messages_customer_id_idx ON conversation_messages (customer_id, created_at);
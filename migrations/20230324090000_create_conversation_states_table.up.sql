@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE conversation_states (
+    key VARCHAR(255) NOT NULL,
+    -- e.g. telegram_<chat_id> while the bot waits for a merchant code
+    value TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    -- the state is ignored, and purged, once expired
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (key)
+);
+
+CREATE INDEX conversation_states_expires_at_idx ON conversation_states (expires_at);
@@ -0,0 +1,37 @@
+-- Add up migration script here
+CREATE TABLE webhook_subscriptions (
+    id SERIAL PRIMARY KEY,
+    merchant_id uuid NOT NULL,
+    url TEXT NOT NULL,
+    secret VARCHAR(255) NOT NULL,
+    -- key of the HMAC-SHA256 signature of every delivery
+    event_types TEXT[] NOT NULL DEFAULT '{}',
+    -- e.g. invoice.created, invoice.paid, customer.verified, job.failed
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP,
+    FOREIGN KEY (merchant_id) REFERENCES merchants(id) ON DELETE CASCADE
+);
+
+CREATE INDEX webhook_subscriptions_merchant_idx ON webhook_subscriptions (merchant_id);
+
+CREATE TABLE webhook_deliveries (
+    id SERIAL PRIMARY KEY,
+    webhook_subscription_id INTEGER NOT NULL,
+    event_type VARCHAR(255) NOT NULL,
+    payload JSONB NOT NULL,
+    -- body posted to the url, the same on every attempt
+    status VARCHAR(255) NOT NULL DEFAULT 'pending',
+    -- pending, retrying, delivered or failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    response_status INTEGER,
+    response_body TEXT,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    attempted_at TIMESTAMP,
+    delivered_at TIMESTAMP,
+    FOREIGN KEY (webhook_subscription_id) REFERENCES webhook_subscriptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX webhook_deliveries_subscription_idx ON webhook_deliveries (webhook_subscription_id, created_at);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX invoices_unpaid_invoice_date_idx;
+ALTER TABLE invoices DROP COLUMN overdue_at;
+ALTER TABLE webhook_subscriptions DROP COLUMN summary_sent_at;
+ALTER TABLE webhook_subscriptions DROP COLUMN kind;
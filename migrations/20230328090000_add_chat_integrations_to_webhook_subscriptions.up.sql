@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE webhook_subscriptions ADD COLUMN kind VARCHAR(32) NOT NULL DEFAULT 'webhook';
+-- webhook posts signed JSON events, slack and discord post formatted messages to an incoming webhook
+ALTER TABLE webhook_subscriptions ADD COLUMN summary_sent_at TIMESTAMP;
+-- when the last daily summary was queued for the subscription
+ALTER TABLE invoices ADD COLUMN overdue_at TIMESTAMP;
+-- when the unpaid invoice passed its due date and invoice.overdue was emitted
+CREATE INDEX invoices_unpaid_invoice_date_idx ON invoices (invoice_date) WHERE status = 'unpaid' AND overdue_at IS NULL;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX customer_contact_channels_primary_idx;
+ALTER TABLE customer_contact_channels DROP COLUMN is_primary;
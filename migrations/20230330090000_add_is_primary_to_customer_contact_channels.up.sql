@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE customer_contact_channels ADD COLUMN is_primary BOOLEAN NOT NULL DEFAULT FALSE;
+-- the channel listed for the customer, exactly one of the customer's channels
+UPDATE customer_contact_channels SET is_primary = TRUE
+WHERE id IN (
+    SELECT DISTINCT ON (customer_id) id FROM customer_contact_channels
+    WHERE deleted_at IS NULL
+    ORDER BY customer_id, created_at
+);
+CREATE UNIQUE INDEX customer_contact_channels_primary_idx ON customer_contact_channels (customer_id) WHERE is_primary AND deleted_at IS NULL;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE job_queues ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+-- failed runs so far, the job is marked dead once they reach its retry limit
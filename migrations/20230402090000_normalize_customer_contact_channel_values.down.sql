@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- the values as they were typed aren't kept, normalized values stay
@@ -0,0 +1,58 @@
+-- Add up migration script here
+-- values saved before contacts::normalize, rewritten in the form it produces.
+-- Values it would reject are left as they are.
+
+-- WhatsApp numbers as E.164 without the +, national numbers get the calling
+-- code of the customer's merchant, 62 when it has none
+WITH compact AS (
+    SELECT
+        customer_contact_channels.id,
+        regexp_replace(btrim(customer_contact_channels.value), '[ .()-]', '', 'g') AS value,
+        regexp_replace(COALESCE(merchants.phone_country_code, ''), '[^0-9]', '', 'g') AS country_code
+    FROM customer_contact_channels
+    INNER JOIN contact_channels ON contact_channels.id = customer_contact_channels.contact_channel_id
+    INNER JOIN customers ON customers.id = customer_contact_channels.customer_id
+    INNER JOIN merchants ON merchants.id = customers.merchant_id
+    WHERE contact_channels.name = 'whatsapp'
+), with_country_code AS (
+    SELECT
+        id,
+        value,
+        CASE WHEN length(country_code) BETWEEN 1 AND 3 THEN country_code ELSE '62' END AS country_code
+    FROM compact
+), normalized AS (
+    SELECT
+        id,
+        CASE
+            WHEN value LIKE '+%' THEN substr(value, 2)
+            WHEN value LIKE '00%' THEN substr(value, 3)
+            WHEN value LIKE '0%' THEN country_code || substr(value, 2)
+            WHEN value LIKE country_code || '%' THEN value
+            ELSE country_code || value
+        END AS value
+    FROM with_country_code
+)
+UPDATE customer_contact_channels
+SET value = normalized.value, updated_at = NOW()
+FROM normalized
+WHERE customer_contact_channels.id = normalized.id
+    AND normalized.value ~ '^[1-9][0-9]{7,14}$'
+    AND customer_contact_channels.value <> normalized.value;
+
+-- email addresses with their domain lowercased
+UPDATE customer_contact_channels
+SET
+    value = substring(btrim(value) from '^(.*)@') || '@' || lower(substring(btrim(value) from '@([^@]*)$')),
+    updated_at = NOW()
+WHERE contact_channel_id IN (SELECT id FROM contact_channels WHERE name = 'email')
+    AND btrim(value) ~ '^[^@\s]+@[^@\s]+$'
+    AND value <> substring(btrim(value) from '^(.*)@') || '@' || lower(substring(btrim(value) from '@([^@]*)$'));
+
+-- Telegram usernames lowercased without @ or a t.me link
+UPDATE customer_contact_channels
+SET
+    value = lower(regexp_replace(btrim(value), '^(https?://)?(t\.me/)?@?', '')),
+    updated_at = NOW()
+WHERE contact_channel_id IN (SELECT id FROM contact_channels WHERE name = 'telegram')
+    AND lower(regexp_replace(btrim(value), '^(https?://)?(t\.me/)?@?', '')) ~ '^[a-z][a-z0-9_]{3,30}[a-z0-9]$'
+    AND value <> lower(regexp_replace(btrim(value), '^(https?://)?(t\.me/)?@?', ''));
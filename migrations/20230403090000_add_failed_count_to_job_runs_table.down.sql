@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE job_runs DROP COLUMN IF EXISTS failed_count;
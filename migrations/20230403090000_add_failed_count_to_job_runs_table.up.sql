@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE job_runs ADD COLUMN failed_count INTEGER;
+-- customers whose delivery failed, the retry of the run resends to them
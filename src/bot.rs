@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    locale::{Locale, Text},
+    models::{
+        customer::Customer,
+        customer_contact_channel::CustomerContactChannel,
+        invoice::{CustomerInvoice, Invoice},
+        item::Item,
+        job_schedule::{repeat_interval_seconds, JobSchedule},
+        merchant::Merchant,
+        user::User,
+    },
+    notifications::{Button, Notification},
+    templates,
+    utils::timezone,
+};
+
+/// How many invoices `/invoices` and `/history` list.
+pub const LIST_LIMIT: i64 = 10;
+
+/// How long "Remind me later" puts off an invoice.
+const SNOOZE_HOURS: i64 = 24;
+
+/// `t.me` link that opens the bot and sends `/start <start_payload>`.
+pub fn start_url(start_payload: &str) -> String {
+    let bot_username = std::env::var("TELEGRAM_BOT_USERNAME").unwrap_or_default();
+
+    format!("https://t.me/{}?start={}", bot_username, start_payload)
+}
+
+/// Conversation state key of a link a merchant user made to connect their chat, holds the
+/// user id until the link is opened.
+pub fn user_link_key(token: &str) -> String {
+    format!("telegram_user_{}", token)
+}
+
+/// Schedules the invoice to be sent once more at `run_at` with the payment
+/// link it already has, moving the resend already waiting if there is one.
+/// Returns false when the invoice was never scheduled, its schedule carries
+/// the names the message needs.
+async fn resend_invoice(
+    db: &PgPool,
+    invoice_id: &Uuid,
+    run_at: &NaiveDateTime,
+) -> Result<bool, sqlx::Error> {
+    let snoozed = JobSchedule::get_snoozed_by_invoice_id(db, &invoice_id.to_string()).await?;
+
+    if let Some(snoozed) = snoozed {
+        JobSchedule::update_run_at(db, snoozed.id, run_at).await?;
+
+        return Ok(true);
+    }
+
+    let job_schedule =
+        match JobSchedule::get_by_job_data_json_by_invoice_id(db, &invoice_id.to_string()).await {
+            Ok(job_schedule) => job_schedule,
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(err) => return Err(err),
+        };
+
+    let mut job_data = match job_schedule.job_data {
+        Some(job_data) => job_data,
+        None => return Ok(false),
+    };
+    job_data["snoozed"] = serde_json::json!(true);
+
+    JobSchedule::create(
+        db,
+        "send_invoice",
+        Some(job_data),
+        run_at,
+        Some(repeat_interval_seconds("ONCE")),
+        Some(0),
+        Some(0),
+        None,
+        "scheduled",
+        None,
+        None,
+        None,
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// Buttons under an invoice: pay it, show its items and put it off.
+pub fn invoice_keyboard(
+    locale: Locale,
+    invoice_id: &Uuid,
+    pay_url: Option<&str>,
+) -> Vec<Vec<Button>> {
+    let mut keyboard = Vec::new();
+
+    if let Some(pay_url) = pay_url {
+        keyboard.push(vec![Button::url(locale.text(Text::InvoicePayNow), pay_url)]);
+    }
+
+    keyboard.push(vec![
+        Button::callback(
+            locale.text(Text::BotShowItems),
+            &format!("items:{}", invoice_id),
+        ),
+        Button::callback(
+            locale.text(Text::BotRemindLater),
+            &format!("snooze:{}", invoice_id),
+        ),
+    ]);
+
+    keyboard
+}
+
+struct ChatCustomer {
+    customer_id: Uuid,
+    merchant: Merchant,
+}
+
+/// A Telegram chat and the customers it is connected to, one per merchant,
+/// answers the commands and buttons of customers.
+pub struct BotChat {
+    customers: Vec<ChatCustomer>,
+    pub locale: Locale,
+}
+
+impl BotChat {
+    /// Resolves the customers from the chat id stored in `additional_value`
+    /// when the chat was connected. `locale` is used until it is connected.
+    pub async fn load(db: &PgPool, chat_id: &i64, locale: Locale) -> Result<Self, sqlx::Error> {
+        let contact_channels =
+            CustomerContactChannel::get_by_additional_value(db, "telegram", &chat_id.to_string())
+                .await?;
+
+        let mut customers: Vec<ChatCustomer> = Vec::new();
+        let mut locale = locale;
+
+        for contact_channel in contact_channels {
+            if customers
+                .iter()
+                .any(|customer| customer.customer_id == contact_channel.customer_id)
+            {
+                continue;
+            }
+
+            let customer = Customer::get_by_id_only(db, contact_channel.customer_id).await?;
+            let merchant = Merchant::get_by_id(db, customer.merchant_id).await?;
+
+            // the first customer the chat was connected as picks the language
+            if customers.is_empty() {
+                locale = customer.locale(&merchant);
+            }
+
+            customers.push(ChatCustomer {
+                customer_id: customer.id,
+                merchant,
+            });
+        }
+
+        Ok(Self { customers, locale })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        !self.customers.is_empty()
+    }
+
+    fn text(&self, text: Text) -> Notification {
+        Notification::new(self.locale.text(text))
+    }
+
+    /// Customers of the chat, only the one of `merchant_id` when given.
+    fn customer_ids(&self, merchant_id: Option<&Uuid>) -> Vec<Uuid> {
+        self.customers
+            .iter()
+            .filter(|customer| match merchant_id {
+                Some(merchant_id) => customer.merchant.id == *merchant_id,
+                None => true,
+            })
+            .map(|customer| customer.customer_id)
+            .collect()
+    }
+
+    /// Asks which merchant `action` is for when the chat is connected to
+    /// several and none was picked yet.
+    fn choose_merchant(&self, action: &str, merchant_id: Option<&Uuid>) -> Option<Notification> {
+        if merchant_id.is_some() {
+            return None;
+        }
+
+        let mut merchants: Vec<&Merchant> = Vec::new();
+        for customer in self.customers.iter() {
+            if !merchants.iter().any(|merchant| merchant.id == customer.merchant.id) {
+                merchants.push(&customer.merchant);
+            }
+        }
+
+        if merchants.len() < 2 {
+            return None;
+        }
+
+        let keyboard = merchants
+            .iter()
+            .map(|merchant| {
+                vec![Button::callback(
+                    &merchant.name,
+                    &format!("{}:{}", action, merchant.id),
+                )]
+            })
+            .collect();
+
+        Some(self.text(Text::BotChooseMerchant).with_keyboard(keyboard))
+    }
+
+    pub fn help(&self) -> Notification {
+        self.text(Text::BotHelp)
+    }
+
+    /// `/invoices`, `merchant_id` is set once a merchant was picked.
+    pub async fn invoices(
+        &self,
+        db: &PgPool,
+        merchant_id: Option<&Uuid>,
+    ) -> Result<Notification, sqlx::Error> {
+        if !self.is_connected() {
+            return Ok(self.text(Text::BotNotConnected));
+        }
+
+        if let Some(choose_merchant) = self.choose_merchant("invoices", merchant_id) {
+            return Ok(choose_merchant);
+        }
+
+        let invoices = Invoice::get_by_customer_ids_and_status(
+            db,
+            &self.customer_ids(merchant_id),
+            "unpaid",
+            LIST_LIMIT,
+        )
+        .await?;
+
+        if invoices.is_empty() {
+            return Ok(self.text(Text::BotNoInvoices));
+        }
+
+        let lines: Vec<String> = invoices
+            .iter()
+            .map(|invoice| {
+                format!(
+                    "{} - {} - {}",
+                    invoice.invoice_number,
+                    invoice.merchant_name,
+                    self.locale.format_currency(invoice.total_amount as i64)
+                )
+            })
+            .collect();
+
+        let keyboard = invoices
+            .iter()
+            .map(|invoice| {
+                vec![Button::callback(
+                    &format!(
+                        "{} - {}",
+                        invoice.invoice_number,
+                        self.locale.format_currency(invoice.total_amount as i64)
+                    ),
+                    &format!("invoice:{}", invoice.id),
+                )]
+            })
+            .collect();
+
+        let message = format!(
+            "{}\n{}\n\n{}",
+            self.locale.text(Text::BotInvoices),
+            lines.join("\n"),
+            self.locale.text(Text::BotInvoiceUsage)
+        );
+
+        Ok(Notification::new(&message).with_keyboard(keyboard))
+    }
+
+    /// `/invoice <number>`
+    pub async fn invoice(
+        &self,
+        db: &PgPool,
+        invoice_number: Option<&str>,
+    ) -> Result<Notification, sqlx::Error> {
+        if !self.is_connected() {
+            return Ok(self.text(Text::BotNotConnected));
+        }
+
+        let invoice_number = match invoice_number {
+            Some(invoice_number) => invoice_number,
+            None => return Ok(self.text(Text::BotInvoiceUsage)),
+        };
+
+        let invoice = match Invoice::get_by_customer_ids_and_invoice_number(
+            db,
+            &self.customer_ids(None),
+            invoice_number,
+        )
+        .await
+        {
+            Ok(invoice) => invoice,
+            Err(sqlx::Error::RowNotFound) => return Ok(self.text(Text::BotInvoiceNotFound)),
+            Err(err) => return Err(err),
+        };
+
+        self.invoice_details(db, &invoice).await
+    }
+
+    /// `/history`, `merchant_id` is set once a merchant was picked.
+    pub async fn history(
+        &self,
+        db: &PgPool,
+        merchant_id: Option<&Uuid>,
+    ) -> Result<Notification, sqlx::Error> {
+        if !self.is_connected() {
+            return Ok(self.text(Text::BotNotConnected));
+        }
+
+        if let Some(choose_merchant) = self.choose_merchant("history", merchant_id) {
+            return Ok(choose_merchant);
+        }
+
+        let invoices = Invoice::get_by_customer_ids_and_status(
+            db,
+            &self.customer_ids(merchant_id),
+            "paid",
+            LIST_LIMIT,
+        )
+        .await?;
+
+        if invoices.is_empty() {
+            return Ok(self.text(Text::BotNoPayments));
+        }
+
+        let lines: Vec<String> = invoices
+            .iter()
+            .map(|invoice| {
+                let paid_at = invoice.paid_at.unwrap_or(invoice.invoice_date);
+
+                format!(
+                    "{} - {} - {} - {}",
+                    self.locale.format_date(&paid_at.date()),
+                    invoice.invoice_number,
+                    invoice.merchant_name,
+                    self.locale.format_currency(invoice.total_amount as i64)
+                )
+            })
+            .collect();
+
+        let message = format!(
+            "{}\n{}",
+            self.locale.text(Text::BotHistory),
+            lines.join("\n")
+        );
+
+        Ok(Notification::new(&message))
+    }
+
+    /// Answers a pressed button, `data` is `<action>:<id>`.
+    pub async fn callback(&self, db: &PgPool, data: &str) -> Result<Notification, sqlx::Error> {
+        if !self.is_connected() {
+            return Ok(self.text(Text::BotNotConnected));
+        }
+
+        let (action, id) = match data
+            .split_once(':')
+            .and_then(|(action, id)| Some((action, Uuid::parse_str(id).ok()?)))
+        {
+            Some((action, id)) => (action, id),
+            None => return Ok(self.help()),
+        };
+
+        match action {
+            "invoices" => return self.invoices(db, Some(&id)).await,
+            "history" => return self.history(db, Some(&id)).await,
+            _ => (),
+        }
+
+        let invoice =
+            match Invoice::get_by_customer_ids_and_id(db, &self.customer_ids(None), &id).await {
+                Ok(invoice) => invoice,
+                Err(sqlx::Error::RowNotFound) => return Ok(self.text(Text::BotInvoiceNotFound)),
+                Err(err) => return Err(err),
+            };
+
+        match action {
+            "items" => self.invoice_items(db, &invoice).await,
+            "snooze" => self.snooze(db, &invoice).await,
+            _ => self.invoice_details(db, &invoice).await,
+        }
+    }
+
+    async fn invoice_details(
+        &self,
+        db: &PgPool,
+        invoice: &CustomerInvoice,
+    ) -> Result<Notification, sqlx::Error> {
+        let locale = self.locale;
+        let items = Item::get_by_invoice_id(db, &invoice.id).await?;
+
+        let mut lines = vec![
+            format!(
+                "{} {}",
+                locale.text(Text::InvoiceTitle),
+                invoice.invoice_number
+            ),
+            invoice.merchant_name.clone(),
+        ];
+
+        if let Some(title) = &invoice.title {
+            lines.push(title.clone());
+        }
+
+        lines.push(format!(
+            "{}: {}",
+            locale.text(Text::InvoiceStatus),
+            self.status(&invoice.status)
+        ));
+        lines.push(String::new());
+        lines.extend(self.item_lines(invoice, &items));
+
+        let notification = Notification::new(&lines.join("\n"));
+
+        if invoice.status != "unpaid" {
+            return Ok(notification);
+        }
+
+        let mut keyboard = Vec::new();
+        if let Some(pay_url) = &invoice.pay_url {
+            keyboard.push(vec![Button::url(locale.text(Text::InvoicePayNow), pay_url)]);
+        }
+        keyboard.push(vec![Button::callback(
+            locale.text(Text::BotRemindLater),
+            &format!("snooze:{}", invoice.id),
+        )]);
+
+        Ok(notification.with_keyboard(keyboard))
+    }
+
+    async fn invoice_items(
+        &self,
+        db: &PgPool,
+        invoice: &CustomerInvoice,
+    ) -> Result<Notification, sqlx::Error> {
+        let items = Item::get_by_invoice_id(db, &invoice.id).await?;
+
+        let mut lines = vec![format!(
+            "{} {}",
+            self.locale.text(Text::InvoiceTitle),
+            invoice.invoice_number
+        )];
+        lines.extend(self.item_lines(invoice, &items));
+
+        Ok(Notification::new(&lines.join("\n")))
+    }
+
+    fn item_lines(&self, invoice: &CustomerInvoice, items: &[Item]) -> Vec<String> {
+        let locale = self.locale;
+
+        let mut lines: Vec<String> = items
+            .iter()
+            .map(|item| {
+                format!(
+                    "- {} x{} @ {}",
+                    item.description,
+                    item.quantity,
+                    locale.format_currency(item.price as i64)
+                )
+            })
+            .collect();
+
+        lines.push(format!(
+            "{}: {}",
+            locale.text(Text::InvoiceTotal),
+            locale.format_currency(invoice.total_amount as i64)
+        ));
+
+        lines
+    }
+
+    /// Sends the invoice again in `SNOOZE_HOURS` with the payment link it
+    /// already has.
+    async fn snooze(
+        &self,
+        db: &PgPool,
+        invoice: &CustomerInvoice,
+    ) -> Result<Notification, sqlx::Error> {
+        if invoice.status == "paid" {
+            return Ok(self.text(Text::BotInvoiceAlreadyPaid));
+        }
+
+        let run_at = Utc::now().naive_utc() + Duration::hours(SNOOZE_HOURS);
+
+        if !resend_invoice(db, &invoice.id, &run_at).await? {
+            return Ok(self.text(Text::BotSnoozeFailed));
+        }
+
+        let tz = match self
+            .customers
+            .iter()
+            .find(|customer| customer.merchant.id == invoice.merchant_id)
+        {
+            Some(customer) => customer.merchant.tz(),
+            None => chrono_tz::UTC,
+        };
+
+        let date = self
+            .locale
+            .format_datetime(&timezone::utc_to_local(&run_at, &tz));
+
+        Ok(Notification::new(&templates::render(
+            self.locale.text(Text::BotSnoozed),
+            &HashMap::from([("date", date)]),
+        )))
+    }
+
+    fn status(&self, status: &str) -> &'static str {
+        match status {
+            "paid" => self.locale.text(Text::InvoicePaid),
+            "expired" => self.locale.text(Text::InvoiceExpired),
+            _ => self.locale.text(Text::InvoiceUnpaid),
+        }
+    }
+}
+
+/// A Telegram chat linked to a merchant user, answers the commands of
+/// merchants about all the merchants of the user.
+pub struct MerchantChat {
+    user: User,
+    merchants: Vec<Merchant>,
+    pub locale: Locale,
+}
+
+impl MerchantChat {
+    /// `None` when no user linked the chat.
+    pub async fn load(db: &PgPool, chat_id: &i64) -> Result<Option<Self>, sqlx::Error> {
+        let user = match User::get_by_telegram_chat_id(db, chat_id).await {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let merchants = Merchant::get_by_user_id(db, &user.id).await?;
+        let locale = match merchants.first() {
+            Some(merchant) => merchant.locale(),
+            None => Locale::default(),
+        };
+
+        Ok(Some(Self {
+            user,
+            merchants,
+            locale,
+        }))
+    }
+
+    /// Reply once the chat is linked, lists the commands.
+    pub fn linked(&self) -> Notification {
+        Notification::new(&templates::render(
+            self.locale.text(Text::BotUserLinked),
+            &HashMap::from([("name", self.user.name.clone())]),
+        ))
+    }
+
+    /// `/today`, invoices paid since midnight in each merchant's timezone.
+    pub async fn today(&self, db: &PgPool) -> Result<Notification, sqlx::Error> {
+        let locale = self.locale;
+        let now = Utc::now().naive_utc();
+        let mut lines = Vec::new();
+
+        for merchant in self.merchants.iter() {
+            let tz = merchant.tz();
+            let midnight = timezone::utc_to_local(&now, &tz)
+                .date()
+                .and_time(NaiveTime::from_hms(0, 0, 0));
+            let paid_since = timezone::local_to_utc(&midnight, &tz);
+
+            let invoices = Invoice::get_paid_by_merchant_id_since(db, &merchant.id, &paid_since)
+                .await?;
+
+            if invoices.is_empty() {
+                continue;
+            }
+
+            let total: i64 = invoices
+                .iter()
+                .map(|invoice| invoice.total_amount as i64)
+                .sum();
+
+            lines.push(format!(
+                "{} - {} - {}",
+                merchant.name,
+                invoices.len(),
+                locale.format_currency(total)
+            ));
+
+            for invoice in invoices.iter() {
+                lines.push(format!(
+                    "- {} - {}",
+                    invoice.invoice_number,
+                    locale.format_currency(invoice.total_amount as i64)
+                ));
+            }
+        }
+
+        if lines.is_empty() {
+            return Ok(Notification::new(locale.text(Text::BotTodayEmpty)));
+        }
+
+        Ok(Notification::new(&format!(
+            "{}\n{}",
+            locale.text(Text::BotToday),
+            lines.join("\n")
+        )))
+    }
+
+    /// `/remind <number>`, sends the invoice to the customer again now.
+    pub async fn remind(
+        &self,
+        db: &PgPool,
+        invoice_number: Option<&str>,
+    ) -> Result<Notification, sqlx::Error> {
+        let locale = self.locale;
+
+        let invoice_number = match invoice_number {
+            Some(invoice_number) => invoice_number,
+            None => return Ok(Notification::new(locale.text(Text::BotRemindUsage))),
+        };
+
+        let merchant_ids: Vec<Uuid> = self.merchants.iter().map(|merchant| merchant.id).collect();
+
+        let invoice = match Invoice::get_by_merchant_ids_and_invoice_number(
+            db,
+            &merchant_ids,
+            invoice_number,
+        )
+        .await
+        {
+            Ok(invoice) => invoice,
+            Err(sqlx::Error::RowNotFound) => {
+                return Ok(Notification::new(locale.text(Text::BotInvoiceNotFound)))
+            }
+            Err(err) => return Err(err),
+        };
+
+        if invoice.status == "paid" {
+            return Ok(Notification::new(locale.text(Text::BotInvoiceAlreadyPaid)));
+        }
+
+        let customer = Customer::get_by_id_only(db, invoice.customer_id).await?;
+        let values = HashMap::from([
+            ("invoice_number", invoice.invoice_number.clone()),
+            ("customer_name", customer.name),
+        ]);
+
+        let text = if resend_invoice(db, &invoice.id, &Utc::now().naive_utc()).await? {
+            Text::BotReminderQueued
+        } else {
+            Text::BotRemindFailed
+        };
+
+        Ok(Notification::new(&templates::render(locale.text(text), &values)))
+    }
+
+    /// `/logout`
+    pub async fn unlink(&self, db: &PgPool) -> Result<Notification, sqlx::Error> {
+        User::update_telegram_chat_id(db, &self.user.id, None).await?;
+
+        Ok(Notification::new(self.locale.text(Text::BotUserUnlinked)))
+    }
+}
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use config::ConfigError;
 use serde::Deserialize;
 
+use crate::jobs::rate_limiter::RateLimit;
+
 #[derive(Deserialize)]
 pub struct ServerConfig {
     pub host: String, 
@@ -18,12 +22,65 @@ pub struct DatabaseConfig {
     pub poolmaxsize: u32,
 }
 
+#[derive(Deserialize, Default)]
+pub struct WorkerConfig {
+    // WORKER_CONCURRENCY, number of jobs processed at the same time
+    pub concurrency: Option<usize>,
+    // WORKER_POLLINTERVAL, seconds between polls when no notification arrives
+    pub pollinterval: Option<u64>,
+    // WORKER_LIMIT_<CHANNEL>, e.g. WORKER_LIMIT_WHATSAPP=2
+    pub limit: Option<HashMap<String, usize>>,
+    // WORKER_SHUTDOWNTIMEOUT, seconds to wait for in-flight jobs on shutdown
+    pub shutdowntimeout: Option<u64>,
+    // WORKER_RATE_<CHANNEL>, messages per minute, e.g. WORKER_RATE_WHATSAPP=20
+    pub rate: Option<HashMap<String, u32>>,
+    // WORKER_BURST_<CHANNEL>, messages sent back to back before the rate applies
+    pub burst: Option<HashMap<String, u32>>,
+}
+
+/// Which parts of the server run in this process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Api,
+    Worker,
+    All,
+}
+
+impl Mode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "api" => Some(Mode::Api),
+            "worker" => Some(Mode::Worker),
+            "all" => Some(Mode::All),
+            _ => None,
+        }
+    }
+
+    pub fn runs_api(&self) -> bool {
+        *self == Mode::Api || *self == Mode::All
+    }
+
+    pub fn runs_worker(&self) -> bool {
+        *self == Mode::Worker || *self == Mode::All
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AppConfig {
+    // APP_MODE, api, worker or all
+    pub mode: Option<String>,
+    // APP_SCHEME, http or https, of the links sent to customers
+    pub scheme: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
+    pub app: Option<AppConfig>,
     pub server: Option<ServerConfig>,
     pub environment: Option<String>,
     pub appkey: Option<String>,
     pub pg: Option<DatabaseConfig>,
+    pub worker: Option<WorkerConfig>,
 }
 
 impl Config {
@@ -35,6 +92,85 @@ impl Config {
             .try_deserialize()
     }
 
+    /// `APPKEY` keys password hashes and unsubscribe tokens, a missing one
+    /// would make them guessable.
+    pub fn check(&self) -> Result<(), String> {
+        if self.appkey.as_deref().unwrap_or_default().is_empty() {
+            return Err("APPKEY must be set".to_string());
+        }
+
+        let scheme = self.app.as_ref().and_then(|app| app.scheme.as_deref());
+        if !matches!(scheme, None | Some("") | Some("http") | Some("https")) {
+            return Err("APP_SCHEME must be http or https".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// The first CLI argument (`invoice-billing-server worker`) takes
+    /// precedence over `APP_MODE`, defaults to `all`.
+    pub fn mode(&self) -> Mode {
+        let from_args = std::env::args().nth(1).and_then(|arg| Mode::parse(&arg));
+        let from_env = self
+            .app
+            .as_ref()
+            .and_then(|app| app.mode.as_ref())
+            .and_then(|mode| Mode::parse(mode));
+
+        from_args.or(from_env).unwrap_or(Mode::All)
+    }
+
+    pub fn worker_shutdown_timeout(&self) -> u64 {
+        self.worker
+            .as_ref()
+            .and_then(|worker| worker.shutdowntimeout)
+            .unwrap_or(30)
+    }
+
+    pub fn worker_concurrency(&self) -> usize {
+        self.worker
+            .as_ref()
+            .and_then(|worker| worker.concurrency)
+            .unwrap_or(4)
+            .max(1)
+    }
+
+    pub fn worker_poll_interval(&self) -> u64 {
+        self.worker
+            .as_ref()
+            .and_then(|worker| worker.pollinterval)
+            .unwrap_or(5)
+            .max(1)
+    }
+
+    pub fn worker_channel_limits(&self) -> HashMap<String, usize> {
+        self.worker
+            .as_ref()
+            .and_then(|worker| worker.limit.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn worker_channel_rates(&self) -> HashMap<String, RateLimit> {
+        let rates = self
+            .worker
+            .as_ref()
+            .and_then(|worker| worker.rate.clone())
+            .unwrap_or_default();
+        let bursts = self
+            .worker
+            .as_ref()
+            .and_then(|worker| worker.burst.clone())
+            .unwrap_or_default();
+
+        rates
+            .into_iter()
+            .map(|(channel, per_minute)| {
+                let burst = bursts.get(&channel).copied().unwrap_or(1);
+                (channel, RateLimit { per_minute, burst })
+            })
+            .collect()
+    }
+
     pub fn database_url(&self) -> String {
         format!(
             "postgres://{}:{}@{}:{}/{}",
@@ -0,0 +1,250 @@
+use crate::models::merchant::Merchant;
+
+/// Calling code of numbers written without one, when the merchant has none.
+pub const DEFAULT_COUNTRY_CODE: &str = "62";
+
+/// The value customers are reached at on `channel_name`, in the form it is
+/// stored and looked up in. Numbers written without a calling code get
+/// `country_code`.
+pub fn normalize(channel_name: &str, value: &str, country_code: &str) -> Result<String, String> {
+    match channel_name {
+        "whatsapp" => normalize_phone(value, country_code),
+        "email" => normalize_email(value),
+        "telegram" => normalize_telegram_username(value),
+        _ => Ok(value.trim().to_string()),
+    }
+}
+
+/// Calling code from `Merchant.phone_country_code`, written as `62`, `+62`
+/// or `(+62)`.
+pub fn country_code(merchant: &Merchant) -> String {
+    let country_code = merchant
+        .phone_country_code
+        .as_deref()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>();
+
+    if country_code.is_empty() || country_code.len() > 3 {
+        return DEFAULT_COUNTRY_CODE.to_string();
+    }
+
+    country_code
+}
+
+/// E.164 number without the leading `+`, the form WhatsApp uses for its ids.
+///
+/// `+62 812-3456-7890`, `0062 812 3456 7890` and, with `62` as
+/// `country_code`, `0812 3456 7890` are all `6281234567890`.
+pub fn normalize_phone(value: &str, country_code: &str) -> Result<String, String> {
+    let value = value.trim();
+    let compact = value
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect::<String>();
+
+    let number = if let Some(international) = compact.strip_prefix('+') {
+        international.to_string()
+    } else if let Some(international) = compact.strip_prefix("00") {
+        international.to_string()
+    } else if let Some(national) = compact.strip_prefix('0') {
+        format!("{}{}", country_code, national)
+    } else if compact.starts_with(country_code) {
+        compact
+    } else {
+        format!("{}{}", country_code, compact)
+    };
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("{} is not a phone number", value));
+    }
+
+    // E.164 allows 15 digits, the shortest subscriber numbers make 8
+    if number.len() < 8 || number.len() > 15 || number.starts_with('0') {
+        return Err(format!("{} is not a valid phone number", value));
+    }
+
+    Ok(number)
+}
+
+/// The address with its domain lowercased, the local part is kept as it is
+/// since mail servers may tell cases apart.
+pub fn normalize_email(value: &str) -> Result<String, String> {
+    let value = value.trim();
+
+    if !validator::validate_email(value) {
+        return Err(format!("{} is not a valid email address", value));
+    }
+
+    match value.rsplit_once('@') {
+        Some((local, domain)) => Ok(format!("{}@{}", local, domain.to_lowercase())),
+        None => Err(format!("{} is not a valid email address", value)),
+    }
+}
+
+/// Lowercased username without `@`, `t.me/` links are accepted too.
+///
+/// Usernames are 5 to 32 letters, digits and underscores, start with a
+/// letter and don't end with an underscore. Telegram ignores their case.
+pub fn normalize_telegram_username(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let username = value
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("t.me/")
+        .trim_start_matches('@')
+        .to_lowercase();
+
+    let is_valid = (5..=32).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && username.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !username.ends_with('_');
+
+    if !is_valid {
+        return Err(format!("{} is not a valid Telegram username", value));
+    }
+
+    Ok(username)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn merchant(phone_country_code: Option<&str>) -> Merchant {
+        Merchant {
+            id: Uuid::new_v4(),
+            name: "Toko Maju".to_string(),
+            description: String::new(),
+            user_id: Uuid::new_v4(),
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+            deleted_at: None,
+            address: None,
+            phone_country_code: phone_country_code.map(str::to_string),
+            phone_number: None,
+            tax: None,
+            merchant_code: None,
+            timezone: "Asia/Jakarta".to_string(),
+            default_locale: "id".to_string(),
+        }
+    }
+
+    #[test]
+    fn country_code_keeps_the_digits() {
+        assert_eq!(country_code(&merchant(Some("62"))), "62");
+        assert_eq!(country_code(&merchant(Some("+65"))), "65");
+        assert_eq!(country_code(&merchant(Some("(+1)"))), "1");
+    }
+
+    #[test]
+    fn country_code_falls_back_to_the_default() {
+        assert_eq!(country_code(&merchant(None)), DEFAULT_COUNTRY_CODE);
+        assert_eq!(country_code(&merchant(Some(""))), DEFAULT_COUNTRY_CODE);
+        assert_eq!(
+            country_code(&merchant(Some("Indonesia"))),
+            DEFAULT_COUNTRY_CODE
+        );
+        assert_eq!(country_code(&merchant(Some("+6212"))), DEFAULT_COUNTRY_CODE);
+    }
+
+    #[test]
+    fn normalize_phone_accepts_the_usual_spellings() {
+        for value in [
+            "+62 812-3456-7890",
+            "0062 812 3456 7890",
+            "0812 3456 7890",
+            "(0812) 3456.7890",
+            "6281234567890",
+            "81234567890",
+            " +6281234567890 ",
+        ] {
+            assert_eq!(
+                normalize_phone(value, "62").as_deref(),
+                Ok("6281234567890"),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_phone_uses_the_given_country_code() {
+        assert_eq!(
+            normalize_phone("9123 4567", "65").as_deref(),
+            Ok("6591234567")
+        );
+        assert_eq!(
+            normalize_phone("+62 812 3456 7890", "65").as_deref(),
+            Ok("6281234567890")
+        );
+    }
+
+    #[test]
+    fn normalize_phone_rejects_what_is_not_a_number() {
+        assert!(normalize_phone("", "62").is_err());
+        assert!(normalize_phone("+", "62").is_err());
+        assert!(normalize_phone("0812 abcd 7890", "62").is_err());
+        assert!(normalize_phone("+62 812", "62").is_err());
+        assert!(normalize_phone("+62 8123 4567 8901 234", "62").is_err());
+        assert!(normalize_phone("+0812 3456 7890", "62").is_err());
+    }
+
+    #[test]
+    fn normalize_email_lowercases_the_domain_only() {
+        assert_eq!(
+            normalize_email(" Budi.Santoso@Example.COM ").as_deref(),
+            Ok("Budi.Santoso@example.com")
+        );
+        assert!(normalize_email("budi").is_err());
+        assert!(normalize_email("budi@").is_err());
+        assert!(normalize_email("budi santoso@example.com").is_err());
+    }
+
+    #[test]
+    fn normalize_telegram_username_strips_links_and_case() {
+        for value in [
+            "@Budi_Santoso",
+            "budi_santoso",
+            "t.me/Budi_Santoso",
+            "https://t.me/budi_santoso",
+        ] {
+            assert_eq!(
+                normalize_telegram_username(value).as_deref(),
+                Ok("budi_santoso"),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_telegram_username_rejects_invalid_usernames() {
+        for value in ["@budi", "1budi", "budi_", "budi-santoso", &"a".repeat(33)] {
+            assert!(normalize_telegram_username(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn normalize_picks_the_rule_of_the_channel() {
+        assert_eq!(
+            normalize("whatsapp", "0812 3456 7890", "62").as_deref(),
+            Ok("6281234567890")
+        );
+        assert_eq!(
+            normalize("email", "a@B.com", "62").as_deref(),
+            Ok("a@b.com")
+        );
+        assert_eq!(
+            normalize("telegram", "@BudiS", "62").as_deref(),
+            Ok("budis")
+        );
+        assert_eq!(normalize("sms", " 0812 ", "62").as_deref(), Ok("0812"));
+    }
+}
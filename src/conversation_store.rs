@@ -0,0 +1,151 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use deadpool_redis::{Config, Pool, Runtime};
+use redis::cmd;
+use sqlx::PgPool;
+
+use crate::{errors::DefaultError, models::conversation_state::ConversationState};
+
+/// What the bots wait for between two messages of a conversation, e.g. the
+/// merchant code after `/connect`. States expire after their TTL so an
+/// abandoned conversation starts over.
+#[async_trait]
+pub trait ConversationStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, DefaultError>;
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), DefaultError>;
+
+    async fn delete(&self, key: &str) -> Result<(), DefaultError>;
+}
+
+/// Redis when `REDIS_CONNECTION` is set, the `conversation_states` table
+/// otherwise so the bots work without Redis.
+pub fn from_env(pool: &PgPool) -> Arc<dyn ConversationStore> {
+    let redis_connection = std::env::var("REDIS_CONNECTION").unwrap_or_default();
+
+    if redis_connection.is_empty() {
+        return Arc::new(PostgresConversationStore::new(pool.clone()));
+    }
+
+    match RedisConversationStore::new(&redis_connection) {
+        Ok(store) => Arc::new(store),
+        Err(err) => {
+            println!(
+                "Redis unavailable, keeping conversations in Postgres: {}",
+                err.to_string()
+            );
+
+            Arc::new(PostgresConversationStore::new(pool.clone()))
+        }
+    }
+}
+
+pub struct PostgresConversationStore {
+    pool: PgPool,
+}
+
+impl PostgresConversationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ConversationStore for PostgresConversationStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, DefaultError> {
+        match ConversationState::get_by_key(&self.pool, key).await {
+            Ok(conversation_state) => Ok(conversation_state.map(|state| state.value)),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), DefaultError> {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(1));
+        let expires_at = chrono::Utc::now().naive_utc() + ttl;
+
+        // expired states are never read again, drop them as new ones come in
+        match ConversationState::delete_expired(&self.pool).await {
+            Ok(_) => (),
+            Err(err) => println!("Failed to purge expired conversation states: {}", err),
+        }
+
+        match ConversationState::upsert(&self.pool, key, value, &expires_at).await {
+            Ok(_) => Ok(()),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), DefaultError> {
+        match ConversationState::delete_by_key(&self.pool, key).await {
+            Ok(_) => Ok(()),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+}
+
+/// Pooled async connections, Redis expires the keys itself.
+pub struct RedisConversationStore {
+    pool: Pool,
+}
+
+impl RedisConversationStore {
+    pub fn new(redis_connection: &str) -> Result<Self, DefaultError> {
+        match Config::from_url(redis_connection).create_pool(Some(Runtime::Tokio1)) {
+            Ok(pool) => Ok(Self { pool }),
+            Err(err) => Err(DefaultError::new("redis".to_string(), err.to_string())),
+        }
+    }
+
+    async fn connection(&self, key: &str) -> Result<deadpool_redis::Connection, DefaultError> {
+        match self.pool.get().await {
+            Ok(connection) => Ok(connection),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl ConversationStore for RedisConversationStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, DefaultError> {
+        let mut connection = self.connection(key).await?;
+
+        match cmd("GET")
+            .arg(key)
+            .query_async::<_, Option<String>>(&mut connection)
+            .await
+        {
+            Ok(value) => Ok(value),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), DefaultError> {
+        let mut connection = self.connection(key).await?;
+
+        match cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("EX")
+            .arg(ttl.as_secs().max(1))
+            .query_async::<_, ()>(&mut connection)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), DefaultError> {
+        let mut connection = self.connection(key).await?;
+
+        match cmd("DEL")
+            .arg(key)
+            .query_async::<_, ()>(&mut connection)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(DefaultError::new(key.to_string(), err.to_string())),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use crate::{
+    locale::{Locale, Text},
+    models::{invoice::Invoice, item::Item},
+    templates::escape_html,
+};
+
+/// One row of the items table.
+#[derive(Debug, Clone)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: i32,
+    pub price: i64,
+}
+
+impl InvoiceLine {
+    pub fn amount(&self) -> i64 {
+        self.price * self.quantity as i64
+    }
+}
+
+/// An invoice laid out for the customer, as the HTML of an email or a PDF.
+#[derive(Debug, Clone)]
+pub struct InvoiceDocument {
+    pub locale: Locale,
+    pub merchant_name: String,
+    pub customer_name: String,
+    pub invoice_number: String,
+    pub title: Option<String>,
+    /// Already formatted for `locale`.
+    pub due_date: String,
+    pub pay_url: String,
+    pub lines: Vec<InvoiceLine>,
+    pub subtotal: i64,
+    pub tax: i64,
+    pub total: i64,
+}
+
+impl InvoiceDocument {
+    pub fn new(
+        invoice: &Invoice,
+        items: &[Item],
+        merchant_name: &str,
+        customer_name: &str,
+        due_date: &str,
+        pay_url: &str,
+        locale: Locale,
+    ) -> Self {
+        Self {
+            locale,
+            merchant_name: merchant_name.to_string(),
+            customer_name: customer_name.to_string(),
+            invoice_number: invoice.invoice_number.clone(),
+            title: invoice.title.clone(),
+            due_date: due_date.to_string(),
+            pay_url: pay_url.to_string(),
+            lines: items
+                .iter()
+                .map(|item| InvoiceLine {
+                    description: item.description.clone(),
+                    quantity: item.quantity,
+                    price: item.price as i64,
+                })
+                .collect(),
+            subtotal: invoice.amount as i64,
+            tax: invoice.tax_amount as i64,
+            total: invoice.total_amount as i64,
+        }
+    }
+
+    fn text(&self, text: Text) -> &'static str {
+        self.locale.text(text)
+    }
+
+    /// Email body with `message` above the invoice, inline styles only since
+    /// most mail clients drop `<style>`.
+    pub fn html(&self, message: &str) -> String {
+        let locale = self.locale;
+        let cell = "padding:8px;border-bottom:1px solid #e5e7eb;";
+        let number = "padding:8px;border-bottom:1px solid #e5e7eb;text-align:right;";
+
+        let message = message
+            .split("\n\n")
+            .map(|paragraph| {
+                format!(
+                    "<p>{}</p>",
+                    escape_html(paragraph.trim()).replace('\n', "<br>")
+                )
+            })
+            .collect::<String>();
+
+        let rows = self
+            .lines
+            .iter()
+            .map(|line| {
+                format!(
+                    "<tr><td style=\"{cell}\">{}</td><td style=\"{number}\">{}</td><td style=\"{number}\">{}</td><td style=\"{number}\">{}</td></tr>",
+                    escape_html(&line.description),
+                    line.quantity,
+                    locale.format_currency(line.price),
+                    locale.format_currency(line.amount()),
+                    cell = cell,
+                    number = number,
+                )
+            })
+            .collect::<String>();
+
+        let total_row = |label: Text, amount: i64, weight: &str| {
+            format!(
+                "<tr><td colspan=\"3\" style=\"{number}font-weight:{weight};\">{}</td><td style=\"{number}font-weight:{weight};\">{}</td></tr>",
+                self.text(label),
+                locale.format_currency(amount),
+                number = number,
+                weight = weight,
+            )
+        };
+
+        let title = match &self.title {
+            Some(title) => format!(
+                "<p style=\"margin:0;color:#6b7280;\">{}</p>",
+                escape_html(title)
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<body style="margin:0;padding:24px;background:#f3f4f6;font-family:Helvetica,Arial,sans-serif;color:#111827;">
+<div style="max-width:600px;margin:0 auto;background:#ffffff;padding:24px;border-radius:8px;">
+<h2 style="margin:0 0 4px 0;">{merchant_name}</h2>
+<p style="margin:0 0 16px 0;color:#6b7280;">{invoice_title} {invoice_number}</p>
+{message}
+<table style="width:100%;margin:16px 0;font-size:14px;">
+<tr><td>{billed_to_label}</td><td style="text-align:right;">{due_date_label}</td></tr>
+<tr><td><strong>{customer_name}</strong></td><td style="text-align:right;"><strong>{due_date}</strong></td></tr>
+</table>
+{title}
+<table style="width:100%;border-collapse:collapse;font-size:14px;">
+<tr><th style="{cell}text-align:left;">{item}</th><th style="{number}">{quantity}</th><th style="{number}">{price}</th><th style="{number}">{amount}</th></tr>
+{rows}
+{subtotal}
+{tax}
+{total}
+</table>
+<p style="text-align:center;margin:24px 0;"><a href="{pay_url}" style="display:inline-block;padding:12px 24px;background:#2563eb;color:#ffffff;text-decoration:none;border-radius:6px;font-weight:bold;">{pay_now}</a></p>
+</div>
+</body>
+</html>"#,
+            merchant_name = escape_html(&self.merchant_name),
+            invoice_title = self.text(Text::InvoiceTitle),
+            invoice_number = escape_html(&self.invoice_number),
+            message = message,
+            billed_to_label = self.text(Text::InvoiceBilledTo),
+            due_date_label = self.text(Text::InvoiceDueDate),
+            customer_name = escape_html(&self.customer_name),
+            due_date = escape_html(&self.due_date),
+            title = title,
+            cell = cell,
+            number = number,
+            item = self.text(Text::InvoiceItem),
+            quantity = self.text(Text::InvoiceQuantity),
+            price = self.text(Text::InvoicePrice),
+            amount = self.text(Text::InvoiceLineTotal),
+            rows = rows,
+            subtotal = total_row(Text::InvoiceSubtotal, self.subtotal, "normal"),
+            tax = total_row(Text::InvoiceTax, self.tax, "normal"),
+            total = total_row(Text::InvoiceTotal, self.total, "bold"),
+            pay_url = escape_html(&self.pay_url),
+            pay_now = self.text(Text::InvoicePayNow),
+        )
+    }
+
+    /// The invoice as a plain PDF, lines of monospaced text so the columns
+    /// line up without font metrics.
+    pub fn pdf(&self) -> Vec<u8> {
+        let locale = self.locale;
+        let rule = "-".repeat(78);
+        let column = |label: Text, amount: i64| {
+            format!(
+                "{:>60} {:>17}",
+                self.text(label),
+                locale.format_currency(amount)
+            )
+        };
+
+        let mut lines = vec![
+            PdfLine::heading(&self.merchant_name),
+            PdfLine::text(&format!(
+                "{} {}",
+                self.text(Text::InvoiceTitle),
+                self.invoice_number
+            )),
+            PdfLine::blank(),
+            PdfLine::text(&format!(
+                "{}: {}",
+                self.text(Text::InvoiceBilledTo),
+                self.customer_name
+            )),
+            PdfLine::text(&format!(
+                "{}: {}",
+                self.text(Text::InvoiceDueDate),
+                self.due_date
+            )),
+        ];
+
+        if let Some(title) = &self.title {
+            lines.push(PdfLine::text(title));
+        }
+
+        lines.push(PdfLine::blank());
+        lines.push(PdfLine::text(&format!(
+            "{:<36} {:>5} {:>17} {:>17}",
+            self.text(Text::InvoiceItem),
+            self.text(Text::InvoiceQuantity),
+            self.text(Text::InvoicePrice),
+            self.text(Text::InvoiceLineTotal)
+        )));
+        lines.push(PdfLine::text(&rule));
+
+        for line in &self.lines {
+            let description: String = line.description.chars().take(36).collect();
+
+            lines.push(PdfLine::text(&format!(
+                "{:<36} {:>5} {:>17} {:>17}",
+                description,
+                line.quantity,
+                locale.format_currency(line.price),
+                locale.format_currency(line.amount())
+            )));
+        }
+
+        lines.push(PdfLine::text(&rule));
+        lines.push(PdfLine::text(&column(Text::InvoiceSubtotal, self.subtotal)));
+        lines.push(PdfLine::text(&column(Text::InvoiceTax, self.tax)));
+        lines.push(PdfLine::text(&column(Text::InvoiceTotal, self.total)));
+        lines.push(PdfLine::blank());
+        lines.push(PdfLine::text(&format!(
+            "{}: {}",
+            self.text(Text::InvoicePayNow),
+            self.pay_url
+        )));
+
+        render_pdf(&lines)
+    }
+}
+
+struct PdfLine {
+    font_size: u32,
+    text: String,
+}
+
+impl PdfLine {
+    fn text(text: &str) -> Self {
+        Self {
+            font_size: 9,
+            text: text.to_string(),
+        }
+    }
+
+    fn heading(text: &str) -> Self {
+        Self {
+            font_size: 12,
+            text: text.to_string(),
+        }
+    }
+
+    fn blank() -> Self {
+        Self::text("")
+    }
+}
+
+/// A4 pages of Courier text in WinAnsiEncoding, which covers the Latin
+/// alphabets. Other characters are replaced since the built-in fonts have no
+/// Unicode mapping.
+fn render_pdf(lines: &[PdfLine]) -> Vec<u8> {
+    const LINES_PER_PAGE: usize = 56;
+
+    let pages: Vec<&[PdfLine]> = lines.chunks(LINES_PER_PAGE).collect();
+
+    // 1 catalog, 2 page tree, 3 font, then a page and its content per page
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 4 + i * 2))
+                .collect::<Vec<String>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = String::new();
+        let mut y = 800;
+
+        for line in page.iter() {
+            content.push_str(&format!(
+                "BT /F1 {} Tf 40 {} Td ({}) Tj ET\n",
+                line.font_size,
+                y,
+                escape_pdf(&line.text)
+            ));
+            y -= line.font_size + 5;
+        }
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + i * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+
+    pdf.into_bytes()
+}
+
+/// Escapes a PDF string literal, keeping offsets byte exact by writing ASCII
+/// only. Characters past ASCII are written as octal WinAnsiEncoding codes.
+fn escape_pdf(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => match win_ansi_code(c) {
+                Some(code) => escaped.push_str(&format!("\\{:03o}", code)),
+                None => escaped.push('?'),
+            },
+        }
+    }
+
+    escaped
+}
+
+/// Code of `c` in WinAnsiEncoding, Latin-1 plus the typographic characters
+/// Windows-1252 puts in 0x80 to 0x9F.
+fn win_ansi_code(c: char) -> Option<u8> {
+    let code = match c {
+        '\u{a0}'..='\u{ff}' => c as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8a,
+        '‹' => 0x8b,
+        'Œ' => 0x8c,
+        'Ž' => 0x8e,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9a,
+        '›' => 0x9b,
+        'œ' => 0x9c,
+        'ž' => 0x9e,
+        'Ÿ' => 0x9f,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::Document;
+
+    use super::*;
+
+    fn document(merchant_name: &str, lines: usize) -> InvoiceDocument {
+        InvoiceDocument {
+            locale: Locale::Id,
+            merchant_name: merchant_name.to_string(),
+            customer_name: "Budi (Santoso)".to_string(),
+            invoice_number: "INV-001".to_string(),
+            title: None,
+            due_date: "1 April 2023".to_string(),
+            pay_url: "https://checkout.xendit.co/web/1".to_string(),
+            lines: (0..lines)
+                .map(|i| InvoiceLine {
+                    description: format!("Item {}", i + 1),
+                    quantity: 2,
+                    price: 15000,
+                })
+                .collect(),
+            subtotal: 30000 * lines as i64,
+            tax: 0,
+            total: 30000 * lines as i64,
+        }
+    }
+
+    fn page_text(pdf: &[u8], page: u32) -> String {
+        let document = Document::load_mem(pdf).expect("pdf should parse");
+
+        document.extract_text(&[page]).expect("page should have text")
+    }
+
+    #[test]
+    fn pdf_parses_with_its_text() {
+        let pdf = document("Toko Maju", 1).pdf();
+        let text = page_text(&pdf, 1);
+
+        assert!(text.contains("Toko Maju"));
+        assert!(text.contains("INV-001"));
+        assert!(text.contains("Budi (Santoso)"));
+        assert!(text.contains("30.000"));
+    }
+
+    #[test]
+    fn pdf_keeps_latin_characters() {
+        let pdf = document("Café Müller – Señor", 1).pdf();
+
+        assert!(page_text(&pdf, 1).contains("Café Müller – Señor"));
+    }
+
+    #[test]
+    fn pdf_replaces_characters_outside_win_ansi() {
+        let pdf = document("東京 Store", 1).pdf();
+
+        assert!(page_text(&pdf, 1).contains("?? Store"));
+    }
+
+    #[test]
+    fn pdf_splits_long_invoices_into_pages() {
+        let pdf = document("Toko Maju", 80).pdf();
+        let pages = Document::load_mem(&pdf).expect("pdf should parse").get_pages();
+
+        assert_eq!(pages.len(), 2);
+        assert!(page_text(&pdf, 2).contains("Item 80"));
+    }
+
+    #[test]
+    fn escape_pdf_escapes_delimiters_and_encodes_latin() {
+        assert_eq!(escape_pdf(r"a (b) \c"), r"a \(b\) \\c");
+        assert_eq!(escape_pdf("é€"), r"\351\200");
+        assert_eq!(escape_pdf("東"), "?");
+    }
+}
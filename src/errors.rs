@@ -18,9 +18,11 @@ impl DefaultError {
     pub fn new(value: String, message: String) -> Self {
         Self { value, message }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!("{}: {}", self.value, self.message)
+impl std::fmt::Display for DefaultError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.value, self.message)
     }
 }
 
@@ -55,6 +57,12 @@ impl Errors {
     }
 }
 
+impl std::fmt::Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.errors)
+    }
+}
+
 impl IntoResponse for Errors {
     fn into_response(self) -> Response {
         use validator::ValidationErrorsKind::Field;
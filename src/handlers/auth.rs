@@ -4,11 +4,15 @@ use crate::models::requests::auth::{RequestLogin, RequestRegister};
 use crate::models::responses::DefaultResponse;
 use crate::models::tester::Tester;
 use crate::models::user::User;
+use crate::notifications::Notifier;
 
 use argon2::{self, Config};
 use argon2::{ThreadMode, Variant, Version};
 
+use std::sync::Arc;
+
 use axum::response::{IntoResponse, Response};
+use axum::Extension;
 use axum::{extract::State, response::Json};
 use crypto_hash::{hex_digest, Algorithm};
 use reqwest::StatusCode;
@@ -17,7 +21,11 @@ use sqlx::PgPool;
 
 use super::verification::{setup_verification};
 
-pub async fn register(State(db): State<PgPool>, Json(payload): Json<RequestRegister>) -> Response {
+pub async fn register(
+    State(db): State<PgPool>,
+    Extension(notifier): Extension<Arc<Notifier>>,
+    Json(payload): Json<RequestRegister>,
+) -> Response {
     let mut extractor = FieldValidator::validate(&payload);
 
     let name = extractor.extract("name", Some(payload.name));
@@ -64,7 +72,9 @@ pub async fn register(State(db): State<PgPool>, Json(payload): Json<RequestRegis
         }
     };
 
-    match setup_verification(&db, Some(user.id), None, "email".to_string(), email).await {
+    match setup_verification(&db, &notifier, Some(user.id), None, "email".to_string(), email)
+        .await
+    {
         Ok(_) => (),
         Err(err) => {
             let body =
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::extract::{Path, Query};
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
@@ -7,15 +9,20 @@ use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::contacts;
 use crate::errors::Errors;
 use crate::models::contact_channel::ContactChannel;
+use crate::models::conversation_message::ConversationMessage;
 use crate::models::customer::Customer;
 use crate::models::customer_contact_channel::CustomerContactChannel;
+use crate::models::job_run::JobRun;
 use crate::models::job_schedule::JobSchedule;
+use crate::models::merchant::Merchant;
 use crate::models::requests::customer::{
     RequestCreateCustomer, RequestGetCustomers, RequestUpdateCustomer,
 };
 use crate::models::responses::DefaultResponse;
+use crate::notifications::Notifier;
 
 use super::verification::setup_verification;
 
@@ -75,6 +82,7 @@ pub async fn get_by_merchant_id(
 pub async fn create(
     State(db): State<PgPool>,
     Extension(_): Extension<Uuid>,
+    Extension(notifier): Extension<Arc<Notifier>>,
     Path((merchant_id,)): Path<(Uuid,)>,
     Json(body): Json<RequestCreateCustomer>,
 ) -> Response {
@@ -94,11 +102,66 @@ pub async fn create(
             }
         };
 
+    let contact_channel = match ContactChannel::get_by_id(&db, &contact_channel_id).await {
+        Ok(contact_channel) => contact_channel,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let contact_value = match normalize_contact_value(
+        &db,
+        &merchant_id,
+        &contact_channel.name,
+        &contact_channel_value,
+    )
+    .await
+    {
+        Ok(contact_value) => contact_value,
+        Err(response) => return response,
+    };
+
+    match CustomerContactChannel::value_exists(
+        &db,
+        &merchant_id,
+        &contact_channel_id,
+        &contact_value,
+        None,
+    )
+    .await
+    {
+        Ok(false) => (),
+        Ok(true) => {
+            let body = DefaultResponse::error(
+                "contact channel value is already used by another customer",
+                contact_value,
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+        Err(err) => {
+            let body =
+                DefaultResponse::error("create customer failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
     let mut db_transaction = db.begin().await.expect("Failed to begin transaction");
 
     let customer =
-        match Customer::create_using_transaction(&mut db_transaction, &name, &tags, &merchant_id)
-            .await
+        match Customer::create_using_transaction(
+            &mut db_transaction,
+            &name,
+            &tags,
+            &merchant_id,
+            body.locale,
+        )
+        .await
         {
             Ok(customer) => customer,
             Err(err) => {
@@ -114,25 +177,6 @@ pub async fn create(
             }
         };
 
-    // remove + in +62 from phone number
-    let contact_value = contact_channel_value.replace("+", "");
-
-    // replace first 0 with 62 if phone number start with 0
-    let contact_value: String = if contact_value.starts_with("0") {
-        let mut phone = contact_value.clone();
-        phone.replace_range(0..1, "62");
-        phone
-    } else {
-        contact_value
-    };
-
-    // replace first @ with empty
-    let contact_value: String = if contact_value.starts_with("@") {
-        contact_channel_value.replace("@", "")
-    } else {
-        contact_value
-    };
-
     match CustomerContactChannel::create_using_transaction(
         &mut db_transaction,
         &customer.id,
@@ -160,20 +204,10 @@ pub async fn create(
         .await
         .expect("Failed to commit transaction");
 
-    let contact_channel = match ContactChannel::get_by_id(&db, &contact_channel_id).await {
-        Ok(contact_channel) => contact_channel,
-        Err(_) => {
-            let body = DefaultResponse::ok("create customer success")
-                .with_data(json!(customer))
-                .into_json();
-
-            return (StatusCode::CREATED, body).into_response();
-        }
-    };
-
     if contact_channel.name != "telegram" {
         match setup_verification(
             &db,
+            &notifier,
             None,
             Some(customer.id),
             contact_channel.name,
@@ -198,6 +232,30 @@ pub async fn create(
     (StatusCode::CREATED, body).into_response()
 }
 
+/// `contacts::normalize` with the merchant's calling code, an invalid value
+/// is answered with the response to return.
+pub async fn normalize_contact_value(
+    db: &PgPool,
+    merchant_id: &Uuid,
+    contact_channel_name: &str,
+    contact_channel_value: &str,
+) -> Result<String, Response> {
+    let country_code = match Merchant::get_by_id(db, *merchant_id).await {
+        Ok(merchant) => contacts::country_code(&merchant),
+        Err(_) => contacts::DEFAULT_COUNTRY_CODE.to_string(),
+    };
+
+    match contacts::normalize(contact_channel_name, contact_channel_value, &country_code) {
+        Ok(contact_value) => Ok(contact_value),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.as_str(), contact_channel_value.to_string()).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
+
 pub async fn update(
     State(db): State<PgPool>,
     Extension(_): Extension<Uuid>,
@@ -213,7 +271,16 @@ pub async fn update(
         }
     };
 
-    let customer = match Customer::update(&db, &customer_id, &name, &tags, &merchant_id).await {
+    let customer = match Customer::update(
+        &db,
+        &customer_id,
+        &name,
+        &tags,
+        &merchant_id,
+        body.locale,
+    )
+    .await
+    {
         Ok(customer) => customer,
         Err(err) => {
             let body =
@@ -343,3 +410,57 @@ pub async fn get_tags_by_merchant_id(
 
     (StatusCode::OK, body).into_response()
 }
+
+/// Job runs of the customer with the result of every channel they were sent to.
+pub async fn get_history(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    let job_runs = match JobRun::get_by_customer_id(&db, &merchant_id, &customer_id).await {
+        Ok(job_runs) => job_runs,
+        Err(err) => {
+            let body = DefaultResponse::error("get job runs failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let history = match JobRun::with_customer_deliveries(&db, job_runs, &customer_id).await {
+        Ok(history) => history,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get deliveries failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get customer history success")
+        .with_data(json!(history))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// WhatsApp messages from and to the customer, oldest first.
+pub async fn get_conversation(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    let conversation_messages =
+        match ConversationMessage::get_by_customer_id(&db, &merchant_id, &customer_id).await {
+            Ok(conversation_messages) => conversation_messages,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("get conversation failed", err.to_string()).into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("get customer conversation success")
+        .with_data(json!(conversation_messages))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
@@ -0,0 +1,444 @@
+use std::sync::Arc;
+
+use axum::extract::Path;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use axum::{extract::State, response::Json};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Errors;
+use crate::models::contact_channel::ContactChannel;
+use crate::models::customer::Customer;
+use crate::models::customer_contact_channel::CustomerContactChannel;
+use crate::models::requests::customer::RequestCustomerContactChannel;
+use crate::models::responses::DefaultResponse;
+use crate::notifications::Notifier;
+
+use super::customer::normalize_contact_value;
+use super::verification::setup_verification;
+
+pub async fn get_channels(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    let customer_contact_channels =
+        match CustomerContactChannel::get_customer_contact_channels_by_customer_and_merchant(
+            &db,
+            &customer_id,
+            &merchant_id,
+        )
+        .await
+        {
+            Ok(customer_contact_channels) => customer_contact_channels,
+            Err(err) => {
+                let body = DefaultResponse::error("get contact channels failed", err.to_string())
+                    .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("get customer contact channels success")
+        .with_data(json!(customer_contact_channels))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Adds a channel to the customer, the customer is asked to verify it unless
+/// it is Telegram, which is verified when the customer connects in the chat.
+pub async fn create_channel(
+    State(db): State<PgPool>,
+    Extension(notifier): Extension<Arc<Notifier>>,
+    Path((merchant_id, customer_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<RequestCustomerContactChannel>,
+) -> Response {
+    let (contact_channel_id, contact_channel_value) = match validate_channel(&body) {
+        Ok(channel) => channel,
+        Err(response) => return response,
+    };
+
+    if let Err(err) = Customer::get_by_id(&db, customer_id, &merchant_id).await {
+        let body = DefaultResponse::error("customer not found", err.to_string()).into_json();
+
+        return (StatusCode::NOT_FOUND, body).into_response();
+    }
+
+    let contact_channel = match ContactChannel::get_by_id(&db, &contact_channel_id).await {
+        Ok(contact_channel) => contact_channel,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let contact_value = match normalize_contact_value(
+        &db,
+        &merchant_id,
+        &contact_channel.name,
+        &contact_channel_value,
+    )
+    .await
+    {
+        Ok(contact_value) => contact_value,
+        Err(response) => return response,
+    };
+
+    if let Err(response) =
+        check_duplicate(&db, &merchant_id, &contact_channel_id, &contact_value, None).await
+    {
+        return response;
+    }
+
+    let customer_contact_channel = match CustomerContactChannel::create(
+        &db,
+        &customer_id,
+        &contact_channel_id,
+        &contact_value,
+        None,
+    )
+    .await
+    {
+        Ok(customer_contact_channel) => customer_contact_channel,
+        Err(err) => {
+            let body = DefaultResponse::error("create contact channel failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let customer_contact_channel = if body.is_primary.unwrap_or(false) {
+        match CustomerContactChannel::set_primary(&db, &customer_contact_channel.id, &customer_id)
+            .await
+        {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("set primary contact channel failed", err.to_string())
+                        .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        }
+    } else {
+        customer_contact_channel
+    };
+
+    if let Err(response) =
+        verify_channel(&db, &notifier, &customer_id, contact_channel, contact_value).await
+    {
+        return response;
+    }
+
+    let body = DefaultResponse::ok("create contact channel success")
+        .with_data(json!(customer_contact_channel))
+        .into_json();
+
+    (StatusCode::CREATED, body).into_response()
+}
+
+/// Changes the channel, a new value is verified again like an added channel
+/// and loses the Telegram chat linked to the old one.
+pub async fn update_channel(
+    State(db): State<PgPool>,
+    Extension(notifier): Extension<Arc<Notifier>>,
+    Path((merchant_id, customer_id, customer_contact_channel_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(body): Json<RequestCustomerContactChannel>,
+) -> Response {
+    let (contact_channel_id, contact_channel_value) = match validate_channel(&body) {
+        Ok(channel) => channel,
+        Err(response) => return response,
+    };
+
+    let customer_contact_channel = match CustomerContactChannel::get_by_id_and_customer_id(
+        &db,
+        &customer_contact_channel_id,
+        &customer_id,
+        &merchant_id,
+    )
+    .await
+    {
+        Ok(customer_contact_channel) => customer_contact_channel,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+            return (StatusCode::NOT_FOUND, body).into_response();
+        }
+    };
+
+    let contact_channel = match ContactChannel::get_by_id(&db, &contact_channel_id).await {
+        Ok(contact_channel) => contact_channel,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let contact_value = match normalize_contact_value(
+        &db,
+        &merchant_id,
+        &contact_channel.name,
+        &contact_channel_value,
+    )
+    .await
+    {
+        Ok(contact_value) => contact_value,
+        Err(response) => return response,
+    };
+    let is_changed = customer_contact_channel.contact_channel_id != contact_channel_id
+        || customer_contact_channel.value != contact_value;
+
+    let customer_contact_channel = if is_changed {
+        if let Err(response) = check_duplicate(
+            &db,
+            &merchant_id,
+            &contact_channel_id,
+            &contact_value,
+            Some(customer_contact_channel.id),
+        )
+        .await
+        {
+            return response;
+        }
+
+        match CustomerContactChannel::update(
+            &db,
+            &customer_contact_channel.id,
+            &contact_channel_id,
+            &contact_value,
+            None,
+        )
+        .await
+        {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(err) => {
+                let body = DefaultResponse::error("update contact channel failed", err.to_string())
+                    .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        }
+    } else {
+        customer_contact_channel
+    };
+
+    let customer_contact_channel = if body.is_primary.unwrap_or(false) {
+        match CustomerContactChannel::set_primary(&db, &customer_contact_channel.id, &customer_id)
+            .await
+        {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("set primary contact channel failed", err.to_string())
+                        .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        }
+    } else {
+        customer_contact_channel
+    };
+
+    if is_changed {
+        if let Err(response) =
+            verify_channel(&db, &notifier, &customer_id, contact_channel, contact_value).await
+        {
+            return response;
+        }
+    }
+
+    let body = DefaultResponse::ok("update contact channel success")
+        .with_data(json!(customer_contact_channel))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Removes the channel, customers keep at least one.
+pub async fn delete_channel(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id, customer_contact_channel_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Response {
+    let customer_contact_channel = match CustomerContactChannel::get_by_id_and_customer_id(
+        &db,
+        &customer_contact_channel_id,
+        &customer_id,
+        &merchant_id,
+    )
+    .await
+    {
+        Ok(customer_contact_channel) => customer_contact_channel,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+            return (StatusCode::NOT_FOUND, body).into_response();
+        }
+    };
+
+    match CustomerContactChannel::count_by_customer_id(&db, &customer_id).await {
+        Ok(count) if count > 1 => (),
+        Ok(_) => {
+            let body = DefaultResponse::error(
+                "delete contact channel failed, customers need at least one contact channel",
+                "".to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+        Err(err) => {
+            let body = DefaultResponse::error("delete contact channel failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let customer_contact_channel =
+        match CustomerContactChannel::delete(&db, &customer_contact_channel.id, &customer_id).await
+        {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(err) => {
+                let body = DefaultResponse::error("delete contact channel failed", err.to_string())
+                    .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("delete contact channel success")
+        .with_data(json!(customer_contact_channel))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn set_primary_channel(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id, customer_contact_channel_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Response {
+    if let Err(err) = CustomerContactChannel::get_by_id_and_customer_id(
+        &db,
+        &customer_contact_channel_id,
+        &customer_id,
+        &merchant_id,
+    )
+    .await
+    {
+        let body = DefaultResponse::error("contact channel not found", err.to_string()).into_json();
+
+        return (StatusCode::NOT_FOUND, body).into_response();
+    }
+
+    let customer_contact_channel =
+        match CustomerContactChannel::set_primary(&db, &customer_contact_channel_id, &customer_id)
+            .await
+        {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("set primary contact channel failed", err.to_string())
+                        .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("set primary contact channel success")
+        .with_data(json!(customer_contact_channel))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+fn validate_channel(body: &RequestCustomerContactChannel) -> Result<(Uuid, String), Response> {
+    match validator::Validate::validate(body) {
+        Ok(_) => Ok((
+            body.contact_channel_id.unwrap(),
+            body.contact_channel_value.clone().unwrap(),
+        )),
+        Err(err) => {
+            let value = Errors::into_string(err);
+
+            let body = DefaultResponse::error(value.as_str(), "".to_string()).into_json();
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
+
+/// Rejects a value another customer of the merchant is already reached at.
+async fn check_duplicate(
+    db: &PgPool,
+    merchant_id: &Uuid,
+    contact_channel_id: &Uuid,
+    contact_value: &str,
+    except_id: Option<Uuid>,
+) -> Result<(), Response> {
+    match CustomerContactChannel::value_exists(
+        db,
+        merchant_id,
+        contact_channel_id,
+        contact_value,
+        except_id,
+    )
+    .await
+    {
+        Ok(false) => Ok(()),
+        Ok(true) => {
+            let body = DefaultResponse::error(
+                "contact channel value is already used by another customer",
+                contact_value.to_string(),
+            )
+            .into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+        Err(err) => {
+            let body =
+                DefaultResponse::error("check contact channel failed", err.to_string()).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
+
+async fn verify_channel(
+    db: &PgPool,
+    notifier: &Notifier,
+    customer_id: &Uuid,
+    contact_channel: ContactChannel,
+    contact_value: String,
+) -> Result<(), Response> {
+    if contact_channel.name == "telegram" {
+        return Ok(());
+    }
+
+    match setup_verification(
+        db,
+        notifier,
+        None,
+        Some(*customer_id),
+        contact_channel.name,
+        contact_value,
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("setup verification failed", err.to_string()).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
@@ -1,17 +1,20 @@
-use std::ops::Add;
-
 use crate::models::customer::Customer;
 use crate::models::invoice::Invoice;
 use crate::models::item::Item;
 use crate::models::job_queue::JobQueue;
+use crate::models::job_run::JobRun;
 use crate::models::job_schedule::JobSchedule;
 use crate::models::merchant::Merchant;
 use crate::models::requests::invoice::{RequestAddInvoiceItem, RequestCreateInvoice};
 use crate::models::requests::invoice_schedule::{
     RequestInvoiceSchedule, RequestSetStatusInvoiceSchedule,
 };
+use crate::handlers::job_schedule::{preview_invoice_recipient, preview_timing};
 use crate::models::responses::DefaultResponse;
 use crate::repositories::invoice::send_invoice_to_xendit;
+use crate::utils::schedule::ScheduleTiming;
+use crate::utils::timezone;
+use crate::webhooks;
 use axum::extract::Path;
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
@@ -72,6 +75,15 @@ pub async fn create(
     let tax_amount = body.amount * tax_rate / 100;
     let total_amount = body.amount + tax_amount;
 
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
     let now = chrono::Utc::now().naive_utc();
 
     let invoice_number =
@@ -115,7 +127,10 @@ pub async fn create(
         &total_amount,
         &tax_amount,
         &tax_rate,
-        &body.invoice_date.expect("invoice date is required"),
+        &timezone::local_to_utc(
+            &body.invoice_date.expect("invoice date is required"),
+            &merchant.tz(),
+        ),
         &user_id,
         body.title.as_deref(),
         body.description.as_deref(),
@@ -139,6 +154,8 @@ pub async fn create(
         }
     };
 
+    webhooks::emit(&db, &merchant_id, "invoice.created", json!(invoice)).await;
+
     let body = DefaultResponse::created("create invoice success")
         .with_data(json!(invoice))
         .into_json();
@@ -207,7 +224,7 @@ pub async fn set_invoice_status(
 pub async fn set_invoice_scheduler(
     State(db): State<PgPool>,
     Extension(user_id): Extension<Uuid>,
-    Path((_, invoice_id)): Path<(Uuid, Uuid)>,
+    Path((merchant_id, invoice_id)): Path<(Uuid, Uuid)>,
     Json(body): Json<RequestInvoiceSchedule>,
 ) -> Response {
     match validator::Validate::validate(&body) {
@@ -219,16 +236,6 @@ pub async fn set_invoice_scheduler(
         }
     }
 
-    if body.is_recurring && (body.start_at.is_none() || body.end_at.is_none()) {
-        let body = DefaultResponse::error(
-            "start_at and end_at is required for recurring invoice",
-            invoice_id.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
     match JobSchedule::get_by_job_data_json_by_invoice_id(&db, invoice_id.to_string().as_str())
         .await
     {
@@ -243,91 +250,28 @@ pub async fn set_invoice_scheduler(
         Err(_) => (),
     };
 
-    let now = chrono::Utc::now();
-
-    let start_at = if !body.is_recurring {
-        now.add(chrono::Duration::seconds(5)).naive_utc()
-    } else {
-        body.start_at.unwrap()
-    };
-
-    let end_at = if !body.is_recurring {
-        now.add(chrono::Duration::seconds(10)).naive_utc()
-    } else {
-        body.end_at.unwrap()
-    };
-
-    if end_at < start_at {
-        let body = DefaultResponse::error(
-            "end_at must be greater than start_at",
-            invoice_id.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if start_at < now.naive_utc() {
-        let body = DefaultResponse::error(
-            format!("start_at must be greater than current time ( {} )", now).as_str(),
-            invoice_id.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if body.is_recurring && end_at - start_at < chrono::Duration::days(5) {
-        let body =
-            DefaultResponse::error("duration must be more than 5 days", invoice_id.to_string())
-                .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if body.is_recurring && body.repeat_interval_type.is_none() {
-        let body = DefaultResponse::error(
-            "repeat_interval_type is required for recurring invoice",
-            invoice_id.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
 
-    let repeat_interval_type = match body.repeat_interval_type {
-        Some(repeat_interval_type) => repeat_interval_type,
-        None => "ONCE".to_string(),
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
     };
 
-    let repeat_interval = if repeat_interval_type == "ONCE" {
-        let duration = 5;
-        duration
-    } else if repeat_interval_type == "PERMINUTE" {
-        let duration = chrono::Duration::minutes(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "HOURLY" {
-        let duration = chrono::Duration::hours(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "DAILY" {
-        let duration = chrono::Duration::days(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "WEEKLY" {
-        let duration = chrono::Duration::weeks(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "MONTHLY" {
-        let duration = chrono::Duration::weeks(4).num_seconds();
-        duration
-    } else {
-        let duration = chrono::Duration::weeks(1).num_seconds();
-        duration
-    };
+    let timing = match ScheduleTiming::compute(
+        body.is_recurring,
+        body.repeat_interval_type.as_deref(),
+        body.start_at,
+        body.end_at,
+        &merchant.tz(),
+    ) {
+        Ok(timing) => timing,
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), invoice_id.to_string()).into_json();
 
-    // repeat count based on start and end date and repeat interval
-    let repeat_count = if !body.is_recurring {
-        0
-    } else {
-        (end_at - start_at).num_seconds() / repeat_interval
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
     };
 
     let invoice = match Invoice::get_by_id(&db, &invoice_id).await {
@@ -348,15 +292,6 @@ pub async fn set_invoice_scheduler(
         }
     };
 
-    let merchant = match Merchant::get_by_id(&db, invoice.merchant_id).await {
-        Ok(merchant) => merchant,
-        Err(err) => {
-            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
-
-            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-        }
-    };
-
     let job_schedule = match JobSchedule::create(
         &db,
         "send_invoice",
@@ -375,15 +310,17 @@ pub async fn set_invoice_scheduler(
             "tax_rate": invoice.tax_rate,
             "invoice_date": invoice.invoice_date,
             "created_by": user_id,
+            "attach_pdf": body.attach_pdf.unwrap_or(false),
         })),
-        &start_at,
-        Some(repeat_interval),
-        repeat_count.to_i32(),
-        repeat_count.to_i32(),
+        &timing.start_at,
+        Some(timing.repeat_interval),
+        timing.repeat_count.to_i32(),
+        timing.repeat_count.to_i32(),
         None,
         "scheduled",
         None,
         None,
+        timing.end_at,
     )
     .await
     {
@@ -511,3 +448,92 @@ pub async fn delete_item_to_invoice(
 
     (StatusCode::OK, body).into_response()
 }
+
+/// Dry run of `set_invoice_scheduler`: validates the body and returns what
+/// would be scheduled without persisting anything.
+pub async fn preview_invoice_scheduler(
+    State(db): State<PgPool>,
+    Path((merchant_id, invoice_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<RequestInvoiceSchedule>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+    let tz = merchant.tz();
+
+    let timing = match ScheduleTiming::compute(
+        body.is_recurring,
+        body.repeat_interval_type.as_deref(),
+        body.start_at,
+        body.end_at,
+        &tz,
+    ) {
+        Ok(timing) => timing,
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), invoice_id.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let customer = match preview_invoice_recipient(&db, &invoice_id, &merchant, &timing).await {
+        Ok(customer) => customer,
+        Err(response) => return response,
+    };
+
+    let mut data = preview_timing(&timing, &tz);
+    data["job_type"] = json!("send_invoice");
+    data["attach_pdf"] = json!(body.attach_pdf.unwrap_or(false));
+    data["customers"] = json!([customer]);
+
+    let body = DefaultResponse::ok("preview invoice scheduler success")
+        .with_data(data)
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Job runs of the invoice with the result of every channel they were sent to.
+pub async fn get_history(
+    State(db): State<PgPool>,
+    Path((merchant_id, invoice_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    let job_runs = match JobRun::get_by_invoice_id(&db, &merchant_id, &invoice_id).await {
+        Ok(job_runs) => job_runs,
+        Err(err) => {
+            let body = DefaultResponse::error("get job runs failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let history = match JobRun::with_deliveries(&db, job_runs).await {
+        Ok(history) => history,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get deliveries failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get invoice history success")
+        .with_data(json!(history))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
@@ -1,15 +1,29 @@
 use std::ops::Add;
 
+use crate::jobs::handlers::send_invoice::{
+    default_invoice_notification, invoice_values, PAYMENT_DUE_HOURS,
+};
+use crate::jobs::handlers::send_reminder::message_builder_reminder;
+use crate::jobs::payloads::{SendInvoicePayload, SendReminderPayload};
 use crate::models::customer::Customer;
+use crate::models::customer_contact_channel::CustomerContactChannel;
 use crate::models::invoice::Invoice;
-use crate::models::job_schedule::JobSchedule;
+use crate::models::item::Item;
+use crate::models::job_deferral::JobDeferral;
+use crate::models::job_queue::JobQueue;
+use crate::models::job_run::JobRun;
+use crate::models::job_schedule::{repeat_interval_seconds, JobSchedule};
 use crate::models::merchant::Merchant;
-use crate::models::requests::job_scheduler::RequestSchedule;
+use crate::models::requests::job_scheduler::{RequestSchedule, RequestUpdateSchedule};
 use crate::models::responses::DefaultResponse;
+use crate::templates::ChannelMessages;
+use crate::utils::schedule::ScheduleTiming;
+use crate::utils::timezone;
 use axum::extract::Path;
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
 use axum::{extract::State, response::Json};
+use chrono_tz::Tz;
 use reqwest::StatusCode;
 use rust_decimal::prelude::ToPrimitive;
 use serde_json::json;
@@ -31,108 +45,48 @@ pub async fn set_scheduler(
         }
     }
 
-    if body.is_recurring && (body.start_at.is_none() || body.end_at.is_none()) {
-        let body = DefaultResponse::error(
-            "start_at and end_at is required for recurring job",
-            body.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    let now = chrono::Utc::now();
-
-    let start_at = if !body.is_recurring {
-        now.add(chrono::Duration::seconds(5)).naive_utc()
-    } else {
-        body.start_at.unwrap()
-    };
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
 
-    let end_at = if !body.is_recurring {
-        now.add(chrono::Duration::seconds(10)).naive_utc()
-    } else {
-        body.end_at.unwrap()
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
     };
 
-    if end_at < start_at {
-        let body = DefaultResponse::error(
-            "end_at must be greater than start_at",
-            body.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if start_at < now.naive_utc() {
-        let body = DefaultResponse::error(
-            format!("start_at must be greater than current time ( {} )", now).as_str(),
-            body.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if body.is_recurring && end_at - start_at < chrono::Duration::days(5) {
-        let body = DefaultResponse::error(
-            "duration must be more than 5 days",
-            body.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
-
-    if body.is_recurring && body.repeat_interval_type.is_none() {
-        let body = DefaultResponse::error(
-            "repeat_interval_type is required for recurring job",
-            body.to_string(),
-        )
-        .into_json();
-
-        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-    }
+    let timing = match ScheduleTiming::compute(
+        body.is_recurring,
+        body.repeat_interval_type.as_deref(),
+        body.start_at,
+        body.end_at,
+        &merchant.tz(),
+    ) {
+        Ok(timing) => timing,
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), body.to_string()).into_json();
 
-    let repeat_interval_type = match &body.repeat_interval_type {
-        Some(repeat_interval_type) => repeat_interval_type,
-        None => "ONCE",
-    };
-
-    let repeat_interval = if repeat_interval_type == "ONCE" {
-        let duration = 5;
-        duration
-    } else if repeat_interval_type == "PERMINUTE" {
-        let duration = chrono::Duration::minutes(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "HOURLY" {
-        let duration = chrono::Duration::hours(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "DAILY" {
-        let duration = chrono::Duration::days(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "WEEKLY" {
-        let duration = chrono::Duration::weeks(1).num_seconds();
-        duration
-    } else if repeat_interval_type == "MONTHLY" {
-        let duration = chrono::Duration::weeks(4).num_seconds();
-        duration
-    } else {
-        let duration = chrono::Duration::weeks(1).num_seconds();
-        duration
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
     };
 
-    // repeat count based on start and end date and repeat interval
-    let repeat_count = if !body.is_recurring {
-        0
-    } else {
-        (end_at - start_at).num_seconds() / repeat_interval
-    };
+    let start_at = timing.start_at;
+    let repeat_interval = timing.repeat_interval;
+    let repeat_count = timing.repeat_count;
+    let recurring_end_at = timing.end_at;
 
     let mut job_schedule: Option<JobSchedule> = None;
 
     if body.job_type == "send_invoice" {
+        if body.external_id.is_none() {
+            let body = DefaultResponse::error(
+                "external_id ( invoice id ) is required for send_invoice job",
+                body.to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+
         job_schedule = match set_invoice_job_schedule(
             &db,
             &user_id,
@@ -140,6 +94,7 @@ pub async fn set_scheduler(
             &start_at,
             &repeat_interval,
             &repeat_count,
+            recurring_end_at.as_ref(),
         )
         .await
         {
@@ -152,90 +107,56 @@ pub async fn set_scheduler(
             }
         };
     } else if body.job_type == "send_reminder" {
-
-        if body.title.is_none() || body.title.as_ref().unwrap().is_empty() {
-            let body = DefaultResponse::error(
-                "title is required for send_reminder job",
-                body.to_string()
-            )
-            .into_json();
-
-            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-        } 
-
-        if body.description.is_none() || body.description.as_ref().unwrap().is_empty() {
-            let body = DefaultResponse::error(
-                "description is required for send_reminder job",
-                body.to_string(),
-            )
-            .into_json();
-
-            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        if let Err(response) = validate_reminder_body(&body) {
+            return response;
         }
 
-        let external_ids = match body.external_id {
-            Some(external_id) => Vec::from([external_id]),
-            None => {
-
-                if body.tag.is_none() || body.tag.as_ref().unwrap().is_empty() {
-                    let body = DefaultResponse::error(
-                        "tag is required for send_reminder job",
-                        body.to_string(),
-                    )
-                    .into_json();
-
-                    return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-                }
-
-                let mut tags = Vec::new();
-                tags.push(body.tag.unwrap());
-
-                let customers = match Customer::get_by_merchant_id_tags(&db, &merchant_id, &tags).await {
-                    Ok(customers) => customers,
-                    Err(err) => {
-                        let body = DefaultResponse::error("get customers by tags failed", err.to_string())
-                            .into_json();
-
-                        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-                    }
-                };
-
-                let mut external_ids = Vec::new();
-                for customer in customers {
-                    external_ids.push(customer.id);
-                }
+        let title = body.title.clone().unwrap();
+        let description = body.description.clone().unwrap();
 
-                external_ids
-                
-            } 
+        let result = match (body.external_id, &body.tag) {
+            (Some(external_id), _) => {
+                set_reminder_job_schedule(
+                    &db,
+                    &user_id,
+                    &merchant_id,
+                    &external_id,
+                    &start_at,
+                    &repeat_interval,
+                    &repeat_count,
+                    recurring_end_at.as_ref(),
+                    &title,
+                    &description,
+                )
+                .await
+            }
+            // the audience is resolved from the tag every time the job runs
+            (None, tag) => {
+                set_tag_reminder_job_schedule(
+                    &db,
+                    &user_id,
+                    &merchant_id,
+                    tag.as_ref().unwrap(),
+                    &start_at,
+                    &repeat_interval,
+                    &repeat_count,
+                    recurring_end_at.as_ref(),
+                    &title,
+                    &description,
+                )
+                .await
+            }
         };
 
-        let title = body.title.unwrap();
-        let description = body.description.unwrap();
+        job_schedule = match result {
+            Ok(job_schedule) => Some(job_schedule),
+            Err(err) => {
+                let body = DefaultResponse::error("set reminder scheduler failed", err.to_string())
+                    .into_json();
 
-        for external_id in external_ids {
-            job_schedule = match set_reminder_job_schedule(
-                &db,
-                &user_id,
-                &merchant_id,
-                &external_id,
-                &start_at,
-                &repeat_interval,
-                &repeat_count,
-                &title,
-                &description,
-            )
-            .await
-            {
-                Ok(job_schedule) => Some(job_schedule),
-                Err(err) => {
-                    let body = DefaultResponse::error("set reminder scheduler failed", err.to_string())
-                        .into_json();
-    
-                    return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
-                }
-            };
-        }
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
     } else {
         let body =
             DefaultResponse::error("job_type is not supported", body.to_string())
@@ -251,6 +172,64 @@ pub async fn set_scheduler(
     (StatusCode::OK, body).into_response()
 }
 
+fn validate_reminder_body(body: &RequestSchedule) -> Result<(), Response> {
+    if body.title.is_none() || body.title.as_ref().unwrap().is_empty() {
+        let body =
+            DefaultResponse::error("title is required for send_reminder job", body.to_string())
+                .into_json();
+
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+    }
+
+    if body.description.is_none() || body.description.as_ref().unwrap().is_empty() {
+        let body = DefaultResponse::error(
+            "description is required for send_reminder job",
+            body.to_string(),
+        )
+        .into_json();
+
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+    }
+
+    if body.external_id.is_none() && body.tag.as_ref().map_or(true, |tag| tag.is_empty()) {
+        let body = DefaultResponse::error(
+            "external_id ( customer id ) or tag is required for send_reminder job",
+            body.to_string(),
+        )
+        .into_json();
+
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+    }
+
+    Ok(())
+}
+
+/// Validates a `send_reminder` body and returns the customers it targets right
+/// now, either the single `external_id` or every customer tagged with `tag`.
+async fn reminder_customer_ids(
+    db: &PgPool,
+    merchant_id: &Uuid,
+    body: &RequestSchedule,
+) -> Result<Vec<Uuid>, Response> {
+    validate_reminder_body(body)?;
+
+    if let Some(external_id) = body.external_id {
+        return Ok(Vec::from([external_id]));
+    }
+
+    let tags = vec![body.tag.clone().unwrap_or_default()];
+
+    match Customer::get_distinct_by_merchant_id_tags(&db, &merchant_id, &tags).await {
+        Ok(customers) => Ok(customers.into_iter().map(|customer| customer.id).collect()),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get customers by tags failed", err.to_string()).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
+
 async fn set_invoice_job_schedule(
     db: &sqlx::PgPool,
     user_id: &Uuid,
@@ -258,6 +237,7 @@ async fn set_invoice_job_schedule(
     start_at: &chrono::NaiveDateTime,
     repeat_interval: &i64,
     repeat_count: &i64,
+    end_at: Option<&chrono::NaiveDateTime>,
 ) -> Result<JobSchedule, Json<serde_json::Value>> {
     let invoice = match Invoice::get_by_id(&db, &external_id).await {
         Ok(invoice) => invoice,
@@ -307,6 +287,7 @@ async fn set_invoice_job_schedule(
         "scheduled",
         None,
         None,
+        end_at.copied(),
     )
     .await
     {
@@ -327,6 +308,7 @@ async fn set_reminder_job_schedule(
     start_at: &chrono::NaiveDateTime,
     repeat_interval: &i64,
     repeat_count: &i64,
+    end_at: Option<&chrono::NaiveDateTime>,
     title: &str,
     description: &str,
 ) -> Result<JobSchedule, Json<serde_json::Value>> {
@@ -364,6 +346,58 @@ async fn set_reminder_job_schedule(
         "scheduled",
         None,
         None,
+        end_at.copied(),
+    )
+    .await
+    {
+        Ok(job_schedule) => Ok(job_schedule),
+        Err(err) => {
+            return Err(
+                DefaultResponse::error("create job schedule failed", err.to_string()).into_json(),
+            )
+        }
+    }
+}
+
+async fn set_tag_reminder_job_schedule(
+    db: &sqlx::PgPool,
+    user_id: &Uuid,
+    merchant_id: &Uuid,
+    tag: &str,
+    start_at: &chrono::NaiveDateTime,
+    repeat_interval: &i64,
+    repeat_count: &i64,
+    end_at: Option<&chrono::NaiveDateTime>,
+    title: &str,
+    description: &str,
+) -> Result<JobSchedule, Json<serde_json::Value>> {
+    let merchant = match Merchant::get_by_id(&db, *merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            return Err(DefaultResponse::error("get merchant failed", err.to_string()).into_json())
+        }
+    };
+
+    match JobSchedule::create(
+        &db,
+        "send_reminder_by_tag",
+        Some(json!({
+            "title": title,
+            "description": description,
+            "tag": tag,
+            "merchant_id": merchant_id,
+            "merchant_name": merchant.name,
+            "created_by": user_id,
+        })),
+        &start_at,
+        Some(*repeat_interval),
+        repeat_count.to_i32(),
+        repeat_count.to_i32(),
+        None,
+        "scheduled",
+        None,
+        None,
+        end_at.copied(),
     )
     .await
     {
@@ -375,3 +409,785 @@ async fn set_reminder_job_schedule(
         }
     }
 }
+
+async fn get_merchant_schedule(
+    db: &PgPool,
+    merchant_id: &Uuid,
+    schedule_id: i32,
+) -> Result<JobSchedule, Response> {
+    match JobSchedule::get_by_id_and_merchant_id(&db, schedule_id, &merchant_id.to_string()).await
+    {
+        Ok(job_schedule) => Ok(job_schedule),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("job schedule not found", err.to_string()).into_json();
+
+            Err((StatusCode::NOT_FOUND, body).into_response())
+        }
+    }
+}
+
+fn statuses(statuses: &[&str]) -> Vec<String> {
+    statuses.iter().map(|status| status.to_string()).collect()
+}
+
+pub async fn get_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    let job_queues = match JobQueue::get_by_schedule_id(&db, job_schedule.id).await {
+        Ok(job_queues) => job_queues,
+        Err(err) => {
+            let body = DefaultResponse::error("get job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let job_queue_ids: Vec<i32> = job_queues.iter().map(|job_queue| job_queue.id).collect();
+    let job_deferrals = match JobDeferral::get_by_job_queue_ids(&db, &job_queue_ids).await {
+        Ok(job_deferrals) => job_deferrals,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get job deferrals failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let job_runs = match JobRun::get_by_job_schedule_id(&db, job_schedule.id).await {
+        Ok(job_runs) => job_runs,
+        Err(err) => {
+            let body = DefaultResponse::error("get job runs failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get job schedule success")
+        .with_data(json!({
+            "job_schedule": job_schedule,
+            "job_queues": job_queues,
+            "job_deferrals": job_deferrals,
+            "job_runs": job_runs,
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn pause_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    if !["scheduled", "pending", "in_progress"].contains(&job_schedule.status.as_str()) {
+        let body = DefaultResponse::error(
+            format!("job schedule is {} and can't be paused", job_schedule.status).as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    match JobQueue::update_status_by_schedule_id(
+        &db,
+        job_schedule.id,
+        &statuses(&["pending", "failed"]),
+        "paused",
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("pause job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let job_schedule = match JobSchedule::update_status(&db, job_schedule.id, "paused").await {
+        Ok(job_schedule) => job_schedule,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("pause job schedule failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("pause job schedule success")
+        .with_data(json!(job_schedule))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn resume_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    if job_schedule.status != "paused" {
+        let body = DefaultResponse::error(
+            format!("job schedule is {} and can't be resumed", job_schedule.status).as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    let resumed_queues = match JobQueue::update_status_by_schedule_id(
+        &db,
+        job_schedule.id,
+        &statuses(&["paused"]),
+        "pending",
+    )
+    .await
+    {
+        Ok(job_queues) => job_queues,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("resume job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    // a job waiting in the queue keeps the schedule pending until it runs
+    let status = if resumed_queues.is_empty() {
+        "scheduled"
+    } else {
+        "pending"
+    };
+
+    let job_schedule = match JobSchedule::update_status(&db, job_schedule.id, status).await {
+        Ok(job_schedule) => job_schedule,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("resume job schedule failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("resume job schedule success")
+        .with_data(json!(job_schedule))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn cancel_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    if job_schedule.status == "completed" || job_schedule.status == "cancelled" {
+        let body = DefaultResponse::error(
+            format!("job schedule is already {}", job_schedule.status).as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    match JobQueue::update_status_by_schedule_id(
+        &db,
+        job_schedule.id,
+        &statuses(&["pending", "failed", "paused"]),
+        "cancelled",
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("cancel job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let job_schedule = match JobSchedule::update_status(&db, job_schedule.id, "cancelled").await {
+        Ok(job_schedule) => job_schedule,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("cancel job schedule failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("cancel job schedule success")
+        .with_data(json!(job_schedule))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn skip_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    if job_schedule.status == "completed" || job_schedule.status == "cancelled" {
+        let body = DefaultResponse::error(
+            format!("job schedule is already {}", job_schedule.status).as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    // the occurrence may already be waiting in the queue
+    match JobQueue::update_status_by_schedule_id(
+        &db,
+        job_schedule.id,
+        &statuses(&["pending", "failed", "paused"]),
+        "skipped",
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("skip job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let repeat_count = job_schedule.repeat_count.unwrap_or(0);
+
+    let (run_at, repeat_count, status) = match job_schedule.repeat_interval {
+        Some(repeat_interval) if repeat_count > 0 => (
+            timezone::add_interval(&job_schedule.run_at, repeat_interval, &merchant.tz()),
+            repeat_count - 1,
+            if job_schedule.status == "paused" {
+                "paused"
+            } else {
+                "scheduled"
+            },
+        ),
+        _ => (job_schedule.run_at, 0, "completed"),
+    };
+
+    let job_schedule = match JobSchedule::update_timing(
+        &db,
+        job_schedule.id,
+        &run_at,
+        job_schedule.repeat_interval,
+        Some(repeat_count),
+        job_schedule.total_repeat_count,
+        job_schedule.end_at,
+        status,
+    )
+    .await
+    {
+        Ok(job_schedule) => job_schedule,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("skip job schedule failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("skip next occurrence success")
+        .with_data(json!(job_schedule))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn update_schedule(
+    State(db): State<PgPool>,
+    Path((merchant_id, schedule_id)): Path<(Uuid, i32)>,
+    Json(body): Json<RequestUpdateSchedule>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let job_schedule = match get_merchant_schedule(&db, &merchant_id, schedule_id).await {
+        Ok(job_schedule) => job_schedule,
+        Err(response) => return response,
+    };
+
+    if job_schedule.status == "completed" || job_schedule.status == "cancelled" {
+        let body = DefaultResponse::error(
+            format!("job schedule is already {}", job_schedule.status).as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+    let tz = merchant.tz();
+
+    let now = chrono::Utc::now().naive_utc();
+
+    let run_at = match body.run_at {
+        Some(run_at) => timezone::local_to_utc(&run_at, &tz),
+        None => job_schedule.run_at,
+    };
+
+    if body.run_at.is_some() && run_at < now {
+        let body = DefaultResponse::error(
+            format!(
+                "run_at must be greater than current time ( {} )",
+                timezone::format(&now, &tz, "%Y-%m-%d %H:%M:%S %Z")
+            )
+            .as_str(),
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    let repeat_interval = match &body.repeat_interval_type {
+        Some(repeat_interval_type) => Some(repeat_interval_seconds(repeat_interval_type)),
+        None => job_schedule.repeat_interval,
+    };
+
+    let end_at = match body.end_at {
+        Some(end_at) => Some(timezone::local_to_utc(&end_at, &tz)),
+        None => job_schedule.end_at,
+    };
+
+    if end_at.is_some() && end_at.unwrap() < run_at {
+        let body = DefaultResponse::error(
+            "end_at must be greater than run_at",
+            job_schedule.id.to_string(),
+        )
+        .into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    // the remaining repeats follow the merged run_at, end_at and interval, so
+    // changing any one of them doesn't leave a stale count
+    let done_count =
+        job_schedule.total_repeat_count.unwrap_or(0) - job_schedule.repeat_count.unwrap_or(0);
+    let repeat_count = match (end_at, repeat_interval) {
+        // a one-off schedule never repeats, whatever its end_at
+        (_, Some(repeat_interval)) if repeat_interval == repeat_interval_seconds("ONCE") => {
+            Some(0)
+        }
+        (Some(end_at), Some(repeat_interval)) if repeat_interval > 0 => {
+            ((end_at - run_at).num_seconds() / repeat_interval).to_i32()
+        }
+        // without an end the remaining repeats are kept
+        _ => job_schedule.repeat_count,
+    };
+    let total_repeat_count = match repeat_count {
+        Some(repeat_count) => Some(repeat_count + done_count.max(0)),
+        None => job_schedule.total_repeat_count,
+    };
+
+    // an occurrence queued with the old timing must not run anymore
+    match JobQueue::update_status_by_schedule_id(
+        &db,
+        job_schedule.id,
+        &statuses(&["pending", "failed", "paused"]),
+        "cancelled",
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error("update job queues failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let status = if job_schedule.status == "paused" {
+        "paused"
+    } else {
+        "scheduled"
+    };
+
+    let job_schedule = match JobSchedule::update_timing(
+        &db,
+        job_schedule.id,
+        &run_at,
+        repeat_interval,
+        repeat_count,
+        total_repeat_count,
+        end_at,
+        status,
+    )
+    .await
+    {
+        Ok(job_schedule) => job_schedule,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("update job schedule failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("update job schedule success")
+        .with_data(json!(job_schedule))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+const PREVIEW_OCCURRENCES_LIMIT: usize = 100;
+const PREVIEW_CUSTOMERS_LIMIT: usize = 20;
+
+/// Occurrences and repeat count of `timing`, rendered in the merchant's timezone.
+pub fn preview_timing(timing: &ScheduleTiming, tz: &Tz) -> serde_json::Value {
+    let occurrences: Vec<String> = timing
+        .occurrences(tz, PREVIEW_OCCURRENCES_LIMIT)
+        .iter()
+        .map(|run_at| timezone::format(run_at, tz, "%Y-%m-%d %H:%M:%S"))
+        .collect();
+
+    json!({
+        "timezone": tz.name(),
+        "repeat_interval_type": timing.repeat_interval_type,
+        "repeat_interval": timing.repeat_interval,
+        "total_repeat_count": timing.repeat_count,
+        "total_occurrences": timing.total_occurrences(),
+        "occurrences_truncated": timing.total_occurrences() > occurrences.len() as i64,
+        "occurrences": occurrences,
+        "end_at": timing
+            .end_at
+            .map(|end_at| timezone::format(&end_at, tz, "%Y-%m-%d %H:%M:%S")),
+    })
+}
+
+/// The message `customer_id` would get on each of their contact channels.
+pub async fn preview_recipient(
+    db: &PgPool,
+    customer_id: &Uuid,
+    merchant_id: &Uuid,
+    messages: &ChannelMessages,
+) -> Result<serde_json::Value, Response> {
+    let customer = match Customer::get_by_id(&db, *customer_id, &merchant_id).await {
+        Ok(customer) => customer,
+        Err(err) => {
+            let body = DefaultResponse::error(
+                format!("customer {} not found", customer_id).as_str(),
+                err.to_string(),
+            )
+            .into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    };
+
+    let contact_channels =
+        match CustomerContactChannel::get_customer_contact_channels_by_customer_and_merchant(
+            &db,
+            &customer.id,
+            &merchant_id,
+        )
+        .await
+        {
+            Ok(contact_channels) => contact_channels,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("get contact channels failed", err.to_string())
+                        .into_json();
+
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+            }
+        };
+
+    let channels: Vec<serde_json::Value> = contact_channels
+        .iter()
+        .map(|contact_channel| {
+            json!({
+                "channel": contact_channel.name,
+                "value": contact_channel.value,
+                "opted_out": contact_channel.opted_out_at.is_some(),
+                "subject": messages.for_channel(&contact_channel.name).subject,
+                "message": messages.for_channel(&contact_channel.name).body,
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "customer_id": customer.id,
+        "customer_name": customer.name,
+        "channels": channels,
+    }))
+}
+
+/// Dry run of `set_scheduler`: validates the body and returns what would be
+/// scheduled without persisting anything.
+pub async fn preview_scheduler(
+    State(db): State<PgPool>,
+    Path(merchant_id): Path<Uuid>,
+    Json(body): Json<RequestSchedule>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+    let tz = merchant.tz();
+
+    let timing = match ScheduleTiming::compute(
+        body.is_recurring,
+        body.repeat_interval_type.as_deref(),
+        body.start_at,
+        body.end_at,
+        &tz,
+    ) {
+        Ok(timing) => timing,
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), body.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let mut customers = Vec::new();
+    let mut total_customers = 1;
+
+    if body.job_type == "send_invoice" {
+        let invoice_id = match body.external_id {
+            Some(invoice_id) => invoice_id,
+            None => {
+                let body = DefaultResponse::error(
+                    "external_id ( invoice id ) is required for send_invoice job",
+                    body.to_string(),
+                )
+                .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+        match preview_invoice_recipient(&db, &invoice_id, &merchant, &timing).await {
+            Ok(customer) => customers.push(customer),
+            Err(response) => return response,
+        };
+    } else if body.job_type == "send_reminder" {
+        let customer_ids = match reminder_customer_ids(&db, &merchant_id, &body).await {
+            Ok(customer_ids) => customer_ids,
+            Err(response) => return response,
+        };
+        total_customers = customer_ids.len();
+
+        // every customer takes a few queries to render, a large tag is only
+        // sampled
+        for customer_id in customer_ids.into_iter().take(PREVIEW_CUSTOMERS_LIMIT) {
+            let customer = match Customer::get_by_id(&db, customer_id, &merchant_id).await {
+                Ok(customer) => customer,
+                Err(err) => {
+                    let body = DefaultResponse::error(
+                        format!("customer {} not found", customer_id).as_str(),
+                        err.to_string(),
+                    )
+                    .into_json();
+
+                    return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+                }
+            };
+
+            let messages = match message_builder_reminder(
+                &db,
+                &SendReminderPayload {
+                    title: body.title.clone().unwrap_or_default(),
+                    description: body.description.clone().unwrap_or_default(),
+                    customer_id: customer.id,
+                    customer_name: customer.name.clone(),
+                    merchant_id,
+                    merchant_name: merchant.name.clone(),
+                },
+            )
+            .await
+            {
+                Ok(messages) => messages,
+                Err(err) => {
+                    let body =
+                        DefaultResponse::error("get message templates failed", err.to_string())
+                            .into_json();
+
+                    return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+                }
+            };
+
+            match preview_recipient(&db, &customer.id, &merchant_id, &messages).await {
+                Ok(customer) => customers.push(customer),
+                Err(response) => return response,
+            };
+        }
+    } else {
+        let body =
+            DefaultResponse::error("job_type is not supported", body.to_string()).into_json();
+
+        return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+    }
+
+    let mut data = preview_timing(&timing, &tz);
+    data["job_type"] = json!(body.job_type);
+    data["total_customers"] = json!(total_customers);
+    data["customers_truncated"] = json!(total_customers > customers.len());
+    data["customers"] = json!(customers);
+    // tag reminders pick up whoever carries the tag when each occurrence runs
+    data["resolved_at_run_time"] =
+        json!(body.job_type == "send_reminder" && body.external_id.is_none());
+
+    let body = DefaultResponse::ok("preview job schedule success")
+        .with_data(data)
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Recipient preview of a `send_invoice` schedule. The payment link is only
+/// created when the invoice is sent, so a placeholder is shown instead.
+pub async fn preview_invoice_recipient(
+    db: &PgPool,
+    invoice_id: &Uuid,
+    merchant: &Merchant,
+    timing: &ScheduleTiming,
+) -> Result<serde_json::Value, Response> {
+    let invoice = match Invoice::get_by_id(&db, &invoice_id).await {
+        Ok(invoice) if invoice.merchant_id == merchant.id => invoice,
+        Ok(_) | Err(_) => {
+            let body = DefaultResponse::error(
+                format!("invoice {} not found", invoice_id).as_str(),
+                merchant.id.to_string(),
+            )
+            .into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    };
+
+    let customer = match Customer::get_by_id(&db, invoice.customer_id, &invoice.merchant_id).await
+    {
+        Ok(customer) => customer,
+        Err(err) => {
+            let body = DefaultResponse::error("get customer failed", err.to_string()).into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    };
+
+    let locale = customer.locale(&merchant);
+
+    let payload = SendInvoicePayload {
+        invoice_id: invoice.id,
+        customer_id: customer.id,
+        customer_name: customer.name,
+        merchant_id: merchant.id,
+        merchant_name: merchant.name.clone(),
+        total_amount: invoice.total_amount as i64,
+        attach_pdf: false,
+        snoozed: false,
+    };
+
+    let items = match Item::get_by_invoice_id(&db, &invoice.id).await {
+        Ok(items) => items,
+        Err(err) => {
+            let body = DefaultResponse::error("get items failed", err.to_string()).into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    };
+
+    let due_at = timing.start_at.add(chrono::Duration::hours(PAYMENT_DUE_HOURS));
+    let values = invoice_values(
+        &payload,
+        &invoice,
+        &items,
+        "<payment link>",
+        &due_at,
+        &merchant.tz(),
+        locale,
+    );
+
+    let messages = match ChannelMessages::for_merchant(
+        &db,
+        &merchant.id,
+        "invoice",
+        &values,
+        default_invoice_notification(locale),
+    )
+    .await
+    {
+        Ok(messages) => messages,
+        Err(err) => {
+            let body = DefaultResponse::error("get message templates failed", err.to_string())
+                .into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    };
+
+    preview_recipient(&db, &payload.customer_id, &merchant.id, &messages).await
+}
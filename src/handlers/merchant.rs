@@ -1,9 +1,16 @@
 use crate::errors::FieldValidator;
 use crate::models::job_schedule::JobSchedule;
 use crate::models::merchant::Merchant;
-use crate::models::requests::merchant::RequestUpdateMerchant;
+use crate::models::merchant_holiday::MerchantHoliday;
+use crate::models::merchant_rate_limit::MerchantRateLimit;
+use crate::models::merchant_sending_window::MerchantSendingWindow;
+use crate::models::requests::merchant::{
+    RequestCreateHoliday, RequestSetRateLimits, RequestSetSendingWindows, RequestUpdateMerchant,
+};
 use crate::models::responses::DefaultResponse;
 use crate::{models::requests::merchant::RequestCreateMerchant};
+use crate::locale;
+use crate::utils::timezone;
 use axum::extract::Path;
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
@@ -80,7 +87,14 @@ pub async fn create(
     // generate code merchant based on name and number
     let code = Merchant::generate_merchant_code(&name);
 
-    let merchant = match Merchant::create(&db, &name, &description, &user_id, address, body.phone_country_code, phone_number, tax, &code).await {
+    let timezone = body
+        .timezone
+        .unwrap_or_else(|| timezone::DEFAULT_TIMEZONE.to_string());
+    let default_locale = body
+        .default_locale
+        .unwrap_or_else(|| locale::DEFAULT_LOCALE.to_string());
+
+    let merchant = match Merchant::create(&db, &name, &description, &user_id, address, body.phone_country_code, phone_number, tax, &code, &timezone, &default_locale).await {
         Ok(merchant) => merchant,
         Err(err) => {
             let body =
@@ -115,8 +129,24 @@ pub async fn update(
         Err(err) => return (StatusCode::UNPROCESSABLE_ENTITY, err.into_response()).into_response(),
     }
 
+    let (timezone, default_locale) = match (body.timezone, body.default_locale) {
+        (Some(timezone), Some(default_locale)) => (timezone, default_locale),
+        (timezone, default_locale) => match Merchant::get_by_id(&db, merchant_id).await {
+            Ok(merchant) => (
+                timezone.unwrap_or(merchant.timezone),
+                default_locale.unwrap_or(merchant.default_locale),
+            ),
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("update merchant failed", err.to_string()).into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        },
+    };
+
     let merchant =
-        match Merchant::update(&db, merchant_id, &name, &description, &user_id, address, body.phone_country_code, phone_number, tax).await {
+        match Merchant::update(&db, merchant_id, &name, &description, &user_id, address, body.phone_country_code, phone_number, tax, &timezone, &default_locale).await {
             Ok(merchant) => merchant,
             Err(err) => {
                 let body =
@@ -172,4 +202,216 @@ pub async fn get_job_schedule_by_merchant_id(
         .into_json();
 
     (StatusCode::OK, body).into_response()
-}
\ No newline at end of file
+}
+
+pub async fn get_sending_windows(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+) -> Response {
+    let windows = match MerchantSendingWindow::get_by_merchant_id(&db, &merchant_id).await {
+        Ok(windows) => windows,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get sending windows failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get sending windows success")
+        .with_data(json!(windows))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Replaces the merchant's sending windows, an empty list allows sending at
+/// any time.
+pub async fn set_sending_windows(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+    Json(body): Json<RequestSetSendingWindows>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let mut rows = Vec::new();
+    for window in body.windows.iter() {
+        match window.to_rows() {
+            Ok(window_rows) => rows.extend(window_rows),
+            Err(err) => {
+                let body = DefaultResponse::error(err.as_str(), merchant_id.to_string()).into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        }
+    }
+
+    let windows = match MerchantSendingWindow::replace_by_merchant_id(&db, &merchant_id, &rows).await {
+        Ok(windows) => windows,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("set sending windows failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("set sending windows success")
+        .with_data(json!(windows))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn get_holidays(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+) -> Response {
+    let holidays = match MerchantHoliday::get_by_merchant_id(&db, &merchant_id).await {
+        Ok(holidays) => holidays,
+        Err(err) => {
+            let body = DefaultResponse::error("get holidays failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get holidays success")
+        .with_data(json!(holidays))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn create_holiday(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+    Json(body): Json<RequestCreateHoliday>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let holiday =
+        match MerchantHoliday::create(&db, &merchant_id, &body.date, body.description).await {
+            Ok(holiday) => holiday,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("create holiday failed", err.to_string()).into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("create holiday success")
+        .with_data(json!(holiday))
+        .into_json();
+
+    (StatusCode::CREATED, body).into_response()
+}
+
+pub async fn delete_holiday(
+    State(db): State<PgPool>,
+    Path((merchant_id, holiday_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let holiday = match MerchantHoliday::delete(&db, holiday_id, &merchant_id).await {
+        Ok(holiday) => holiday,
+        Err(err) => {
+            let body = DefaultResponse::error("holiday not found", err.to_string()).into_json();
+
+            return (StatusCode::NOT_FOUND, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("delete holiday success")
+        .with_data(json!(holiday))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn get_rate_limits(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+) -> Response {
+    let rate_limits = match MerchantRateLimit::get_by_merchant_id(&db, &merchant_id).await {
+        Ok(rate_limits) => rate_limits,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get rate limits failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get rate limits success")
+        .with_data(json!(rate_limits))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Replaces the merchant's per channel send limits, channels without one only
+/// follow the server-wide `WORKER_RATE_*` limits.
+pub async fn set_rate_limits(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+    Json(body): Json<RequestSetRateLimits>,
+) -> Response {
+    match validator::Validate::validate(&body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let mut rows: Vec<(String, i32, i32)> = Vec::new();
+    for rate_limit in body.rate_limits.iter() {
+        if rows.iter().any(|(channel, _, _)| *channel == rate_limit.channel) {
+            let body = DefaultResponse::error(
+                format!("rate limit for {} is set more than once", rate_limit.channel).as_str(),
+                merchant_id.to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+
+        rows.push((
+            rate_limit.channel.clone(),
+            rate_limit.per_minute,
+            rate_limit.burst.unwrap_or(1),
+        ));
+    }
+
+    let rate_limits =
+        match MerchantRateLimit::replace_by_merchant_id(&db, &merchant_id, &rows).await {
+            Ok(rate_limits) => rate_limits,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("set rate limits failed", err.to_string()).into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("set rate limits success")
+        .with_data(json!(rate_limits))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
@@ -0,0 +1,203 @@
+use crate::models::message_template::MessageTemplate;
+use crate::models::requests::message_template::{
+    RequestMessageTemplate, RequestPreviewMessageTemplate,
+};
+use crate::models::responses::DefaultResponse;
+use crate::templates;
+use axum::extract::Path;
+use axum::response::{IntoResponse, Response};
+use axum::{extract::State, response::Json};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn get_templates(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+) -> Response {
+    let message_templates = match MessageTemplate::get_by_merchant_id(&db, &merchant_id).await {
+        Ok(message_templates) => message_templates,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("get message templates failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let variables: serde_json::Map<String, serde_json::Value> = templates::EVENT_TYPES
+        .iter()
+        .map(|(event_type, variables)| (event_type.to_string(), json!(variables)))
+        .collect();
+
+    let body = DefaultResponse::ok("get message templates success")
+        .with_data(json!({
+            "message_templates": message_templates,
+            "variables": variables,
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn create_template(
+    State(db): State<PgPool>,
+    Path((merchant_id,)): Path<(Uuid,)>,
+    Json(body): Json<RequestMessageTemplate>,
+) -> Response {
+    if let Err(response) = validate_template(&body) {
+        return response;
+    }
+
+    let message_template = match MessageTemplate::create(
+        &db,
+        &merchant_id,
+        &body.event_type,
+        body.channel(),
+        body.subject.clone(),
+        &body.body,
+    )
+    .await
+    {
+        Ok(message_template) => message_template,
+        Err(err) => {
+            let body = DefaultResponse::error(
+                format!(
+                    "create message template failed, {} may already have a template for {}",
+                    body.event_type,
+                    body.channel()
+                )
+                .as_str(),
+                err.to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("create message template success")
+        .with_data(json!(message_template))
+        .into_json();
+
+    (StatusCode::CREATED, body).into_response()
+}
+
+pub async fn update_template(
+    State(db): State<PgPool>,
+    Path((merchant_id, message_template_id)): Path<(Uuid, i32)>,
+    Json(body): Json<RequestMessageTemplate>,
+) -> Response {
+    if let Err(response) = validate_template(&body) {
+        return response;
+    }
+
+    let message_template = match MessageTemplate::update(
+        &db,
+        message_template_id,
+        &merchant_id,
+        &body.event_type,
+        body.channel(),
+        body.subject.clone(),
+        &body.body,
+    )
+    .await
+    {
+        Ok(message_template) => message_template,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("update message template failed", err.to_string())
+                    .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("update message template success")
+        .with_data(json!(message_template))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn delete_template(
+    State(db): State<PgPool>,
+    Path((merchant_id, message_template_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let message_template =
+        match MessageTemplate::delete(&db, message_template_id, &merchant_id).await {
+            Ok(message_template) => message_template,
+            Err(err) => {
+                let body = DefaultResponse::error("message template not found", err.to_string())
+                    .into_json();
+
+                return (StatusCode::NOT_FOUND, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("delete message template success")
+        .with_data(json!(message_template))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Renders a template with sample values, or the given ones, without saving
+/// it.
+pub async fn preview_template(
+    Path((_merchant_id,)): Path<(Uuid,)>,
+    Json(body): Json<RequestPreviewMessageTemplate>,
+) -> Response {
+    if let Err(response) = validate_template(&body.template) {
+        return response;
+    }
+
+    let template = &body.template;
+
+    let mut values = templates::sample_values(&template.event_type);
+    if let Some(overrides) = &body.values {
+        for (name, value) in values.iter_mut() {
+            if let Some(override_value) = overrides.get(*name) {
+                *value = override_value.clone();
+            }
+        }
+    }
+
+    let subject = template
+        .subject
+        .as_ref()
+        .map(|subject| templates::render(subject, &values));
+
+    let body = DefaultResponse::ok("preview message template success")
+        .with_data(json!({
+            "event_type": template.event_type,
+            "channel": template.channel(),
+            "subject": subject,
+            "body": templates::render(&template.body, &values),
+            "variables": templates::variables(&template.body),
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+fn validate_template(body: &RequestMessageTemplate) -> Result<(), Response> {
+    match validator::Validate::validate(body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    }
+
+    match body.validate_variables() {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), body.body.clone()).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
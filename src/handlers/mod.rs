@@ -2,7 +2,12 @@ pub mod auth;
 pub mod user;
 pub mod merchant;
 pub mod customer;
+pub mod customer_contact_channel;
 pub mod invoice;
 pub mod job_schedule;
+pub mod message_template;
+pub mod telegram_invite;
+pub mod unsubscribe;
 pub mod verification;
-pub mod webhook;
\ No newline at end of file
+pub mod webhook;
+pub mod webhook_subscription;
@@ -0,0 +1,115 @@
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use chrono::{Duration, Utc};
+use qrcode::{render::svg, QrCode};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::bot::start_url;
+use crate::models::customer::Customer;
+use crate::models::merchant::Merchant;
+use crate::models::responses::DefaultResponse;
+use crate::models::telegram_invite::TelegramInvite;
+
+/// How long an invite link can be opened.
+const INVITE_DAYS: i64 = 7;
+
+/// A one-tap link that connects the chat opening it to the customer, with a
+/// QR code of it for printed invoices.
+pub async fn create_invite(
+    State(db): State<PgPool>,
+    Path((merchant_id, customer_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    let customer = match Customer::get_by_id(&db, customer_id, &merchant_id).await {
+        Ok(customer) => customer,
+        Err(err) => {
+            let body = DefaultResponse::error("customer not found", err.to_string()).into_json();
+
+            return (StatusCode::NOT_FOUND, body).into_response();
+        }
+    };
+
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let expires_at = Utc::now().naive_utc() + Duration::days(INVITE_DAYS);
+
+    let invite =
+        match TelegramInvite::create(&db, &token, &merchant_id, &customer.id, &expires_at).await {
+            Ok(invite) => invite,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("create telegram invite failed", err.to_string())
+                        .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    link_response("create telegram invite success", &invite.token, json!(invite))
+}
+
+/// Link with the merchant code as payload, any registered customer opening
+/// it is connected by their Telegram username.
+pub async fn get_merchant_link(
+    State(db): State<PgPool>,
+    Path(merchant_id): Path<Uuid>,
+) -> Response {
+    let merchant = match Merchant::get_by_id(&db, merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let body = DefaultResponse::error("get merchant failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let merchant_code = match &merchant.merchant_code {
+        Some(merchant_code) => merchant_code.clone(),
+        None => {
+            let body = DefaultResponse::error(
+                "merchant has no merchant code",
+                merchant.id.to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    link_response(
+        "get telegram link success",
+        &merchant_code,
+        json!({ "merchant_id": merchant.id }),
+    )
+}
+
+fn link_response(message: &str, start_payload: &str, data: serde_json::Value) -> Response {
+    let link = start_url(start_payload);
+
+    let qr_svg = match QrCode::new(link.as_bytes()) {
+        Ok(code) => code
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .build(),
+        Err(err) => {
+            let body = DefaultResponse::error("generate qr code failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let mut data = data;
+    data["link"] = json!(link);
+    data["qr_svg"] = json!(qr_svg);
+
+    let body = DefaultResponse::ok(message).with_data(data).into_json();
+
+    (StatusCode::OK, body).into_response()
+}
@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Query, State},
+    response::Html,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    locale::{Locale, Text},
+    models::customer_contact_channel::CustomerContactChannel,
+    notifications::unsubscribe,
+};
+
+use super::verification::customer_locale;
+
+#[derive(Deserialize, Debug)]
+pub struct UnsubscribeQuery {
+    pub id: Uuid,
+    pub token: String,
+}
+
+/// Target of the link at the bottom of emails, asks to confirm. Mail scanners
+/// and link prefetchers open every link, so a GET never opts out.
+pub async fn confirm(
+    State(db): State<PgPool>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Html<String> {
+    if !unsubscribe::is_valid(&query.id, &query.token) {
+        return Html(Locale::default().text(Text::UnsubscribeInvalid).to_string());
+    }
+
+    let locale = match CustomerContactChannel::get_by_id(&db, &query.id).await {
+        Ok(customer_contact_channel) => {
+            customer_locale(&db, &customer_contact_channel.customer_id).await
+        }
+        Err(_) => return Html(Locale::default().text(Text::UnsubscribeInvalid).to_string()),
+    };
+
+    // the form posts back to this URL, id and token included
+    Html(format!(
+        "{}\n<form method=\"post\"><button type=\"submit\">{}</button></form>",
+        locale.text(Text::UnsubscribeConfirm),
+        locale.text(Text::Unsubscribe)
+    ))
+}
+
+/// Opts the contact channel out, posted by the confirmation page and by mail
+/// clients' one-click unsubscribe (RFC 8058).
+pub async fn unsubscribe(
+    State(db): State<PgPool>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Html<&'static str> {
+    if !unsubscribe::is_valid(&query.id, &query.token) {
+        return Html(Locale::default().text(Text::UnsubscribeInvalid));
+    }
+
+    let customer_contact_channel =
+        match CustomerContactChannel::set_opted_out(&db, &query.id, true).await {
+            Ok(customer_contact_channel) => customer_contact_channel,
+            Err(_) => return Html(Locale::default().text(Text::UnsubscribeInvalid)),
+        };
+
+    println!(
+        "Customer contact channel {} opted out by unsubscribe link",
+        customer_contact_channel.id
+    );
+
+    let locale = customer_locale(&db, &customer_contact_channel.customer_id).await;
+
+    Html(locale.text(Text::UnsubscribeDone))
+}
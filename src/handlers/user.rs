@@ -1,7 +1,17 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bot::{start_url, user_link_key};
+use crate::conversation_store::ConversationStore;
+use crate::models::responses::DefaultResponse;
 use crate::models::user::User;
 
-use axum::{response::{Json, IntoResponse}, extract::State};
+use axum::{response::{Json, IntoResponse, Response}, extract::State, Extension};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::StatusCode;
+use serde_json::json;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use axum::response::Html;
 
@@ -15,3 +25,61 @@ pub async fn get_users(State(db): State<PgPool>,) -> impl IntoResponse {
 
     Json(users)
 }
+
+/// How long a Telegram link of a user can be opened.
+const TELEGRAM_LINK_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Link that connects the Telegram chat opening it to the authenticated user,
+/// the chat then gets the alerts of the user's merchants.
+pub async fn create_telegram_link(
+    Extension(user_id): Extension<Uuid>,
+    Extension(conversation_store): Extension<Arc<dyn ConversationStore>>,
+) -> Response {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+
+    match conversation_store
+        .set(&user_link_key(&token), &user_id.to_string(), TELEGRAM_LINK_TTL)
+        .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let body = DefaultResponse::error("create telegram link failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let body = DefaultResponse::ok("create telegram link success")
+        .with_data(json!({
+            "link": start_url(&token),
+            "expires_in": TELEGRAM_LINK_TTL.as_secs(),
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Stops the merchant alerts, the chat is unlinked from the user.
+pub async fn delete_telegram_link(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+) -> Response {
+    match User::update_telegram_chat_id(&db, &user_id, None).await {
+        Ok(_) => (),
+        Err(err) => {
+            let body = DefaultResponse::error("delete telegram link failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    }
+
+    let body = DefaultResponse::ok("delete telegram link success").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
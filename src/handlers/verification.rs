@@ -1,16 +1,20 @@
+use std::collections::HashMap;
+
 use axum::{
     extract::{Query, State},
     response::Html,
 };
 use serde::Deserialize;
+use serde_json::json;
 use sqlx::PgPool;
 use validator_derive::Validate;
 
 use crate::{
     errors::DefaultError,
-    functions::send_email_verification,
-    models::{customer::Customer, user::User, verification::Verification},
-    repositories::{telegram::telegram_send_message, whatsapp::whatsapp_send_message},
+    locale::{Locale, Text},
+    models::{customer::Customer, merchant::Merchant, user::User, verification::Verification},
+    notifications::{Notification, Notifier, Recipient},
+    templates, webhooks,
 };
 
 #[derive(Deserialize, Validate, Debug)]
@@ -28,15 +32,16 @@ pub async fn auth(
     if query.id.is_some() {
         let id = uuid::Uuid::parse_str(&query.id.unwrap()).unwrap();
         let verification = Verification::get_by_id(&db, id).await.unwrap();
+        let locale = verification_locale(&db, &verification).await;
 
         let expires_at = verification.expires_at;
 
         if expires_at.is_some() && expires_at < Some(now) {
-            return Html("<h1>Verification link has expired</h1>");
+            return Html(locale.text(Text::VerificationExpired));
         }
 
         if verification.status == "verified" {
-            return Html("<h1>Verification link has already been used</h1>");
+            return Html(locale.text(Text::VerificationUsed));
         }
 
         if verification.code == query.code {
@@ -58,12 +63,20 @@ pub async fn auth(
                     .await
                     .unwrap();
 
-                match Customer::update_verified_at(&db, &customer.id, &now).await {
-                    Ok(_) => (),
+                let customer = match Customer::update_verified_at(&db, &customer.id, &now).await {
+                    Ok(customer) => customer,
                     Err(e) => {
                         panic!("Error updating customer verified_at: {}", e)
                     }
-                }
+                };
+
+                webhooks::emit(
+                    &db,
+                    &customer.merchant_id,
+                    "customer.verified",
+                    json!(customer),
+                )
+                .await;
             }
 
             match Verification::update_status(&db, &verification.id, &"verified".to_string()).await
@@ -75,14 +88,38 @@ pub async fn auth(
             }
         }
 
-        return Html("<h1>Thank you for verifying!</h1>");
+        return Html(locale.text(Text::VerificationDone));
     }
 
     return Html("<h1>Hello world</h1>");
 }
 
+/// Language of the verification page, the customer's when the verification
+/// belongs to one.
+async fn verification_locale(db: &sqlx::PgPool, verification: &Verification) -> Locale {
+    match verification.customer_id {
+        Some(customer_id) => customer_locale(db, &customer_id).await,
+        None => Locale::default(),
+    }
+}
+
+/// Language of pages and replies for a customer found without their
+/// merchant, the default when the customer can't be loaded.
+pub async fn customer_locale(db: &sqlx::PgPool, customer_id: &uuid::Uuid) -> Locale {
+    let customer = Customer::get_by_id_only(&db, *customer_id).await.ok();
+
+    match customer {
+        Some(customer) => match Merchant::get_by_id(&db, customer.merchant_id).await {
+            Ok(merchant) => customer.locale(&merchant),
+            Err(_) => Locale::default(),
+        },
+        None => Locale::default(),
+    }
+}
+
 pub async fn setup_verification(
     db: &sqlx::PgPool,
+    notifier: &Notifier,
     user_id: Option<uuid::Uuid>,
     customer_id: Option<uuid::Uuid>,
     contact_channel_name: String,
@@ -109,7 +146,7 @@ pub async fn setup_verification(
         base_url, code, verification.id
     );
 
-    let recepient_name = if user_id.is_some() {
+    let (recepient_name, locale) = if user_id.is_some() {
         let user = match User::get_by_id(&db, user_id.unwrap()).await {
             Ok(user) => user,
             Err(err) => {
@@ -120,7 +157,7 @@ pub async fn setup_verification(
             }
         };
 
-        user.name
+        (user.name, Locale::default())
     } else {
         let customer = match Customer::get_by_id_only(&db, customer_id.unwrap()).await {
             Ok(customer) => customer,
@@ -132,50 +169,51 @@ pub async fn setup_verification(
             }
         };
 
-        customer.name
+        let locale = match Merchant::get_by_id(&db, customer.merchant_id).await {
+            Ok(merchant) => customer.locale(&merchant),
+            Err(_) => Locale::default(),
+        };
+
+        (customer.name, locale)
     };
 
-    if contact_channel_name == "email" {
-        match send_email_verification(&recepient_name, &contact_value, &url_verification).await {
-            Ok(_) => (),
-            Err(err) => {
-                return Err(DefaultError {
-                    value: "setup verification error".to_string(),
-                    message: err.to_string(),
-                });
-            }
-        };
-    } else if contact_channel_name == "whatsapp" {
-        let message = format!(
-            "Hi {}, please verify your registration by clicking this link: {}",
-            recepient_name, url_verification
-        );
-        match whatsapp_send_message(contact_value.as_str(), message.as_str()).await {
-            Ok(_) => (),
-            Err(err) => {
-                return Err(DefaultError {
-                    value: "setup verification error".to_string(),
-                    message: err.to_string(),
-                });
-            }
-        }
+    let values = HashMap::from([("name", recepient_name), ("url", url_verification)]);
+
+    let (recipient, notification) = if contact_channel_name == "email" {
+        let message = templates::render(locale.text(Text::VerificationEmail), &values);
+
+        (
+            Recipient::new(&contact_value),
+            Notification::new(&message)
+                .with_subject(locale.text(Text::VerificationEmailSubject))
+                .with_sender_name("Verification"),
+        )
     } else if contact_channel_name == "telegram" {
-        let message = format!(
-            "Hi {}, please verify your registration by clicking this link: {}",
-            recepient_name, url_verification
-        );
+        let message = templates::render(locale.text(Text::VerificationMessage), &values);
         let parsed_chat_id = contact_value
             .parse::<i64>()
             .expect("format contact value invalid");
 
-        match telegram_send_message(&parsed_chat_id, message.as_str()).await {
-            Ok(_) => (),
-            Err(err) => {
-                return Err(DefaultError {
-                    value: "setup verification error".to_string(),
-                    message: err.to_string(),
-                });
-            }
+        (
+            Recipient::telegram_chat(&parsed_chat_id),
+            Notification::new(&message),
+        )
+    } else {
+        let message = templates::render(locale.text(Text::VerificationMessage), &values);
+
+        (Recipient::new(&contact_value), Notification::new(&message))
+    };
+
+    match notifier
+        .send(&contact_channel_name, &recipient, &notification)
+        .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            return Err(DefaultError {
+                value: "setup verification error".to_string(),
+                message: err.to_string(),
+            });
         }
     }
 
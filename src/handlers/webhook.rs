@@ -1,22 +1,49 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bot::{user_link_key, BotChat, MerchantChat, LIST_LIMIT};
+use crate::contacts;
+use crate::conversation_store::ConversationStore;
+use crate::models::contact_channel::ContactChannel;
+use crate::models::conversation_message::ConversationMessage;
 use crate::models::customer::Customer;
 use crate::models::customer_contact_channel::CustomerContactChannel;
+use crate::models::delivery::Delivery;
+use crate::models::invoice::Invoice;
+use crate::models::job_queue::JobQueue;
+use crate::models::job_schedule::JobSchedule;
 use crate::models::merchant::Merchant;
-use crate::models::requests::telegram::TelegramUpdateItem;
+use crate::models::requests::telegram::{TelegramCallbackQuery, TelegramUpdateItem};
+use crate::models::requests::whatsapp::WhatsappInboundMessage;
+use crate::models::requests::xendit::XenditInvoiceCallback;
 use crate::models::responses::DefaultResponse;
-use crate::repositories::telegram::telegram_send_message;
+use crate::models::telegram_invite::TelegramInvite;
+use crate::models::user::User;
+use crate::locale::{Locale, Text};
+use crate::notifications::{merchant_alert, Notification, Notifier, Recipient};
+use crate::repositories::telegram::telegram_answer_callback_query;
+use crate::webhooks;
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
 use axum::{extract::State, response::Json};
-use redis::cmd;
+use hmac::{Hmac, Mac};
 use reqwest::StatusCode;
 use serde_json::json;
+use sha2::Sha256;
 use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::verification::{customer_locale, setup_verification};
 
-use super::verification::setup_verification;
+/// How long the bot waits for the merchant code after `/connect`.
+const CONNECT_STATE_TTL: Duration = Duration::from_secs(10 * 60);
 
 pub async fn telegram(
     State(db): State<PgPool>,
     Extension(headers): Extension<Vec<(String, String)>>,
+    Extension(conversation_store): Extension<Arc<dyn ConversationStore>>,
+    Extension(notifier): Extension<Arc<Notifier>>,
     Json(payload): Json<TelegramUpdateItem>,
 ) -> Response {
     let secret_token = std::env::var("TELEGRAM_SECRET_TOKEN").unwrap();
@@ -47,6 +74,10 @@ pub async fn telegram(
         return (StatusCode::OK, body).into_response();
     }
 
+    if let Some(callback_query) = payload.callback_query {
+        return telegram_callback(&db, &notifier, callback_query).await;
+    }
+
     let telegram_message = if payload.message.is_some() {
         payload.message.unwrap()
     } else {
@@ -117,64 +148,163 @@ pub async fn telegram(
         return (StatusCode::OK, body).into_response();
     };
 
-    let redis_connection = std::env::var("REDIS_CONNECTION").unwrap();
-    let client = match redis::Client::open(redis_connection) {
-        Ok(client) => client,
-        Err(err) => {
-            let body =
-                DefaultResponse::error("unable to connect to redis", err.to_string()).into_json();
-
-            return (StatusCode::OK, body).into_response();
-        }
-    };
-
-    let mut con = match client.get_connection() {
-        Ok(con) => con,
-        Err(err) => {
-            let body =
-                DefaultResponse::error("unable to connect to redis", err.to_string()).into_json();
+    let key = format!("telegram_{}", chat_id);
 
-            return (StatusCode::OK, body).into_response();
-        }
+    // the customer isn't known yet, answer in the language of their Telegram app
+    let locale = match &from.language_code {
+        Some(language_code) => Locale::parse_or_default(language_code),
+        None => Locale::default(),
     };
 
-    let key = format!("telegram_{}", chat_id);
+    // commands may be addressed to the bot as /invoices@bot_name in groups
+    let mut words = message_text.split_whitespace();
+    let command = words
+        .next()
+        .unwrap_or_default()
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let argument = words.next();
 
-    if message_text == "/start" {
-        telegram_send_message(
+    if command == "/start" && argument.is_some() {
+        return start_link(
+            &db,
+            &notifier,
+            conversation_store.as_ref(),
             &chat_id,
-            "Hi, welcome to the telegram bot. Send /connect to connect to the merchant",
+            locale,
+            argument.unwrap_or_default(),
+            &from_username,
+        )
+        .await;
+    } else if message_text == "/start" {
+        let opted_in = match set_opted_out(
+            &db,
+            "telegram",
+            &chat_id.to_string(),
+            false,
         )
         .await
-        .unwrap();
-    } else if message_text == "/connect" {
-        match cmd("SET").arg(key).arg(&message_text).query::<()>(&mut con) {
-            Ok(_) => Some(()),
-            Err(_) => None,
+        {
+            Ok(opted_in) => opted_in,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("unable to opt in", err.to_string()).into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
         };
 
-        telegram_send_message(
-            &chat_id,
-            "OK. Send me the merchant code that you get from the merchant",
+        // chats that were opted out are welcomed back instead
+        let msg = if opted_in.is_empty() {
+            locale.text(Text::BotWelcome)
+        } else {
+            locale.text(Text::BotOptedIn)
+        };
+
+        reply(&notifier, &chat_id, msg).await;
+    } else if message_text == "/stop" {
+        match set_opted_out(
+            &db,
+            "telegram",
+            &chat_id.to_string(),
+            true,
         )
         .await
-        .unwrap();
-    } else if message_text == "/clear" {
-        match cmd("DEL").arg(&key).query::<Option<()>>(&mut con) {
-            Ok(_) => Some(()),
-            Err(_) => None,
+        {
+            Ok(opted_out) => {
+                for customer_contact_channel in opted_out {
+                    println!(
+                        "Customer contact channel {} opted out by /stop",
+                        customer_contact_channel.id
+                    );
+                }
+            }
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("unable to opt out", err.to_string()).into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
         };
 
-        match telegram_send_message(&chat_id, "Send /connect to connect to the merchant").await {
-            Ok(_) => (),
+        reply(&notifier, &chat_id, locale.text(Text::BotOptedOut)).await;
+    } else if ["/today", "/remind", "/logout"].contains(&command.as_str()) {
+        let notification = match MerchantChat::load(&db, &chat_id).await {
+            Ok(Some(merchant_chat)) => match command.as_str() {
+                "/today" => merchant_chat.today(&db).await,
+                "/remind" => merchant_chat.remind(&db, argument).await,
+                _ => merchant_chat.unlink(&db).await,
+            },
+            Ok(None) => Ok(Notification::new(locale.text(Text::BotUserNotLinked))),
+            Err(err) => Err(err),
+        };
+
+        let notification = match notification {
+            Ok(notification) => notification,
             Err(err) => {
-                let body = DefaultResponse::error(&err.message, err.value.to_string()).into_json();
+                let body = DefaultResponse::error("unable to answer command", err.to_string())
+                    .into_json();
 
                 return (StatusCode::OK, body).into_response();
             }
-        }
+        };
+
+        reply_notification(&notifier, &chat_id, &notification).await;
+    } else if ["/help", "/invoices", "/invoice", "/history"].contains(&command.as_str()) {
+        let bot_chat = match BotChat::load(&db, &chat_id, locale).await {
+            Ok(bot_chat) => bot_chat,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("unable to load chat customers", err.to_string())
+                        .into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
+        };
+
+        let notification = match command.as_str() {
+            "/help" => Ok(bot_chat.help()),
+            "/invoices" => bot_chat.invoices(&db, None).await,
+            "/invoice" => bot_chat.invoice(&db, argument).await,
+            _ => bot_chat.history(&db, None).await,
+        };
+
+        let notification = match notification {
+            Ok(notification) => notification,
+            Err(err) => {
+                let body = DefaultResponse::error("unable to answer command", err.to_string())
+                    .into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
+        };
+
+        reply_notification(&notifier, &chat_id, &notification).await;
+    } else if message_text == "/connect" {
+        match conversation_store
+            .set(&key, &message_text, CONNECT_STATE_TTL)
+            .await
+        {
+            Ok(_) => (),
+            Err(err) => println!(
+                "Failed to keep /connect of chat {}: {}",
+                chat_id,
+                err.to_string()
+            ),
+        };
+
+        reply(&notifier, &chat_id, locale.text(Text::BotAskMerchantCode)).await;
+    } else if message_text == "/clear" {
+        match conversation_store.delete(&key).await {
+            Ok(_) => Some(()),
+            Err(_) => None,
+        };
+
+        reply(&notifier, &chat_id, locale.text(Text::BotSendConnect)).await;
     } else {
-        let current_text = match cmd("GET").arg(&key).query::<Option<String>>(&mut con) {
+        let current_text = match conversation_store.get(&key).await {
             Ok(current_text) => current_text,
             Err(_) => None,
         };
@@ -184,8 +314,8 @@ pub async fn telegram(
                 match Merchant::get_by_merchant_code(&db, &message_text.to_lowercase()).await {
                     Ok(merchant) => merchant,
                     Err(err) => {
-                        let msg = "The merchant code is not valid, please check again.";
-                        telegram_send_message(&chat_id, &msg).await.unwrap();
+                        let msg = locale.text(Text::BotInvalidMerchantCode);
+                        reply(&notifier, &chat_id, &msg).await;
 
                         let body = DefaultResponse::error(&msg, err.to_string()).into_json();
 
@@ -193,79 +323,778 @@ pub async fn telegram(
                     }
                 };
 
-            let customer = match Customer::get_by_merchant_id_contact_channel(
+            let locale =
+                match connect_customer(&db, &notifier, &chat_id, locale, &merchant, &from_username)
+                    .await
+                {
+                    Ok(locale) => locale,
+                    Err(response) => return response,
+                };
+
+            match conversation_store.delete(&key).await {
+                Ok(_) => Some(()),
+                Err(_) => None,
+            };
+
+            let msg = locale.text(Text::BotRegistered);
+            reply(&notifier, &chat_id, msg).await;
+        } else {
+            let msg = locale.text(Text::BotSendConnect);
+            reply(&notifier, &chat_id, msg).await;
+        }
+    }
+
+    let body = DefaultResponse::ok("success webhook telegram").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Incoming WhatsApp messages forwarded by the gateway. Messages are kept in
+/// the conversation of every customer the number belongs to, the STOP, START
+/// and INVOICES keywords are answered and other replies are passed on to the
+/// merchants.
+pub async fn whatsapp(
+    State(db): State<PgPool>,
+    Extension(headers): Extension<Vec<(String, String)>>,
+    Extension(notifier): Extension<Arc<Notifier>>,
+    Json(payload): Json<WhatsappInboundMessage>,
+) -> Response {
+    let webhook_secret = std::env::var("WHATSAPP_WEBHOOK_SECRET").unwrap_or_default();
+
+    let is_valid_secret = headers
+        .iter()
+        .any(|(key, value)| key == "x-whatsapp-webhook-secret" && *value == webhook_secret);
+
+    if webhook_secret.is_empty() || !is_valid_secret {
+        let body =
+            DefaultResponse::error("invalid webhook secret", "invalid webhook secret".to_string())
+                .into_json();
+
+        return (StatusCode::UNAUTHORIZED, body).into_response();
+    }
+
+    // numbers are stored in E.164 without the +, whatever the provider sends
+    let number = contacts::normalize_phone(&payload.number, contacts::DEFAULT_COUNTRY_CODE)
+        .unwrap_or_else(|_| payload.number.clone());
+
+    let customer_contact_channels =
+        match CustomerContactChannel::get_by_value(&db, "whatsapp", &number).await {
+            Ok(customer_contact_channels) => customer_contact_channels,
+            Err(err) => {
+                let body = DefaultResponse::error("unable to get contact channels", err.to_string())
+                    .into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
+        };
+
+    record_whatsapp(
+        &db,
+        &customer_contact_channels,
+        &number,
+        "inbound",
+        &payload.message,
+    )
+    .await;
+
+    let locale = match customer_contact_channels.first() {
+        Some(customer_contact_channel) => {
+            customer_locale(&db, &customer_contact_channel.customer_id).await
+        }
+        None => Locale::default(),
+    };
+
+    let msg = match payload.message.trim().to_uppercase().as_str() {
+        keyword @ ("STOP" | "START") => {
+            let opted_out = keyword == "STOP";
+
+            let changed = match set_opted_out(
                 &db,
-                &merchant.id,
-                &"telegram".to_string(),
-                &from_username,
+                "whatsapp",
+                &number,
+                opted_out,
             )
             .await
             {
-                Ok(result) => result,
+                Ok(changed) => changed,
                 Err(err) => {
-                    let msg = "You're not registered in this merchant, please ask admin to register your telegram username.";
-                    telegram_send_message(&chat_id, &msg).await.unwrap();
-
-                    let body = DefaultResponse::error(&msg, err.to_string()).into_json();
+                    let body = DefaultResponse::error("unable to update consent", err.to_string())
+                        .into_json();
 
                     return (StatusCode::OK, body).into_response();
                 }
             };
 
-            match setup_verification(
-                &db,
-                None,
-                Some(customer.id),
-                customer.contact_channel_name,
-                chat_id.to_string(),
-            )
-            .await
-            {
-                Ok(_) => Some(()),
-                Err(err) => {
-                    let msg = "Unable to sent verification";
-                    telegram_send_message(&chat_id, &msg).await.unwrap();
+            for customer_contact_channel in changed.iter() {
+                println!(
+                    "Customer contact channel {} opted {} by whatsapp keyword",
+                    customer_contact_channel.id,
+                    if opted_out { "out" } else { "in" }
+                );
+            }
 
-                    let body = DefaultResponse::error(&msg, err.to_string()).into_json();
+            if opted_out {
+                locale.text(Text::WhatsappOptedOut).to_string()
+            } else {
+                locale.text(Text::WhatsappOptedIn).to_string()
+            }
+        }
+        "INVOICES" => match whatsapp_invoices(&db, locale, &customer_contact_channels).await {
+            Ok(msg) => msg,
+            Err(err) => {
+                let body = DefaultResponse::error("unable to get invoices", err.to_string())
+                    .into_json();
 
-                    return (StatusCode::OK, body).into_response();
+                return (StatusCode::OK, body).into_response();
+            }
+        },
+        _ => {
+            // free text like "sudah bayar" is for the merchant to read
+            for customer_contact_channel in customer_contact_channels.iter() {
+                if let Ok(customer) =
+                    Customer::get_by_id_only(&db, customer_contact_channel.customer_id).await
+                {
+                    merchant_alert::send(
+                        &db,
+                        &notifier,
+                        &customer.merchant_id,
+                        Text::MerchantAlertCustomerReplied,
+                        |_| {
+                            HashMap::from([
+                                ("customer_name", customer.name.clone()),
+                                ("message", payload.message.clone()),
+                            ])
+                        },
+                    )
+                    .await;
                 }
-            };
+            }
 
-            println!("{}", customer.contact_channel_id);
+            let body = DefaultResponse::ok("success webhook whatsapp").into_json();
 
-            match CustomerContactChannel::update_additional_value(
-                &db,
-                &customer.customer_contact_channel_id,
-                &chat_id.to_string(),
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    match notifier
+        .send("whatsapp", &Recipient::new(&number), &Notification::new(&msg))
+        .await
+    {
+        Ok(_) => {
+            record_whatsapp(&db, &customer_contact_channels, &number, "outbound", &msg)
+                .await
+        }
+        Err(err) => println!(
+            "Failed to reply to whatsapp {}: {}",
+            number,
+            err.to_string()
+        ),
+    }
+
+    let body = DefaultResponse::ok("success webhook whatsapp").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Keeps a WhatsApp message in the conversation of each customer the number
+/// belongs to, or without a customer when it belongs to none.
+/// STOP and START apply to the merchant that messaged the customer last, so a
+/// number shared by several merchants keeps hearing from the others. Before
+/// any merchant messaged it they apply to all of them.
+async fn set_opted_out(
+    db: &PgPool,
+    channel_name: &str,
+    value: &str,
+    opted_out: bool,
+) -> Result<Vec<CustomerContactChannel>, sqlx::Error> {
+    let merchant_id = Delivery::get_last_merchant_id_by_value(db, channel_name, value).await?;
+
+    CustomerContactChannel::set_opted_out_by_value(
+        db,
+        channel_name,
+        value,
+        merchant_id.as_ref(),
+        opted_out,
+    )
+    .await
+}
+
+async fn record_whatsapp(
+    db: &PgPool,
+    customer_contact_channels: &[CustomerContactChannel],
+    number: &str,
+    direction: &str,
+    message: &str,
+) {
+    let customers: Vec<(Option<Uuid>, Option<Uuid>)> = if customer_contact_channels.is_empty() {
+        vec![(None, None)]
+    } else {
+        customer_contact_channels
+            .iter()
+            .map(|channel| (Some(channel.id), Some(channel.customer_id)))
+            .collect()
+    };
+
+    for (customer_contact_channel_id, customer_id) in customers {
+        match ConversationMessage::create(
+            db,
+            customer_contact_channel_id,
+            customer_id,
+            "whatsapp",
+            direction,
+            number,
+            message,
+        )
+        .await
+        {
+            Ok(_) => (),
+            Err(err) => println!("Failed to record whatsapp message of {}: {}", number, err),
+        }
+    }
+}
+
+/// Answer to INVOICES, the unpaid invoices of all the customers of the number
+/// with their payment links since WhatsApp has no buttons.
+async fn whatsapp_invoices(
+    db: &PgPool,
+    locale: Locale,
+    customer_contact_channels: &[CustomerContactChannel],
+) -> Result<String, sqlx::Error> {
+    let customer_ids: Vec<Uuid> = customer_contact_channels
+        .iter()
+        .map(|channel| channel.customer_id)
+        .collect();
+
+    let invoices =
+        Invoice::get_by_customer_ids_and_status(db, &customer_ids, "unpaid", LIST_LIMIT).await?;
+
+    if invoices.is_empty() {
+        return Ok(locale.text(Text::BotNoInvoices).to_string());
+    }
+
+    let lines: Vec<String> = invoices
+        .iter()
+        .map(|invoice| {
+            let line = format!(
+                "{} - {} - {}",
+                invoice.invoice_number,
+                invoice.merchant_name,
+                locale.format_currency(invoice.total_amount as i64)
+            );
+
+            match &invoice.pay_url {
+                Some(pay_url) => format!(
+                    "{}\n{}: {}",
+                    line,
+                    locale.text(Text::InvoicePayNow),
+                    pay_url
+                ),
+                None => line,
+            }
+        })
+        .collect();
+
+    Ok(format!(
+        "{}\n\n{}",
+        locale.text(Text::BotInvoices),
+        lines.join("\n\n")
+    ))
+}
+
+/// Invoice callbacks of Xendit, keeps the payment status of invoices and
+/// stops their reminders once they are paid.
+pub async fn xendit(
+    State(db): State<PgPool>,
+    Extension(headers): Extension<Vec<(String, String)>>,
+    Extension(notifier): Extension<Arc<Notifier>>,
+    Json(payload): Json<XenditInvoiceCallback>,
+) -> Response {
+    let callback_token = std::env::var("XENDIT_CALLBACK_TOKEN").unwrap_or_default();
+
+    let is_valid_token = headers
+        .iter()
+        .any(|(key, value)| key == "x-callback-token" && is_same_token(value, &callback_token));
+
+    if callback_token.is_empty() || !is_valid_token {
+        let body =
+            DefaultResponse::error("invalid callback token", "invalid callback token".to_string())
+                .into_json();
+
+        return (StatusCode::UNAUTHORIZED, body).into_response();
+    }
+
+    let (status, paid_at) = match payload.status.as_str() {
+        "PAID" | "SETTLED" => (
+            "paid",
+            Some(
+                payload
+                    .paid_at
+                    .map(|paid_at| paid_at.naive_utc())
+                    .unwrap_or_else(|| chrono::Utc::now().naive_utc()),
+            ),
+        ),
+        "EXPIRED" => ("expired", None),
+        _ => {
+            let body = DefaultResponse::ok("success webhook xendit").into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    let invoice =
+        match Invoice::update_status_by_xendit_id(&db, &payload.id, status, paid_at).await {
+            Ok(Some(invoice)) => invoice,
+            // a redelivered callback or an invoice that isn't ours, the alert
+            // and events were sent when the status first changed
+            Ok(None) => {
+                let body = DefaultResponse::ok("success webhook xendit").into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
+            Err(err) => {
+                let body = DefaultResponse::error(
+                    format!("update invoice {} failed", payload.external_id).as_str(),
+                    err.to_string(),
+                )
+                .into_json();
+
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+        };
+
+    if invoice.status == "paid" {
+        let invoice_id = invoice.id.to_string();
+        let created_by = invoice.created_by.to_string();
+
+        // nothing is scheduled for invoices that were never sent
+        let _ = JobSchedule::update_status_by_invoice_id(&db, "completed", &invoice_id, &created_by)
+            .await;
+        let _ =
+            JobQueue::update_status_by_invoice_id(&db, "completed", &invoice_id, &created_by).await;
+
+        let customer_name = match Customer::get_by_id_only(&db, invoice.customer_id).await {
+            Ok(customer) => customer.name,
+            Err(_) => String::new(),
+        };
+
+        merchant_alert::send(
+            &db,
+            &notifier,
+            &invoice.merchant_id,
+            Text::MerchantAlertInvoicePaid,
+            |locale| {
+                HashMap::from([
+                    ("invoice_number", invoice.invoice_number.clone()),
+                    ("customer_name", customer_name),
+                    ("amount", locale.format_currency(invoice.total_amount as i64)),
+                ])
+            },
+        )
+        .await;
+    }
+
+    if invoice.status == "paid" || invoice.status == "expired" {
+        let event_type = format!("invoice.{}", invoice.status);
+
+        webhooks::emit(&db, &invoice.merchant_id, &event_type, json!(invoice)).await;
+    }
+
+    let body = DefaultResponse::ok("success webhook xendit")
+        .with_data(json!({
+            "invoice_id": invoice.id,
+            "status": invoice.status,
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Compares the callback token in constant time so it can't be guessed byte
+/// by byte. Both sides are hashed first, which also hides the token's length.
+fn is_same_token(value: &str, token: &str) -> bool {
+    let mac = |input: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"x-callback-token")
+            .expect("HMAC takes keys of any size");
+        mac.update(input.as_bytes());
+        mac
+    };
+
+    mac(value)
+        .verify_slice(&mac(token).finalize().into_bytes())
+        .is_ok()
+}
+
+/// `/start <payload>` from a `t.me/<bot>?start=<payload>` link, the payload
+/// is either an invite of one customer, a link a merchant user made from the
+/// dashboard or the code of a merchant.
+async fn start_link(
+    db: &PgPool,
+    notifier: &Notifier,
+    conversation_store: &dyn ConversationStore,
+    chat_id: &i64,
+    locale: Locale,
+    payload: &str,
+    from_username: &str,
+) -> Response {
+    if let Ok(invite) = TelegramInvite::get_by_token(db, payload).await {
+        return accept_invite(db, notifier, chat_id, locale, invite, from_username).await;
+    }
+
+    let user_link_key = user_link_key(payload);
+    let user_id = match conversation_store.get(&user_link_key).await {
+        Ok(user_id) => user_id.and_then(|user_id| Uuid::parse_str(&user_id).ok()),
+        Err(_) => None,
+    };
+
+    if let Some(user_id) = user_id {
+        match conversation_store.delete(&user_link_key).await {
+            Ok(_) => Some(()),
+            Err(_) => None,
+        };
+
+        return link_user(db, notifier, chat_id, &user_id).await;
+    }
+
+    let merchant = match Merchant::get_by_merchant_code(db, &payload.to_lowercase()).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            let msg = locale.text(Text::BotInvalidMerchantCode);
+            reply(notifier, chat_id, msg).await;
+
+            let body = DefaultResponse::error(msg, err.to_string()).into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    let locale =
+        match connect_customer(db, notifier, chat_id, locale, &merchant, from_username).await {
+            Ok(locale) => locale,
+            Err(response) => return response,
+        };
+
+    reply(notifier, chat_id, locale.text(Text::BotRegistered)).await;
+
+    let body = DefaultResponse::ok("success webhook telegram").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Links the chat to the merchant user, they get the merchant alerts in it
+/// from now on.
+async fn link_user(db: &PgPool, notifier: &Notifier, chat_id: &i64, user_id: &Uuid) -> Response {
+    match User::update_telegram_chat_id(db, user_id, Some(*chat_id)).await {
+        Ok(_) => (),
+        Err(err) => {
+            let body = DefaultResponse::error("unable to link user", err.to_string()).into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    }
+
+    let merchant_chat = match MerchantChat::load(db, chat_id).await {
+        Ok(Some(merchant_chat)) => merchant_chat,
+        Ok(None) => {
+            let body =
+                DefaultResponse::error("unable to link user", "user not found".to_string())
+                    .into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+        Err(err) => {
+            let body = DefaultResponse::error("unable to link user", err.to_string()).into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    reply_notification(notifier, chat_id, &merchant_chat.linked()).await;
+
+    let body = DefaultResponse::ok("success webhook telegram").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Links the chat to the invited customer, the merchant created the invite
+/// for them so no verification message is needed.
+async fn accept_invite(
+    db: &PgPool,
+    notifier: &Notifier,
+    chat_id: &i64,
+    locale: Locale,
+    invite: TelegramInvite,
+    from_username: &str,
+) -> Response {
+    let now = chrono::Utc::now().naive_utc();
+
+    if !invite.is_valid(&now) {
+        let msg = locale.text(Text::BotInviteInvalid);
+        reply(notifier, chat_id, msg).await;
+
+        let body = DefaultResponse::error(msg, invite.token).into_json();
+
+        return (StatusCode::OK, body).into_response();
+    }
+
+    let customer_contact_channels =
+        match CustomerContactChannel::get_customer_contact_channels_by_customer_and_merchant(
+            db,
+            &invite.customer_id,
+            &invite.merchant_id,
+        )
+        .await
+        {
+            Ok(customer_contact_channels) => customer_contact_channels,
+            Err(err) => {
+                let body =
+                    DefaultResponse::error("unable to get contact channels", err.to_string())
+                        .into_json();
+
+                return (StatusCode::OK, body).into_response();
+            }
+        };
+
+    let telegram_channel = customer_contact_channels
+        .iter()
+        .find(|customer_contact_channel| customer_contact_channel.name == "telegram");
+
+    let linked = match telegram_channel {
+        Some(customer_contact_channel) => CustomerContactChannel::update_additional_value(
+            db,
+            &customer_contact_channel.id,
+            &chat_id.to_string(),
+        )
+        .await
+        .map(|_| ()),
+        None => match ContactChannel::get_by_name(db, "telegram").await {
+            Ok(contact_channel) => CustomerContactChannel::create(
+                db,
+                &invite.customer_id,
+                &contact_channel.id,
+                &contacts::normalize_telegram_username(from_username)
+                    .unwrap_or_else(|_| from_username.to_string()),
+                Some(chat_id.to_string()),
             )
             .await
-            {
-                Ok(result) => result,
-                Err(err) => {
-                    let msg = "Unable to get customer contact channel";
-                    telegram_send_message(&chat_id, &msg).await.unwrap();
+            .map(|_| ()),
+            Err(err) => Err(err),
+        },
+    };
 
-                    let body = DefaultResponse::error(&msg, err.to_string()).into_json();
+    let locale = customer_locale(db, &invite.customer_id).await;
 
-                    return (StatusCode::OK, body).into_response();
-                }
-            };
+    if let Err(err) = linked {
+        let msg = locale.text(Text::BotContactChannelFailed);
+        reply(notifier, chat_id, msg).await;
 
-            match cmd("DEL").arg(&key).query::<Option<()>>(&mut con) {
-                Ok(_) => Some(()),
-                Err(_) => None,
-            };
+        let body = DefaultResponse::error(msg, err.to_string()).into_json();
 
-            let msg = "Thank you for register as customer";
-            telegram_send_message(&chat_id, msg).await.unwrap();
-        } else {
-            let msg = "Send /connect to connect to the merchant";
-            telegram_send_message(&chat_id, msg).await.unwrap();
+        return (StatusCode::OK, body).into_response();
+    }
+
+    match Customer::update_verified_at(db, &invite.customer_id, &now).await {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to verify customer {} from invite: {}",
+            invite.customer_id,
+            err.to_string()
+        ),
+    }
+
+    match TelegramInvite::mark_used(db, invite.id, chat_id).await {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to mark telegram invite {} used: {}",
+            invite.id,
+            err.to_string()
+        ),
+    }
+
+    if let Ok(customer) = Customer::get_by_id_only(db, invite.customer_id).await {
+        merchant_alert::send(
+            db,
+            notifier,
+            &invite.merchant_id,
+            Text::MerchantAlertCustomerConnected,
+            |_| HashMap::from([("customer_name", customer.name.clone())]),
+        )
+        .await;
+
+        webhooks::emit(db, &invite.merchant_id, "customer.verified", json!(customer)).await;
+    }
+
+    reply(notifier, chat_id, locale.text(Text::BotRegistered)).await;
+
+    let body = DefaultResponse::ok("success webhook telegram").into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Finds the merchant's customer registered with the Telegram username and
+/// sends them a verification message in the chat. Returns the customer's
+/// locale, or the response to end the webhook with.
+async fn connect_customer(
+    db: &PgPool,
+    notifier: &Notifier,
+    chat_id: &i64,
+    locale: Locale,
+    merchant: &Merchant,
+    from_username: &str,
+) -> Result<Locale, Response> {
+    let customer = match Customer::get_by_merchant_id_contact_channel(
+        db,
+        &merchant.id,
+        &"telegram".to_string(),
+        &from_username.to_string(),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(err) => {
+            let msg = locale.text(Text::BotNotRegistered);
+            reply(notifier, chat_id, msg).await;
+
+            let body = DefaultResponse::error(msg, err.to_string()).into_json();
+
+            return Err((StatusCode::OK, body).into_response());
+        }
+    };
+
+    let locale = Locale::resolve(customer.locale.as_deref(), &merchant.default_locale);
+
+    match setup_verification(
+        db,
+        notifier,
+        None,
+        Some(customer.id),
+        customer.contact_channel_name,
+        chat_id.to_string(),
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let msg = locale.text(Text::BotVerificationFailed);
+            reply(notifier, chat_id, msg).await;
+
+            let body = DefaultResponse::error(msg, err.to_string()).into_json();
+
+            return Err((StatusCode::OK, body).into_response());
         }
+    };
+
+    match CustomerContactChannel::update_additional_value(
+        db,
+        &customer.customer_contact_channel_id,
+        &chat_id.to_string(),
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => {
+            let msg = locale.text(Text::BotContactChannelFailed);
+            reply(notifier, chat_id, msg).await;
+
+            let body = DefaultResponse::error(msg, err.to_string()).into_json();
+
+            return Err((StatusCode::OK, body).into_response());
+        }
+    };
+
+    merchant_alert::send(
+        db,
+        notifier,
+        &merchant.id,
+        Text::MerchantAlertCustomerConnected,
+        |_| HashMap::from([("customer_name", customer.name.clone())]),
+    )
+    .await;
+
+    Ok(locale)
+}
+
+/// A button of an inline keyboard was pressed.
+async fn telegram_callback(
+    db: &PgPool,
+    notifier: &Notifier,
+    callback_query: TelegramCallbackQuery,
+) -> Response {
+    // stop the loading indicator of the button whatever the answer is
+    match telegram_answer_callback_query(&callback_query.id).await {
+        Ok(_) => (),
+        Err(err) => println!("Failed to answer callback query: {}", err.to_string()),
     }
 
+    let chat_id = match callback_query
+        .message
+        .as_ref()
+        .and_then(|message| message.chat.as_ref())
+        .and_then(|chat| chat.id)
+        .or_else(|| callback_query.from.as_ref().and_then(|from| from.id))
+    {
+        Some(chat_id) => chat_id,
+        None => {
+            let body = DefaultResponse::error(
+                "unable to get callback query chat id",
+                "unable to get callback query chat id".to_string(),
+            )
+            .into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    let locale = match callback_query
+        .from
+        .as_ref()
+        .and_then(|from| from.language_code.as_ref())
+    {
+        Some(language_code) => Locale::parse_or_default(language_code),
+        None => Locale::default(),
+    };
+
+    let bot_chat = match BotChat::load(db, &chat_id, locale).await {
+        Ok(bot_chat) => bot_chat,
+        Err(err) => {
+            let body = DefaultResponse::error("unable to load chat customers", err.to_string())
+                .into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    let data = callback_query.data.unwrap_or_default();
+
+    let notification = match bot_chat.callback(db, &data).await {
+        Ok(notification) => notification,
+        Err(err) => {
+            let body =
+                DefaultResponse::error("unable to answer callback query", err.to_string())
+                    .into_json();
+
+            return (StatusCode::OK, body).into_response();
+        }
+    };
+
+    reply_notification(notifier, &chat_id, &notification).await;
+
     let body = DefaultResponse::ok("success webhook telegram").into_json();
 
     (StatusCode::OK, body).into_response()
 }
+
+/// Answers in the chat the update came from. A failed answer is only logged,
+/// Telegram would redeliver the update if the webhook didn't return 200.
+async fn reply(notifier: &Notifier, chat_id: &i64, message: &str) {
+    reply_notification(notifier, chat_id, &Notification::new(message)).await
+}
+
+async fn reply_notification(notifier: &Notifier, chat_id: &i64, notification: &Notification) {
+    match notifier
+        .send("telegram", &Recipient::telegram_chat(chat_id), notification)
+        .await
+    {
+        Ok(_) => (),
+        Err(err) => println!("Failed to reply to telegram chat {}: {}", chat_id, err),
+    }
+}
@@ -0,0 +1,293 @@
+use crate::models::requests::webhook_subscription::RequestWebhookSubscription;
+use crate::models::responses::DefaultResponse;
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::models::webhook_subscription::WebhookSubscription;
+use crate::webhooks;
+use axum::extract::Path;
+use axum::response::{IntoResponse, Response};
+use axum::{extract::State, response::Json};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Deliveries listed per subscription, most recent first.
+const DELIVERIES_LIMIT: i64 = 50;
+
+pub async fn get_subscriptions(
+    State(db): State<PgPool>,
+    Path(merchant_id): Path<Uuid>,
+) -> Response {
+    let webhook_subscriptions = match WebhookSubscription::get_by_merchant_id(&db, &merchant_id)
+        .await
+    {
+        Ok(webhook_subscriptions) => webhook_subscriptions,
+        Err(err) => {
+            let body = DefaultResponse::error("get webhooks failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get webhooks success")
+        .with_data(json!({
+            "webhooks": webhook_subscriptions,
+            "event_types": webhooks::EVENT_TYPES,
+        }))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn create_subscription(
+    State(db): State<PgPool>,
+    Path(merchant_id): Path<Uuid>,
+    Json(body): Json<RequestWebhookSubscription>,
+) -> Response {
+    let kind = body.kind.clone().unwrap_or_else(|| "webhook".to_string());
+
+    if let Err(response) = validate_subscription(&body, &kind).await {
+        return response;
+    }
+
+    let secret = match &body.secret {
+        Some(secret) => secret.clone(),
+        None => generate_secret(),
+    };
+
+    let webhook_subscription = match WebhookSubscription::create(
+        &db,
+        &merchant_id,
+        &kind,
+        &body.url,
+        &secret,
+        &body.event_types,
+        body.is_active.unwrap_or(true),
+    )
+    .await
+    {
+        Ok(webhook_subscription) => webhook_subscription,
+        Err(err) => {
+            let body = DefaultResponse::error("create webhook failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("create webhook success")
+        .with_data(json!(webhook_subscription))
+        .into_json();
+
+    (StatusCode::CREATED, body).into_response()
+}
+
+pub async fn update_subscription(
+    State(db): State<PgPool>,
+    Path((merchant_id, webhook_subscription_id)): Path<(Uuid, i32)>,
+    Json(body): Json<RequestWebhookSubscription>,
+) -> Response {
+    let webhook_subscription =
+        match WebhookSubscription::get_by_id(&db, webhook_subscription_id, &merchant_id).await {
+            Ok(webhook_subscription) => webhook_subscription,
+            Err(err) => {
+                let body = DefaultResponse::error("webhook not found", err.to_string()).into_json();
+
+                return (StatusCode::NOT_FOUND, body).into_response();
+            }
+        };
+
+    let kind = body.kind.clone().unwrap_or(webhook_subscription.kind);
+
+    if let Err(response) = validate_subscription(&body, &kind).await {
+        return response;
+    }
+
+    let secret = body.secret.clone().unwrap_or(webhook_subscription.secret);
+
+    let webhook_subscription = match WebhookSubscription::update(
+        &db,
+        webhook_subscription_id,
+        &merchant_id,
+        &kind,
+        &body.url,
+        &secret,
+        &body.event_types,
+        body.is_active.unwrap_or(webhook_subscription.is_active),
+    )
+    .await
+    {
+        Ok(webhook_subscription) => webhook_subscription,
+        Err(err) => {
+            let body = DefaultResponse::error("update webhook failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("update webhook success")
+        .with_data(json!(webhook_subscription))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn delete_subscription(
+    State(db): State<PgPool>,
+    Path((merchant_id, webhook_subscription_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let webhook_subscription =
+        match WebhookSubscription::delete(&db, webhook_subscription_id, &merchant_id).await {
+            Ok(webhook_subscription) => webhook_subscription,
+            Err(err) => {
+                let body = DefaultResponse::error("webhook not found", err.to_string()).into_json();
+
+                return (StatusCode::NOT_FOUND, body).into_response();
+            }
+        };
+
+    let body = DefaultResponse::ok("delete webhook success")
+        .with_data(json!(webhook_subscription))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+pub async fn get_deliveries(
+    State(db): State<PgPool>,
+    Path((merchant_id, webhook_subscription_id)): Path<(Uuid, i32)>,
+) -> Response {
+    if let Err(err) =
+        WebhookSubscription::get_by_id(&db, webhook_subscription_id, &merchant_id).await
+    {
+        let body = DefaultResponse::error("webhook not found", err.to_string()).into_json();
+
+        return (StatusCode::NOT_FOUND, body).into_response();
+    }
+
+    let webhook_deliveries = match WebhookDelivery::get_by_subscription_id(
+        &db,
+        webhook_subscription_id,
+        DELIVERIES_LIMIT,
+    )
+    .await
+    {
+        Ok(webhook_deliveries) => webhook_deliveries,
+        Err(err) => {
+            let body = DefaultResponse::error("get webhook deliveries failed", err.to_string())
+                .into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok("get webhook deliveries success")
+        .with_data(json!(webhook_deliveries))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+/// Posts a `ping` event right away, without retries, so the merchant can
+/// check the endpoint and its signature verification.
+pub async fn test_subscription(
+    State(db): State<PgPool>,
+    Path((merchant_id, webhook_subscription_id)): Path<(Uuid, i32)>,
+) -> Response {
+    let webhook_subscription =
+        match WebhookSubscription::get_by_id(&db, webhook_subscription_id, &merchant_id).await {
+            Ok(webhook_subscription) => webhook_subscription,
+            Err(err) => {
+                let body = DefaultResponse::error("webhook not found", err.to_string()).into_json();
+
+                return (StatusCode::NOT_FOUND, body).into_response();
+            }
+        };
+
+    let webhook_delivery = match webhooks::create_delivery(
+        &db,
+        &webhook_subscription,
+        webhooks::PING,
+        json!({ "webhook_id": webhook_subscription.id }),
+    )
+    .await
+    {
+        Ok(webhook_delivery) => webhook_delivery,
+        Err(err) => {
+            let body = DefaultResponse::error("test webhook failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let attempt = webhooks::post(&webhook_subscription, &webhook_delivery).await;
+    let status = if attempt.is_delivered() {
+        "delivered"
+    } else {
+        "failed"
+    };
+
+    let webhook_delivery = match WebhookDelivery::record_attempt(
+        &db,
+        webhook_delivery.id,
+        status,
+        attempt.response_status,
+        attempt.response_body,
+        attempt.error,
+    )
+    .await
+    {
+        Ok(webhook_delivery) => webhook_delivery,
+        Err(err) => {
+            let body = DefaultResponse::error("test webhook failed", err.to_string()).into_json();
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+    };
+
+    let body = DefaultResponse::ok(format!("test webhook {}", status).as_str())
+        .with_data(json!(webhook_delivery))
+        .into_json();
+
+    (StatusCode::OK, body).into_response()
+}
+
+async fn validate_subscription(
+    body: &RequestWebhookSubscription,
+    kind: &str,
+) -> Result<(), Response> {
+    match validator::Validate::validate(body) {
+        Ok(_) => (),
+        Err(err) => {
+            let body =
+                DefaultResponse::error(err.to_string().as_str(), err.to_string()).into_json();
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    }
+
+    match body.validate_kind_event_types(kind) {
+        Ok(_) => (),
+        Err(err) => {
+            let body = DefaultResponse::error(err.as_str(), kind.to_string()).into_json();
+
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response());
+        }
+    }
+
+    match webhooks::resolve_public(&body.url).await {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let body = DefaultResponse::error("invalid url", err).into_json();
+
+            Err((StatusCode::UNPROCESSABLE_ENTITY, body).into_response())
+        }
+    }
+}
+
+fn generate_secret() -> String {
+    let secret = rand::Rng::sample_iter(rand::thread_rng(), &rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect::<String>();
+
+    format!("whsec_{}", secret)
+}
@@ -1,26 +1,35 @@
-use std::ops::Add;
+use std::{sync::Arc, time::Duration};
 
-use chrono::{Duration, Utc};
-use cron::Schedule;
-use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
-use rand::Rng;
-use serde_json::Value;
+use chrono_tz::Tz;
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
     errors::Errors,
+    locale::Locale,
     models::{
-        customer_contact_channel::CustomerContactChannel, invoice::Invoice, job_queue::JobQueue,
+        conversation_message::ConversationMessage,
+        customer::Customer,
+        customer_contact_channel::{
+            CustomerContactChannel, CustomerContactChannelWithContactChannel,
+        },
+        delivery::Delivery,
+        job_queue::JobQueue,
+        job_run::JobRun,
         job_schedule::JobSchedule,
+        merchant::Merchant,
+        merchant_rate_limit::MerchantRateLimit,
     },
-    repositories::{
-        invoice::send_invoice_to_xendit, telegram::telegram_send_message,
-        whatsapp::whatsapp_send_message,
-    },
+    notifications::{unsubscribe, Recipient},
+    templates::ChannelMessages,
+};
+
+use super::{
+    context::JobContext,
+    registry::{JobError, JobRegistry},
 };
 
-pub async fn set_job_schedule_to_queue(pool: PgPool) {
+pub async fn set_job_schedule_to_queue(pool: PgPool, registry: Arc<JobRegistry>) {
     let job_schedules = match JobSchedule::get_scheduled_jobs(&pool).await {
         Ok(job_schedules) => job_schedules,
         Err(_) => {
@@ -28,9 +37,23 @@ pub async fn set_job_schedule_to_queue(pool: PgPool) {
         }
     };
 
-    for mut job_schedule in job_schedules {
+    for job_schedule in job_schedules {
         let job_schedule_id = job_schedule.id;
 
+        let handler = match registry.get(&job_schedule.job_type) {
+            Some(handler) => handler,
+            None => {
+                match JobSchedule::update_status(&pool, job_schedule_id, "failed").await {
+                    Ok(_) => (),
+                    Err(_) => {
+                        return;
+                    }
+                };
+
+                continue;
+            }
+        };
+
         match JobSchedule::update_status(&pool, job_schedule_id, "pending").await {
             Ok(_) => (),
             Err(_) => {
@@ -38,12 +61,6 @@ pub async fn set_job_schedule_to_queue(pool: PgPool) {
             }
         };
 
-        let priority = match job_schedule.job_type.as_str() {
-            "send_invoice" => 0,
-            "send_reminder" => 1,
-            _ => 10,
-        };
-
         let is_queue_empty =
             match JobQueue::get_queue_not_completed_by_schedule_id(&pool, job_schedule_id).await {
                 Ok(job_queues) => job_queues.len() == 0,
@@ -54,29 +71,19 @@ pub async fn set_job_schedule_to_queue(pool: PgPool) {
             continue;
         }
 
-        if job_schedule.job_type == "send_invoice" {
-            let job_data = match job_schedule.job_data {
-                Some(job_data) => job_data,
-                None => {
-                    return;
-                }
-            };
-
-            job_schedule.job_data =
-                match set_job_schedule_send_invoice(&pool, job_data, job_schedule_id).await {
-                    Ok(job_data) => Some(job_data),
-                    Err(_) => {
-                        return;
-                    }
-                };
-        }
+        let job_data = match handler.prepare(&pool, &job_schedule).await {
+            Ok(job_data) => job_data,
+            Err(_) => {
+                return;
+            }
+        };
 
         match JobQueue::create(
             &pool,
             &job_schedule.job_type,
-            job_schedule.job_data,
+            Some(job_data),
             Some(job_schedule_id),
-            priority,
+            handler.priority(),
             "pending",
         )
         .await
@@ -89,50 +96,62 @@ pub async fn set_job_schedule_to_queue(pool: PgPool) {
     }
 }
 
-pub async fn prepare_via_channels(
-    pool: &PgPool,
-    job_schedule: &JobSchedule,
-    schedule: &Schedule,
-) -> Result<(), Errors> {
-    let job_data = match &job_schedule.job_data {
-        Some(job_data) => job_data,
-        None => {
-            return Err(Errors::new(&[(
-                "prepare_via_channels",
-                "unable to get job data",
-            )]));
-        }
-    };
+pub async fn merchant_timezone(pool: &PgPool, merchant_id: &Uuid) -> Result<Tz, Errors> {
+    match Merchant::get_by_id(&pool, *merchant_id).await {
+        Ok(merchant) => Ok(merchant.tz()),
+        Err(_) => Err(Errors::new(&[(
+            "prepare_via_channels",
+            "Failed to get merchant",
+        )])),
+    }
+}
 
-    let customer_id = match job_data["customer_id"].as_str() {
-        Some(phone_number) => uuid::Uuid::parse_str(phone_number).unwrap(),
-        None => {
+/// The customer's locale, or the merchant's default when the customer has
+/// none.
+pub async fn customer_locale(
+    pool: &PgPool,
+    customer_id: &Uuid,
+    merchant_id: &Uuid,
+) -> Result<Locale, Errors> {
+    let merchant = match Merchant::get_by_id(&pool, *merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(_) => {
             return Err(Errors::new(&[(
                 "prepare_via_channels",
-                "Failed to prepare invoice",
-            )]));
+                "Failed to get merchant",
+            )]))
         }
     };
 
-    let merchant_id = match job_data["merchant_id"].as_str() {
-        Some(merchant_id) => uuid::Uuid::parse_str(merchant_id).unwrap(),
-        None => {
-            return Err(Errors::new(&[(
-                "prepare_via_channels",
-                "Failed to prepare invoice",
-            )]));
-        }
-    };
+    match Customer::get_by_id(&pool, *customer_id, merchant_id).await {
+        Ok(customer) => Ok(customer.locale(&merchant)),
+        Err(_) => Err(Errors::new(&[(
+            "prepare_via_channels",
+            "Failed to get customer",
+        )])),
+    }
+}
 
-    let merchant_name = match job_data["merchant_name"].as_str() {
-        Some(merchant_name) => merchant_name.to_string(),
-        None => {
-            return Err(Errors::new(&[(
-                "prepare_via_channels",
-                "Failed to prepare invoice",
-            )]));
-        }
-    };
+/// Sends each contact channel the customer registered with the merchant its
+/// message and records each attempt as a delivery of `run`.
+///
+/// Channels that already received or were suppressed the message on an
+/// earlier run of the same queued job are skipped, so a retry only resends the
+/// failed ones. Channels
+/// over their rate limit are left for the job to be re-queued. Channels the
+/// customer opted out of are recorded as suppressed and never sent to, emails
+/// carry the link to opt out.
+///
+/// Returns whether the customer has received the message on any channel, on
+/// this run or an earlier one.
+pub async fn deliver_to_customer(
+    ctx: &JobContext,
+    run: &JobRun,
+    customer_id: &Uuid,
+    merchant_id: &Uuid,
+    messages: &ChannelMessages,
+) -> Result<bool, JobError> {
+    let pool = &ctx.pool;
 
     let customer_contact_channels =
         match CustomerContactChannel::get_customer_contact_channels_by_customer_and_merchant(
@@ -147,295 +166,225 @@ pub async fn prepare_via_channels(
                 return Err(Errors::new(&[(
                     "prepare_via_channels",
                     "Failed to prepare invoice",
-                )]));
+                )])
+                .into());
             }
         };
 
-    let mut message = String::new();
-
-    if job_schedule.job_type == "send_invoice" {
-        message = match message_builder_invoice(&pool, job_data.clone(), &merchant_name).await {
-            Ok(message) => message,
+    let settled_channels =
+        match Delivery::get_settled_channels_by_job_queue_id(&pool, run.job_queue_id).await {
+            Ok(settled_channels) => settled_channels,
             Err(_) => {
                 return Err(Errors::new(&[(
                     "prepare_via_channels",
-                    "Failed to prepare invoice",
-                )]));
+                    "Failed to get previous deliveries",
+                )])
+                .into());
             }
         };
-    } else if job_schedule.job_type == "send_reminder" {
-        message = match message_builder_reminder(job_data.clone(), &merchant_name) {
-            Ok(message) => message,
-            Err(_) => {
-                return Err(Errors::new(&[(
-                    "prepare_via_channels",
-                    "Failed to prepare reminder",
-                )]));
-            }
-        };
-    };
 
-    for contact_channel in customer_contact_channels.iter() {
-        if contact_channel.name == "whatsapp" {
-            let now = Utc::now();
-
-            let schedule = schedule.upcoming(Utc).take(1);
-            let datetime = schedule.last().unwrap();
+    let merchant_rate_limits = match MerchantRateLimit::get_by_merchant_id(&pool, &merchant_id).await
+    {
+        Ok(merchant_rate_limits) => merchant_rate_limits,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "prepare_via_channels",
+                "Failed to get merchant rate limits",
+            )])
+            .into());
+        }
+    };
 
-            if datetime >= now {
-                match whatsapp_send_message(contact_channel.value.as_str(), message.as_str()).await
-                {
-                    Ok(_) => (),
-                    Err(_) => {
-                        return Err(Errors::new(&[(
-                            "prepare_via_channels",
-                            "Failed to send message",
-                        )]));
-                    }
-                }
-            }
-        } else if contact_channel.name == "email" {
-            let email = Message::builder()
-                .from("Reminder <hello@inving.co>".parse().unwrap())
-                .to(contact_channel.value.parse().unwrap())
-                .subject("Reminder")
-                .body(message.clone())
-                .unwrap();
-
-            let password = std::env::var("EMAIL_SENDGRID_API_KEY").unwrap();
-            let creds = Credentials::new("apikey".to_string(), password);
-
-            let mailer = SmtpTransport::relay("smtp.sendgrid.net")
-                .unwrap()
-                .credentials(creds)
-                .build();
-
-            match mailer.send(&email) {
-                Ok(_) => println!("Email sent successfully!"),
-                Err(e) => panic!("Could not send email: {:?}", e),
-            }
-        } else if contact_channel.name == "telegram" {
-            let chat_id = if contact_channel.additional_value.is_some() {
-                contact_channel
-                    .additional_value
-                    .clone()
-                    .unwrap()
-                    .parse::<i64>()
-                    .expect("format contact value invalid")
-            } else {
-                return Err(Errors::new(&[(
-                    "no_additional_value",
-                    "No additional value registered",
-                )]));
-            };
+    let mut failed = false;
+    let mut reached = false;
+    let mut throttled: Option<(String, Duration)> = None;
+    let mut locale: Option<Locale> = None;
 
-            match telegram_send_message(&chat_id, message.as_str()).await {
-                Ok(_) => (),
-                Err(_) => {
-                    return Err(Errors::new(&[(
-                        "prepare_via_channels",
-                        "Failed to send message",
-                    )]));
-                }
-            }
+    for contact_channel in customer_contact_channels.iter() {
+        if let Some(sent) = settled_channels.get(&contact_channel.id) {
+            reached = reached || *sent;
+            continue;
         }
-    }
 
-    Ok(())
-}
+        if contact_channel.opted_out_at.is_some() {
+            println!(
+                "Skipping {} {} of customer {} in job run {}, opted out",
+                contact_channel.name, contact_channel.value, customer_id, run.id
+            );
 
-// generate constant vector of messages
-// generate random number between 0 to 9
-// return random message from vector
-fn generate_message() -> String {
-    let messages = [
-        "Hello {}, {} here, as a reminder, we ask that you please make a payment of *{}* to avoid any late fees. The payment can be made at the following link: {}. The due date for this payment is {}.",
-        "Hello {}, {} here, to avoid incurring late fees, we request that you make a payment of *{}* as soon as possible. You can easily do so by following this payment link: {}. The deadline for this payment is {}.",
-        "Hello {}, {} here, we strongly encourage you to make a payment of *{}* by the due date of {} to avoid late fees. You can make the payment by clicking on the following link: {}.",
-        "Hello {}, {} here, to avoid being charged late fees, we request that you make a payment of *{}* by {}. You can access the payment link here: {}.",
-        "Hello {}, {} here, please make a payment of *{}* by the due date of {} to avoid late fees. You can make the payment at the following link: {}.",
-        "Hello {}, {} here, we request that you make a payment of *{}* as soon as possible to avoid any late fees. The payment link can be found here: {}. Please note that the payment is due on {}.",
-        "Hello {}, {} here, to avoid late fees, we ask that you make a payment of *{}* by the due date of {}. You can make the payment using the following link: {}.",
-        "Hello {}, {} here, as a reminder, a payment of *{}* is due on {} to avoid late fees. You can make the payment at the following link: {}.",
-        "Hello {}, {} here, we request that you make a payment of *{}* by {} to avoid any late fees. The payment link is available here: {}.",
-        "Hello {}, {} here, to avoid being charged late fees, we ask that you make a payment of *{}* as soon as possible. The payment link is provided here: {}. Please note that the payment is due on {}.",
-    ];
-
-    let random_number = rand::thread_rng().gen_range(0..10);
-    messages[random_number].to_string()
-}
+            record_delivery(pool, run, contact_channel, "suppressed", None, None, None).await;
 
-async fn set_job_schedule_send_invoice(
-    pool: &PgPool,
-    job_data: Value,
-    job_schedule_id: i32,
-) -> Result<Value, Errors> {
-    let invoice_id = job_data["invoice_id"].as_str().unwrap();
-    let invoice_id = Uuid::parse_str(invoice_id).unwrap();
-
-    let invoice = match Invoice::get_by_id(&pool, &invoice_id).await {
-        Ok(invoice) => invoice,
-        Err(_) => {
-            return Err(Errors::new(&[("setup_invoice", "Failed to get invoice")]));
+            continue;
         }
-    };
 
-    // update invoice date to today
-    let invoice_date = Utc::now().naive_utc();
-    match Invoice::update_invoice_date(&pool, &invoice.id, &invoice_date).await {
-        Ok(invoice) => invoice,
-        Err(_) => {
-            return Err(Errors::new(&[(
-                "setup_invoice",
-                "Failed to update invoice date",
-            )]));
-        }
-    };
+        let merchant_limit = merchant_rate_limits
+            .iter()
+            .find(|rate_limit| rate_limit.channel == contact_channel.name)
+            .map(|rate_limit| rate_limit.rate_limit());
 
-    let mut job_data = job_data;
-    job_data["invoice_date"] = Value::String(invoice_date.to_string());
+        // the channel is retried with the job once the bucket refills
+        if let Err(retry_after) =
+            ctx.rate_limiter
+                .try_acquire(&contact_channel.name, merchant_id, merchant_limit)
+        {
+            let is_longer = match &throttled {
+                Some((_, longest)) => retry_after > *longest,
+                None => true,
+            };
 
-    match JobSchedule::update_job_data(&pool, job_schedule_id, &job_data).await {
-        Ok(_) => (),
-        Err(_) => {
-            return Err(Errors::new(&[(
-                "setup_invoice",
-                "Failed to update job data",
-            )]));
-        }
-    };
+            if is_longer {
+                throttled = Some((contact_channel.name.clone(), retry_after));
+            }
 
-    let result = match send_invoice_to_xendit(
-        &invoice.invoice_number,
-        &invoice.total_amount,
-        &invoice.to_string(),
-    )
-    .await
-    {
-        Ok(payload) => payload,
-        Err(_) => {
-            return Err(Errors::new(&[(
-                "setup_invoice",
-                "Failed to send invoice to xendit",
-            )]));
+            continue;
         }
-    };
 
-    match Invoice::update_xendit_invoice_payload(&pool, &invoice.id, &result).await {
-        Ok(invoice) => invoice,
-        Err(_) => {
-            return Err(Errors::new(&[(
-                "setup_invoice",
-                "Failed to update xendit invoice payload",
-            )]));
-        }
-    };
+        let mut notification = messages.for_channel(&contact_channel.name).clone();
 
-    Ok(job_data)
-}
+        if contact_channel.name == "email" {
+            let customer_locale = match locale {
+                Some(locale) => locale,
+                None => customer_locale(pool, customer_id, merchant_id).await?,
+            };
+            locale = Some(customer_locale);
 
-async fn message_builder_invoice(
-    pool: &PgPool,
-    job_data: Value,
-    merchant_name: &str,
-) -> Result<String, Errors> {
-    let invoice_id = match job_data["invoice_id"].as_str() {
-        Some(invoice_id) => uuid::Uuid::parse_str(invoice_id).unwrap(),
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_invoice",
-                "Failed to prepare invoice",
-            )]));
+            notification =
+                unsubscribe::with_link(&notification, &contact_channel.id, customer_locale);
         }
-    };
 
-    let invoice = match Invoice::get_by_id(&pool, &invoice_id).await {
-        Ok(invoice) => invoice,
-        Err(_) => {
-            return Err(Errors::new(&[(
-                "message_builder_invoice",
-                "Failed to prepare invoice",
-            )]));
-        }
-    };
+        let result = {
+            let _permit = ctx.limiter.acquire(&contact_channel.name).await;
 
-    let total_amount = match job_data["total_amount"].as_i64() {
-        Some(total_amount) => total_amount,
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_invoice",
-                "Failed to prepare invoice",
-            )]));
-        }
-    };
+            ctx.notifier
+                .send(
+                    &contact_channel.name,
+                    &Recipient::from(contact_channel),
+                    &notification,
+                )
+                .await
+        };
 
-    let customer_name = match job_data["customer_name"].as_str() {
-        Some(customer_name) => customer_name,
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_reminder",
-                "Failed to prepare reminder",
-            )]));
+        if result.is_ok() && contact_channel.name == "whatsapp" {
+            record_conversation(pool, customer_id, contact_channel, &notification.body).await;
         }
-    };
-
-    let xendit_invoice_payload = invoice.xendit_invoice_payload.unwrap();
-    let invoice_url = xendit_invoice_payload["invoice_url"].as_str().unwrap();
 
-    let now = Utc::now();
-    let due_time = &now.add(Duration::hours(24));
-    let due_time = format!("{}", due_time.format("%d/%m/%Y - %H:%M"));
+        let (status, provider_message_id, provider_response, error) = match result {
+            Ok(response) => {
+                reached = true;
+                ("sent", provider_message_id(&response), Some(response), None)
+            }
+            Err(err) => {
+                failed = true;
+                ("failed", None, None, Some(err.to_string()))
+            }
+        };
 
-    let total_amount = format!("Rp{:.2}", total_amount);
+        record_delivery(
+            pool,
+            run,
+            contact_channel,
+            status,
+            provider_message_id,
+            provider_response,
+            error,
+        )
+        .await;
+    }
 
-    let msg = generate_message();
+    if failed {
+        return Err(Errors::new(&[(
+            "prepare_via_channels",
+            "Failed to send message",
+        )])
+        .into());
+    }
 
-    let msg = msg.replacen("{}", &customer_name, 1);
-    let msg = msg.replacen("{}", &merchant_name, 1);
-    let msg = msg.replacen("{}", &total_amount, 1);
-    let msg = msg.replacen("{}", &invoice_url, 1);
-    let msg = msg.replacen("{}", &due_time, 1);
+    if let Some((channel, retry_after)) = throttled {
+        return Err(JobError::Throttled {
+            channel,
+            retry_after,
+        });
+    }
 
-    Ok(msg)
+    Ok(reached)
 }
 
-fn message_builder_reminder(job_data: Value, merchant_name: &str) -> Result<String, Errors> {
-    let title = match job_data["title"].as_str() {
-        Some(title) => title,
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_reminder",
-                "Failed to prepare reminder",
-            )]));
-        }
-    };
+async fn record_delivery(
+    pool: &PgPool,
+    run: &JobRun,
+    contact_channel: &CustomerContactChannelWithContactChannel,
+    status: &str,
+    provider_message_id: Option<String>,
+    provider_response: Option<String>,
+    error: Option<String>,
+) {
+    match Delivery::create(
+        pool,
+        run.id,
+        run.job_queue_id,
+        &contact_channel.id,
+        &contact_channel.name,
+        &contact_channel.value,
+        status,
+        provider_message_id,
+        provider_response,
+        error,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => println!("Failed to record delivery of job run {}: {}", run.id, err),
+    }
+}
 
-    let description = match job_data["description"].as_str() {
-        Some(description) => description,
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_reminder",
-                "Failed to prepare reminder",
-            )]));
-        }
-    };
+/// Keeps a message sent on a two-way channel in the conversation with the
+/// customer, next to their replies.
+async fn record_conversation(
+    pool: &PgPool,
+    customer_id: &Uuid,
+    contact_channel: &CustomerContactChannelWithContactChannel,
+    body: &str,
+) {
+    match ConversationMessage::create(
+        pool,
+        Some(contact_channel.id),
+        Some(*customer_id),
+        &contact_channel.name,
+        "outbound",
+        &contact_channel.value,
+        body,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to record conversation of customer {}: {}",
+            customer_id, err
+        ),
+    }
+}
 
-    let customer_name = match job_data["customer_name"].as_str() {
-        Some(customer_name) => customer_name,
-        None => {
-            return Err(Errors::new(&[(
-                "message_builder_reminder",
-                "Failed to prepare reminder",
-            )]));
-        }
+/// Picks the message id out of a provider response, SMTP responses are kept
+/// as they are since they carry the queue id.
+fn provider_message_id(response: &str) -> Option<String> {
+    let value = match serde_json::from_str::<serde_json::Value>(response) {
+        Ok(value) => value,
+        Err(_) if !response.is_empty() => return Some(response.chars().take(255).collect()),
+        Err(_) => return None,
     };
 
-    let msg = "Hello {}, {} here, we have a message for you \"{}\", \"{}\".".to_string();
-
-    let msg = msg.replacen("{}", &customer_name, 1);
-    let msg = msg.replacen("{}", &merchant_name, 1);
-    let msg = msg.replacen("{}", &title, 1);
-    let msg = msg.replacen("{}", &description, 1);
-
-    Ok(msg)
+    let id = [
+        &value["result"]["message_id"],
+        &value["message_id"],
+        &value["data"]["id"],
+        &value["id"],
+    ]
+    .iter()
+    .find(|id| !id.is_null())
+    .copied()?;
+
+    match id {
+        serde_json::Value::String(id) => Some(id.clone()),
+        id => Some(id.to_string()),
+    }
 }
@@ -0,0 +1,57 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use sqlx::PgPool;
+
+use crate::{models::job_queue::JobQueue, notifications::Notifier};
+
+use super::{limiter::ChannelLimiter, rate_limiter::RateLimiter};
+
+/// Shared state handed to job handlers.
+pub struct JobContext {
+    pub pool: PgPool,
+    pub limiter: Arc<ChannelLimiter>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub notifier: Arc<Notifier>,
+    in_flight: Mutex<HashSet<i32>>,
+}
+
+impl JobContext {
+    pub fn new(
+        pool: PgPool,
+        limiter: ChannelLimiter,
+        rate_limiter: RateLimiter,
+        notifier: Arc<Notifier>,
+    ) -> Self {
+        Self {
+            pool,
+            limiter: Arc::new(limiter),
+            rate_limiter: Arc::new(rate_limiter),
+            notifier,
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn start_job(&self, job_id: i32) {
+        self.in_flight.lock().unwrap().insert(job_id);
+    }
+
+    pub fn finish_job(&self, job_id: i32) {
+        self.in_flight.lock().unwrap().remove(&job_id);
+    }
+
+    /// Puts jobs that are still running back to `pending`, used when the
+    /// shutdown timeout expires before they finish.
+    pub async fn requeue_in_flight(&self) {
+        let job_ids: Vec<i32> = self.in_flight.lock().unwrap().drain().collect();
+
+        for job_id in job_ids {
+            match JobQueue::update_status(&self.pool, &job_id, "pending").await {
+                Ok(_) => (),
+                Err(err) => println!("Failed to requeue job {}: {}", job_id, err),
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    errors::Errors,
+    jobs::{
+        context::JobContext,
+        payloads::{self, DeliverWebhookPayload},
+        registry::{JobError, JobHandler},
+    },
+    models::{
+        job_run::JobRun, webhook_delivery::WebhookDelivery,
+        webhook_subscription::WebhookSubscription,
+    },
+    webhooks,
+};
+
+/// Attempts before a delivery is given up.
+const MAX_ATTEMPTS: i32 = 6;
+
+/// Delay before the second attempt, doubled for every attempt after it.
+const BACKOFF_SECONDS: u64 = 30;
+
+pub struct DeliverWebhookHandler;
+
+#[async_trait]
+impl JobHandler for DeliverWebhookHandler {
+    fn job_type(&self) -> &'static str {
+        "deliver_webhook"
+    }
+
+    fn priority(&self) -> i32 {
+        5
+    }
+
+    /// Webhooks go to the merchant's systems, not to customers.
+    fn respects_sending_window(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        ctx: &JobContext,
+        _run: &JobRun,
+        job_data: &Value,
+    ) -> Result<(), JobError> {
+        let pool = &ctx.pool;
+        let payload = payloads::parse::<DeliverWebhookPayload>(job_data, "deliver_webhook")?;
+
+        let webhook_delivery = match WebhookDelivery::get_by_id(pool, payload.webhook_delivery_id)
+            .await
+        {
+            Ok(webhook_delivery) => webhook_delivery,
+            Err(_) => {
+                return Err(
+                    Errors::new(&[("deliver_webhook", "Failed to get webhook delivery")]).into(),
+                );
+            }
+        };
+
+        let webhook_subscription = match WebhookSubscription::get_by_id(
+            pool,
+            webhook_delivery.webhook_subscription_id,
+            &payload.merchant_id,
+        )
+        .await
+        {
+            Ok(webhook_subscription) if webhook_subscription.is_active => webhook_subscription,
+            // deleted or disabled while the delivery was waiting
+            _ => {
+                record_attempt(
+                    ctx,
+                    &webhook_delivery,
+                    "failed",
+                    webhooks::Attempt {
+                        response_status: None,
+                        response_body: None,
+                        error: Some("webhook subscription is no longer active".to_string()),
+                    },
+                )
+                .await;
+
+                return Ok(());
+            }
+        };
+
+        let attempt = webhooks::post(&webhook_subscription, &webhook_delivery).await;
+
+        if attempt.is_delivered() {
+            record_attempt(ctx, &webhook_delivery, "delivered", attempt).await;
+
+            return Ok(());
+        }
+
+        let attempts = webhook_delivery.attempts + 1;
+        let reason = format!(
+            "webhook delivery {} failed, {}",
+            webhook_delivery.id,
+            attempt.error.clone().unwrap_or_default()
+        );
+
+        if attempts >= MAX_ATTEMPTS {
+            println!("Giving up on {}", reason);
+            record_attempt(ctx, &webhook_delivery, "failed", attempt).await;
+
+            // the delivery keeps the failure, failing the job would only
+            // have the queue post it again
+            return Ok(());
+        }
+
+        record_attempt(ctx, &webhook_delivery, "retrying", attempt).await;
+
+        Err(JobError::Retry {
+            reason,
+            retry_after: Duration::from_secs(BACKOFF_SECONDS * 2u64.pow(attempts as u32 - 1)),
+        })
+    }
+}
+
+async fn record_attempt(
+    ctx: &JobContext,
+    webhook_delivery: &WebhookDelivery,
+    status: &str,
+    attempt: webhooks::Attempt,
+) {
+    match WebhookDelivery::record_attempt(
+        &ctx.pool,
+        webhook_delivery.id,
+        status,
+        attempt.response_status,
+        attempt.response_body,
+        attempt.error,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to record attempt of webhook delivery {}: {}",
+            webhook_delivery.id, err
+        ),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod deliver_webhook;
+pub mod send_invoice;
+pub mod send_reminder;
+pub mod send_tag_reminder;
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, ops::Add};
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use rand::Rng;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    bot::invoice_keyboard,
+    documents::InvoiceDocument,
+    errors::Errors,
+    jobs::{
+        actions::{customer_locale, deliver_to_customer, merchant_timezone},
+        context::JobContext,
+        payloads::{self, SendInvoicePayload},
+        registry::{JobError, JobHandler},
+    },
+    locale::{Locale, Text},
+    models::{invoice::Invoice, item::Item, job_run::JobRun, job_schedule::JobSchedule},
+    notifications::{Attachment, Notification},
+    repositories::invoice::send_invoice_to_xendit,
+    templates::ChannelMessages,
+    utils::timezone,
+};
+
+/// Hours customers have to pay a sent invoice, after which it is overdue.
+pub const PAYMENT_DUE_HOURS: i64 = 24;
+
+pub struct SendInvoiceHandler;
+
+#[async_trait]
+impl JobHandler for SendInvoiceHandler {
+    fn job_type(&self) -> &'static str {
+        "send_invoice"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    async fn prepare(&self, pool: &PgPool, job_schedule: &JobSchedule) -> Result<Value, Errors> {
+        let job_data = match &job_schedule.job_data {
+            Some(job_data) => job_data.clone(),
+            None => {
+                return Err(Errors::new(&[("setup_invoice", "unable to get job data")]));
+            }
+        };
+
+        set_job_schedule_send_invoice(pool, job_data, job_schedule.id).await
+    }
+
+    async fn execute(
+        &self,
+        ctx: &JobContext,
+        run: &JobRun,
+        job_data: &Value,
+    ) -> Result<(), JobError> {
+        let pool = &ctx.pool;
+        let payload = payloads::parse::<SendInvoicePayload>(job_data, "send_invoice")?;
+
+        if payload.snoozed {
+            let invoice = match Invoice::get_by_id(pool, &payload.invoice_id).await {
+                Ok(invoice) => invoice,
+                Err(_) => {
+                    return Err(Errors::new(&[("send_invoice", "Failed to get invoice")]).into());
+                }
+            };
+
+            if is_settled_during_snooze(&payload, &invoice.status) {
+                println!(
+                    "Skipping snoozed invoice {} in job run {}, already {}",
+                    invoice.id, run.id, invoice.status
+                );
+
+                return Ok(());
+            }
+        }
+
+        let tz = merchant_timezone(pool, &payload.merchant_id).await?;
+        let locale = customer_locale(pool, &payload.customer_id, &payload.merchant_id).await?;
+
+        let messages = match message_builder_invoice(pool, &payload, &tz, locale).await {
+            Ok(messages) => messages,
+            Err(_) => {
+                return Err(Errors::new(&[(
+                    "prepare_via_channels",
+                    "Failed to prepare invoice",
+                )])
+                .into());
+            }
+        };
+
+        deliver_to_customer(ctx, run, &payload.customer_id, &payload.merchant_id, &messages)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Whether a snoozed invoice was paid or expired while put off, it isn't sent
+/// again then.
+pub fn is_settled_during_snooze(payload: &SendInvoicePayload, invoice_status: &str) -> bool {
+    payload.snoozed && (invoice_status == "paid" || invoice_status == "expired")
+}
+
+/// Built-in invoice message used when the merchant has no template, one of a
+/// few wordings in the customer's language picked at random.
+pub fn default_invoice_notification(locale: Locale) -> Notification {
+    let english = [
+        "Hello {{customer_name}}, {{merchant_name}} here, as a reminder, we ask that you please make a payment of *{{amount}}* to avoid any late fees. The payment can be made at the following link: {{pay_url}}. The due date for this payment is {{due_date}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, to avoid incurring late fees, we request that you make a payment of *{{amount}}* as soon as possible. You can easily do so by following this payment link: {{pay_url}}. The deadline for this payment is {{due_date}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, we strongly encourage you to make a payment of *{{amount}}* by the due date of {{due_date}} to avoid late fees. You can make the payment by clicking on the following link: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, to avoid being charged late fees, we request that you make a payment of *{{amount}}* by {{due_date}}. You can access the payment link here: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, please make a payment of *{{amount}}* by the due date of {{due_date}} to avoid late fees. You can make the payment at the following link: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, we request that you make a payment of *{{amount}}* as soon as possible to avoid any late fees. The payment link can be found here: {{pay_url}}. Please note that the payment is due on {{due_date}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, to avoid late fees, we ask that you make a payment of *{{amount}}* by the due date of {{due_date}}. You can make the payment using the following link: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, as a reminder, a payment of *{{amount}}* is due on {{due_date}} to avoid late fees. You can make the payment at the following link: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, we request that you make a payment of *{{amount}}* by {{due_date}} to avoid any late fees. The payment link is available here: {{pay_url}}.",
+        "Hello {{customer_name}}, {{merchant_name}} here, to avoid being charged late fees, we ask that you make a payment of *{{amount}}* as soon as possible. The payment link is provided here: {{pay_url}}. Please note that the payment is due on {{due_date}}.",
+    ];
+
+    let indonesian = [
+        "Halo {{customer_name}}, {{merchant_name}} di sini, sebagai pengingat, mohon lakukan pembayaran sebesar *{{amount}}* agar tidak dikenakan denda keterlambatan. Pembayaran dapat dilakukan melalui tautan berikut: {{pay_url}}. Batas waktu pembayaran adalah {{due_date}}.",
+        "Halo {{customer_name}}, {{merchant_name}} di sini, agar terhindar dari denda keterlambatan, mohon lakukan pembayaran sebesar *{{amount}}* sebelum {{due_date}}. Anda dapat membayar melalui tautan ini: {{pay_url}}.",
+        "Halo {{customer_name}}, {{merchant_name}} di sini, tagihan sebesar *{{amount}}* jatuh tempo pada {{due_date}}. Silakan lakukan pembayaran melalui tautan berikut: {{pay_url}}.",
+        "Halo {{customer_name}}, {{merchant_name}} di sini, kami mohon Anda segera melakukan pembayaran sebesar *{{amount}}*. Tautan pembayaran tersedia di sini: {{pay_url}}. Mohon diperhatikan, pembayaran jatuh tempo pada {{due_date}}.",
+        "Halo {{customer_name}}, {{merchant_name}} di sini, mohon selesaikan pembayaran sebesar *{{amount}}* paling lambat {{due_date}} untuk menghindari denda keterlambatan. Bayar dengan mudah melalui tautan berikut: {{pay_url}}.",
+    ];
+
+    let messages: &[&str] = match locale {
+        Locale::Id => &indonesian,
+        Locale::En => &english,
+    };
+
+    let random_number = rand::thread_rng().gen_range(0..messages.len());
+    Notification::new(messages[random_number])
+        .with_subject(locale.text(Text::InvoiceSubject))
+        .with_sender_name("{{merchant_name}}")
+}
+
+async fn set_job_schedule_send_invoice(
+    pool: &PgPool,
+    job_data: Value,
+    job_schedule_id: i32,
+) -> Result<Value, Errors> {
+    let payload = payloads::parse::<SendInvoicePayload>(&job_data, "setup_invoice")?;
+
+    let invoice = match Invoice::get_by_id(&pool, &payload.invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(_) => {
+            return Err(Errors::new(&[("setup_invoice", "Failed to get invoice")]));
+        }
+    };
+
+    if payload.snoozed && invoice.xendit_invoice_payload.is_some() {
+        return Ok(job_data);
+    }
+
+    // update invoice date to today
+    let invoice_date = Utc::now().naive_utc();
+    match Invoice::update_invoice_date(&pool, &invoice.id, &invoice_date).await {
+        Ok(invoice) => invoice,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "setup_invoice",
+                "Failed to update invoice date",
+            )]));
+        }
+    };
+
+    let mut job_data = job_data;
+    job_data["invoice_date"] = json!(invoice_date);
+
+    match JobSchedule::update_job_data(&pool, job_schedule_id, &job_data).await {
+        Ok(_) => (),
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "setup_invoice",
+                "Failed to update job data",
+            )]));
+        }
+    };
+
+    let result = match send_invoice_to_xendit(
+        &invoice.invoice_number,
+        &invoice.total_amount,
+        &invoice.to_string(),
+    )
+    .await
+    {
+        Ok(payload) => payload,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "setup_invoice",
+                "Failed to send invoice to xendit",
+            )]));
+        }
+    };
+
+    match Invoice::update_xendit_invoice_payload(&pool, &invoice.id, &result).await {
+        Ok(invoice) => invoice,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "setup_invoice",
+                "Failed to update xendit invoice payload",
+            )]));
+        }
+    };
+
+    Ok(job_data)
+}
+
+async fn message_builder_invoice(
+    pool: &PgPool,
+    payload: &SendInvoicePayload,
+    tz: &Tz,
+    locale: Locale,
+) -> Result<ChannelMessages, Errors> {
+    let invoice = match Invoice::get_by_id(&pool, &payload.invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "message_builder_invoice",
+                "Failed to prepare invoice",
+            )]));
+        }
+    };
+
+    let invoice_url = match invoice
+        .xendit_invoice_payload
+        .as_ref()
+        .and_then(|payload| payload["invoice_url"].as_str())
+    {
+        Some(invoice_url) => invoice_url.to_string(),
+        None => {
+            return Err(Errors::new(&[(
+                "message_builder_invoice",
+                "Invoice has no payment link",
+            )]));
+        }
+    };
+
+    let items = match Item::get_by_invoice_id(&pool, &invoice.id).await {
+        Ok(items) => items,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "message_builder_invoice",
+                "Failed to get invoice items",
+            )]));
+        }
+    };
+
+    let due_at = Utc::now().naive_utc().add(Duration::hours(PAYMENT_DUE_HOURS));
+    let values = invoice_values(payload, &invoice, &items, &invoice_url, &due_at, tz, locale);
+
+    let mut messages = match ChannelMessages::for_merchant(
+        pool,
+        &payload.merchant_id,
+        "invoice",
+        &values,
+        default_invoice_notification(locale),
+    )
+    .await
+    {
+        Ok(messages) => messages,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "message_builder_invoice",
+                "Failed to get message templates",
+            )]));
+        }
+    };
+
+    let document = InvoiceDocument::new(
+        &invoice,
+        &items,
+        &payload.merchant_name,
+        &payload.customer_name,
+        &values["due_date"],
+        &invoice_url,
+        locale,
+    );
+
+    messages.map_channel("telegram", |notification| {
+        notification.with_keyboard(invoice_keyboard(locale, &invoice.id, Some(&invoice_url)))
+    });
+
+    messages.map_channel("email", |notification| {
+        let html = document.html(&notification.body);
+        let notification = notification.with_html(&html);
+
+        if payload.attach_pdf {
+            let filename = format!("{}.pdf", invoice.invoice_number);
+            notification.with_attachment(Attachment::pdf(&filename, document.pdf()))
+        } else {
+            notification
+        }
+    });
+
+    Ok(messages)
+}
+
+/// Values of the `invoice` template variables formatted for `locale`,
+/// `due_at` is in UTC.
+pub fn invoice_values(
+    payload: &SendInvoicePayload,
+    invoice: &Invoice,
+    items: &[Item],
+    invoice_url: &str,
+    due_at: &NaiveDateTime,
+    tz: &Tz,
+    locale: Locale,
+) -> HashMap<&'static str, String> {
+    let items = items
+        .iter()
+        .map(|item| {
+            format!(
+                "- {} x{} @ {}",
+                item.description,
+                item.quantity,
+                locale.format_currency(item.price as i64)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    HashMap::from([
+        ("customer_name", payload.customer_name.clone()),
+        ("merchant_name", payload.merchant_name.clone()),
+        ("invoice_number", invoice.invoice_number.clone()),
+        ("amount", locale.format_currency(payload.total_amount)),
+        (
+            "due_date",
+            locale.format_datetime(&timezone::utc_to_local(due_at, tz)),
+        ),
+        ("pay_url", invoice_url.to_string()),
+        ("items", items),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn payload(snoozed: bool) -> SendInvoicePayload {
+        SendInvoicePayload {
+            invoice_id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            customer_name: "Budi".to_string(),
+            merchant_id: Uuid::new_v4(),
+            merchant_name: "Toko Maju".to_string(),
+            total_amount: 150000,
+            attach_pdf: false,
+            snoozed,
+        }
+    }
+
+    #[test]
+    fn snoozed_invoice_paid_or_expired_meanwhile_is_not_resent() {
+        assert!(is_settled_during_snooze(&payload(true), "paid"));
+        assert!(is_settled_during_snooze(&payload(true), "expired"));
+    }
+
+    #[test]
+    fn snoozed_invoice_still_unpaid_is_resent() {
+        assert!(!is_settled_during_snooze(&payload(true), "unpaid"));
+    }
+}
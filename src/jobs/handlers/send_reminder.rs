@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::{
+    errors::Errors,
+    jobs::{
+        actions::{customer_locale, deliver_to_customer},
+        context::JobContext,
+        payloads::{self, SendReminderPayload},
+        registry::{JobError, JobHandler},
+    },
+    locale::{Locale, Text},
+    models::job_run::JobRun,
+    notifications::Notification,
+    templates::ChannelMessages,
+};
+
+pub struct SendReminderHandler;
+
+#[async_trait]
+impl JobHandler for SendReminderHandler {
+    fn job_type(&self) -> &'static str {
+        "send_reminder"
+    }
+
+    fn priority(&self) -> i32 {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &JobContext,
+        run: &JobRun,
+        job_data: &Value,
+    ) -> Result<(), JobError> {
+        let payload = payloads::parse::<SendReminderPayload>(job_data, "send_reminder")?;
+        let messages = message_builder_reminder(&ctx.pool, &payload).await?;
+
+        deliver_to_customer(ctx, run, &payload.customer_id, &payload.merchant_id, &messages)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Built-in reminder message used when the merchant has no template.
+pub fn default_reminder_notification(locale: Locale) -> Notification {
+    let message = match locale {
+        Locale::Id => "Halo {{customer_name}}, {{merchant_name}} di sini, kami punya pesan untuk Anda \"{{title}}\", \"{{description}}\".",
+        Locale::En => "Hello {{customer_name}}, {{merchant_name}} here, we have a message for you \"{{title}}\", \"{{description}}\".",
+    };
+    Notification::new(message)
+        .with_subject(locale.text(Text::ReminderSubject))
+        .with_sender_name("{{merchant_name}}")
+}
+
+/// Values of the `reminder` template variables.
+pub fn reminder_values(payload: &SendReminderPayload) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("customer_name", payload.customer_name.clone()),
+        ("merchant_name", payload.merchant_name.clone()),
+        ("title", payload.title.clone()),
+        ("description", payload.description.clone()),
+    ])
+}
+
+pub async fn message_builder_reminder(
+    pool: &PgPool,
+    payload: &SendReminderPayload,
+) -> Result<ChannelMessages, Errors> {
+    let locale = customer_locale(pool, &payload.customer_id, &payload.merchant_id).await?;
+
+    match ChannelMessages::for_merchant(
+        pool,
+        &payload.merchant_id,
+        "reminder",
+        &reminder_values(payload),
+        default_reminder_notification(locale),
+    )
+    .await
+    {
+        Ok(messages) => Ok(messages),
+        Err(_) => Err(Errors::new(&[(
+            "message_builder_reminder",
+            "Failed to get message templates",
+        )])),
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    errors::Errors,
+    jobs::{
+        actions::deliver_to_customer,
+        context::JobContext,
+        payloads::{self, SendReminderPayload, SendTagReminderPayload},
+        registry::{JobError, JobHandler},
+    },
+    models::{
+        customer::Customer, job_run::JobRun, merchant::Merchant,
+        message_template::MessageTemplate,
+    },
+    templates::ChannelMessages,
+};
+
+use super::send_reminder::{default_reminder_notification, reminder_values};
+
+/// Runs of a tag reminder before the customers still failing are given up on.
+const MAX_RUNS: i64 = 5;
+/// Delay before the first retry of failed customers, doubled on each run.
+const BACKOFF_SECONDS: u64 = 60;
+
+pub struct SendTagReminderHandler;
+
+#[async_trait]
+impl JobHandler for SendTagReminderHandler {
+    fn job_type(&self) -> &'static str {
+        "send_reminder_by_tag"
+    }
+
+    fn priority(&self) -> i32 {
+        1
+    }
+
+    async fn execute(
+        &self,
+        ctx: &JobContext,
+        run: &JobRun,
+        job_data: &Value,
+    ) -> Result<(), JobError> {
+        let pool = &ctx.pool;
+        let payload = payloads::parse::<SendTagReminderPayload>(job_data, "send_reminder_by_tag")?;
+
+        let customers = match Customer::get_distinct_by_merchant_id_tags(
+            &pool,
+            &payload.merchant_id,
+            &vec![payload.tag.clone()],
+        )
+        .await
+        {
+            Ok(customers) => customers,
+            Err(_) => {
+                return Err(Errors::new(&[(
+                    "send_reminder_by_tag",
+                    "Failed to get customers by tag",
+                )])
+                .into());
+            }
+        };
+
+        let merchant = match Merchant::get_by_id(&pool, payload.merchant_id).await {
+            Ok(merchant) => merchant,
+            Err(_) => {
+                return Err(Errors::new(&[(
+                    "send_reminder_by_tag",
+                    "Failed to get merchant",
+                )])
+                .into());
+            }
+        };
+
+        let templates = match MessageTemplate::get_by_merchant_id_and_event_type(
+            &pool,
+            &payload.merchant_id,
+            "reminder",
+        )
+        .await
+        {
+            Ok(templates) => templates,
+            Err(_) => {
+                return Err(Errors::new(&[(
+                    "send_reminder_by_tag",
+                    "Failed to get message templates",
+                )])
+                .into());
+            }
+        };
+
+        let mut reached_count = 0;
+        let mut failures: Vec<String> = Vec::new();
+        let mut throttled: Option<JobError> = None;
+
+        for customer in customers.iter() {
+            let values = reminder_values(&SendReminderPayload {
+                title: payload.title.clone(),
+                description: payload.description.clone(),
+                customer_id: customer.id,
+                customer_name: customer.name.clone(),
+                merchant_id: payload.merchant_id,
+                merchant_name: payload.merchant_name.clone(),
+            });
+            let messages = ChannelMessages::render(
+                &templates,
+                &values,
+                default_reminder_notification(customer.locale(&merchant)),
+            );
+
+            // one customer's failure doesn't stop the others from getting
+            // the reminder
+            match deliver_to_customer(ctx, run, &customer.id, &payload.merchant_id, &messages)
+                .await
+            {
+                Ok(true) => reached_count += 1,
+                // opted out of every channel or has none
+                Ok(false) => (),
+                Err(err @ JobError::Throttled { .. }) => throttled = Some(err),
+                Err(err) => failures.push(format!("customer {}: {}", customer.id, err)),
+            }
+        }
+
+        let failed_count = failures.len() as i32;
+
+        match JobRun::set_recipient_counts(
+            &pool,
+            run.id,
+            customers.len() as i32,
+            reached_count,
+            failed_count,
+        )
+        .await
+        {
+            Ok(_) => (),
+            Err(err) => println!("Failed to set recipient counts of job run {}: {}", run.id, err),
+        }
+
+        if !failures.is_empty() {
+            let reason = format!(
+                "reached {} of {} customers tagged {}, failed for {}",
+                reached_count,
+                customers.len(),
+                payload.tag,
+                failures.join(", ")
+            );
+
+            let runs = match JobRun::count_by_job_queue_id(&pool, run.job_queue_id).await {
+                Ok(runs) => runs,
+                Err(_) => MAX_RUNS,
+            };
+
+            // the retry skips the channels already settled, so only the
+            // failed and throttled customers are sent to again
+            if runs < MAX_RUNS {
+                return Err(JobError::Retry {
+                    reason,
+                    retry_after: Duration::from_secs(BACKOFF_SECONDS * 2u64.pow(runs as u32 - 1)),
+                });
+            }
+
+            // the run keeps the failed count, failing the job would only
+            // have the queue send it again
+            println!("Giving up on job run {}, {}", run.id, reason);
+
+            return Ok(());
+        }
+
+        // throttled customers are sent to when the job is re-queued, the
+        // retry skips the channels already reached
+        match throttled {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps how many messages are sent at the same time through a channel, so a
+/// burst of jobs doesn't overload a gateway.
+pub struct ChannelLimiter {
+    semaphores: HashMap<String, Arc<Semaphore>>,
+}
+
+impl ChannelLimiter {
+    pub fn new(limits: &HashMap<String, usize>) -> Self {
+        let semaphores = limits
+            .iter()
+            .filter(|(_, limit)| **limit > 0)
+            .map(|(channel, limit)| (channel.to_lowercase(), Arc::new(Semaphore::new(*limit))))
+            .collect();
+
+        Self { semaphores }
+    }
+
+    /// Waits for a free slot on `channel`. Channels without a limit return
+    /// immediately.
+    pub async fn acquire(&self, channel: &str) -> Option<OwnedSemaphorePermit> {
+        match self.semaphores.get(channel) {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+}
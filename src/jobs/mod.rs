@@ -1,2 +1,9 @@
 pub mod spawns;
-pub mod actions;
\ No newline at end of file
+pub mod actions;
+pub mod context;
+pub mod handlers;
+pub mod limiter;
+pub mod rate_limiter;
+pub mod payloads;
+pub mod registry;
+pub mod window;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::errors::Errors;
+
+/// `job_data` of a `send_invoice` job.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendInvoicePayload {
+    pub invoice_id: Uuid,
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+    pub total_amount: i64,
+    /// Schedules created before the option existed have no PDF.
+    #[serde(default)]
+    pub attach_pdf: bool,
+    /// Put off by the customer, sent again with the payment link it has.
+    #[serde(default)]
+    pub snoozed: bool,
+}
+
+/// `job_data` of a `send_reminder` job.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendReminderPayload {
+    pub title: String,
+    pub description: String,
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+}
+
+/// `job_data` of a `send_reminder_by_tag` job, the customers are resolved
+/// from `tag` when the job runs.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendTagReminderPayload {
+    pub title: String,
+    pub description: String,
+    pub tag: String,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+}
+
+/// `job_data` of a `deliver_webhook` job.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliverWebhookPayload {
+    pub webhook_delivery_id: i32,
+    pub merchant_id: Uuid,
+}
+
+pub fn parse<T: serde::de::DeserializeOwned>(
+    job_data: &Value,
+    field: &'static str,
+) -> Result<T, Errors> {
+    match serde_json::from_value::<T>(job_data.clone()) {
+        Ok(payload) => Ok(payload),
+        Err(_) => Err(Errors::new(&[(field, "invalid job data")])),
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+/// Sustained rate and burst size of a token bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst.max(1) as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        let capacity = self.limit.burst.max(1) as f64;
+
+        self.tokens = (self.tokens + elapsed * self.per_second()).min(capacity);
+        self.refilled_at = now;
+    }
+
+    fn per_second(&self) -> f64 {
+        self.limit.per_minute as f64 / 60.0
+    }
+
+    /// Time until a token is available, zero when one is available now.
+    fn wait(&mut self) -> Duration {
+        self.refill();
+
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64((1.0 - self.tokens) / self.per_second())
+    }
+}
+
+/// Token buckets on outbound sends, one per channel (from `WORKER_RATE_*`)
+/// and one per merchant and channel (from `merchant_rate_limits`).
+///
+/// Buckets live in the worker process, each worker process enforces the
+/// limits on its own.
+pub struct RateLimiter {
+    channel_limits: HashMap<String, RateLimit>,
+    channel_buckets: Mutex<HashMap<String, TokenBucket>>,
+    merchant_buckets: Mutex<HashMap<(Uuid, String), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(channel_limits: HashMap<String, RateLimit>) -> Self {
+        let channel_limits = channel_limits
+            .into_iter()
+            .filter(|(_, limit)| limit.per_minute > 0)
+            .map(|(channel, limit)| (channel.to_lowercase(), limit))
+            .collect();
+
+        Self {
+            channel_limits,
+            channel_buckets: Mutex::new(HashMap::new()),
+            merchant_buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for a send on `channel` for `merchant_id`. When either
+    /// bucket is empty nothing is taken and the time to wait is returned.
+    pub fn try_acquire(
+        &self,
+        channel: &str,
+        merchant_id: &Uuid,
+        merchant_limit: Option<RateLimit>,
+    ) -> Result<(), Duration> {
+        let mut channel_buckets = self.channel_buckets.lock().unwrap();
+        let mut merchant_buckets = self.merchant_buckets.lock().unwrap();
+
+        let channel_bucket = match self.channel_limits.get(channel) {
+            Some(limit) => Some(
+                channel_buckets
+                    .entry(channel.to_string())
+                    .or_insert_with(|| TokenBucket::new(*limit)),
+            ),
+            None => None,
+        };
+
+        let merchant_bucket = match merchant_limit.filter(|limit| limit.per_minute > 0) {
+            Some(limit) => {
+                let bucket = merchant_buckets
+                    .entry((*merchant_id, channel.to_string()))
+                    .or_insert_with(|| TokenBucket::new(limit));
+
+                // the merchant changed its limit since the bucket was made
+                if bucket.limit != limit {
+                    *bucket = TokenBucket::new(limit);
+                }
+
+                Some(bucket)
+            }
+            None => None,
+        };
+
+        let mut wait = Duration::ZERO;
+        let mut buckets = Vec::new();
+
+        for bucket in [channel_bucket, merchant_bucket].into_iter().flatten() {
+            wait = wait.max(bucket.wait());
+            buckets.push(bucket);
+        }
+
+        if wait > Duration::ZERO {
+            return Err(wait);
+        }
+
+        for bucket in buckets {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(per_minute: u32, burst: u32) -> RateLimit {
+        RateLimit { per_minute, burst }
+    }
+
+    fn limiter(channel_limits: &[(&str, RateLimit)]) -> RateLimiter {
+        RateLimiter::new(
+            channel_limits
+                .iter()
+                .map(|(channel, limit)| (channel.to_string(), *limit))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn sends_without_limits_are_never_throttled() {
+        let limiter = limiter(&[("email", limit(0, 1))]);
+        let merchant_id = Uuid::new_v4();
+
+        for _ in 0..100 {
+            assert!(limiter.try_acquire("email", &merchant_id, None).is_ok());
+            assert!(limiter
+                .try_acquire("telegram", &merchant_id, Some(limit(0, 1)))
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn channel_bucket_allows_the_burst_then_waits_for_a_token() {
+        let limiter = limiter(&[("WhatsApp", limit(1, 2))]);
+        let merchant_id = Uuid::new_v4();
+
+        assert!(limiter.try_acquire("whatsapp", &merchant_id, None).is_ok());
+        assert!(limiter.try_acquire("whatsapp", &merchant_id, None).is_ok());
+
+        let wait = limiter
+            .try_acquire("whatsapp", &merchant_id, None)
+            .unwrap_err();
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+
+        // other channels have their own bucket
+        assert!(limiter.try_acquire("email", &merchant_id, None).is_ok());
+    }
+
+    #[test]
+    fn merchant_buckets_are_kept_per_merchant() {
+        let limiter = limiter(&[]);
+        let merchant_id = Uuid::new_v4();
+        let other_merchant_id = Uuid::new_v4();
+
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(2, 1)))
+            .is_ok());
+
+        let wait = limiter
+            .try_acquire("email", &merchant_id, Some(limit(2, 1)))
+            .unwrap_err();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+
+        assert!(limiter
+            .try_acquire("email", &other_merchant_id, Some(limit(2, 1)))
+            .is_ok());
+    }
+
+    #[test]
+    fn nothing_is_taken_when_one_bucket_is_empty() {
+        let limiter = limiter(&[("email", limit(1, 2))]);
+        let merchant_id = Uuid::new_v4();
+        let other_merchant_id = Uuid::new_v4();
+
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(1, 1)))
+            .is_ok());
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(1, 1)))
+            .is_err());
+
+        // the refused send left the channel token for another merchant
+        assert!(limiter
+            .try_acquire("email", &other_merchant_id, None)
+            .is_ok());
+        assert!(limiter
+            .try_acquire("email", &other_merchant_id, None)
+            .is_err());
+    }
+
+    #[test]
+    fn a_changed_merchant_limit_starts_a_new_bucket() {
+        let limiter = limiter(&[]);
+        let merchant_id = Uuid::new_v4();
+
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(1, 1)))
+            .is_ok());
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(1, 1)))
+            .is_err());
+        assert!(limiter
+            .try_acquire("email", &merchant_id, Some(limit(1, 2)))
+            .is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::{
+    errors::Errors,
+    models::{job_run::JobRun, job_schedule::JobSchedule},
+};
+
+use super::context::JobContext;
+use super::handlers::{
+    deliver_webhook::DeliverWebhookHandler, send_invoice::SendInvoiceHandler,
+    send_reminder::SendReminderHandler, send_tag_reminder::SendTagReminderHandler,
+};
+
+/// Why a job didn't complete.
+#[derive(Debug)]
+pub enum JobError {
+    /// The job failed and is retried like any failed job.
+    Failed(Errors),
+    /// A rate limit was hit, the job is re-queued after the delay.
+    Throttled { channel: String, retry_after: Duration },
+    /// A temporary failure the job handles itself, it is re-queued after the
+    /// delay instead of failing.
+    Retry { reason: String, retry_after: Duration },
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::Failed(errors) => write!(f, "{}", errors),
+            JobError::Throttled {
+                channel,
+                retry_after,
+            } => write!(
+                f,
+                "rate limited on {}, retry after {}s",
+                channel,
+                retry_after.as_secs()
+            ),
+            JobError::Retry {
+                reason,
+                retry_after,
+            } => write!(f, "{}, retry after {}s", reason, retry_after.as_secs()),
+        }
+    }
+}
+
+impl From<Errors> for JobError {
+    fn from(errors: Errors) -> Self {
+        JobError::Failed(errors)
+    }
+}
+
+/// A kind of job that can be scheduled and run by the job queue.
+///
+/// New job kinds are added by implementing this trait and registering the
+/// handler in `JobRegistry::default`.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    /// Value stored in `job_schedules.job_type` and `job_queues.job_type`.
+    fn job_type(&self) -> &'static str;
+
+    /// Queue priority, lower values are processed first.
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    /// Whether the job notifies customers and so waits for the merchant's
+    /// sending window.
+    fn respects_sending_window(&self) -> bool {
+        true
+    }
+
+    /// Called when a due schedule is moved to the queue, returns the job data
+    /// stored on the queued job.
+    async fn prepare(&self, _pool: &PgPool, job_schedule: &JobSchedule) -> Result<Value, Errors> {
+        match &job_schedule.job_data {
+            Some(job_data) => Ok(job_data.clone()),
+            None => Err(Errors::new(&[("prepare", "unable to get job data")])),
+        }
+    }
+
+    /// Runs a queued job, deliveries are recorded against `run`.
+    async fn execute(
+        &self,
+        ctx: &JobContext,
+        run: &JobRun,
+        job_data: &Value,
+    ) -> Result<(), JobError>;
+}
+
+pub struct JobRegistry {
+    handlers: HashMap<&'static str, Arc<dyn JobHandler>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn register<H: JobHandler + 'static>(&mut self, handler: H) {
+        self.handlers.insert(handler.job_type(), Arc::new(handler));
+    }
+
+    pub fn get(&self, job_type: &str) -> Option<Arc<dyn JobHandler>> {
+        self.handlers.get(job_type).cloned()
+    }
+
+    pub fn contains(&self, job_type: &str) -> bool {
+        self.handlers.contains_key(job_type)
+    }
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(SendInvoiceHandler);
+        registry.register(SendReminderHandler);
+        registry.register(SendTagReminderHandler);
+        registry.register(DeliverWebhookHandler);
+        registry
+    }
+}
@@ -1,138 +1,484 @@
-use std::{ops::Add, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use cron::Schedule;
-use sqlx::PgPool;
-use tokio::time::interval;
+use chrono_tz::Tz;
+use serde_json::json;
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::{
+    sync::{watch, Notify},
+    task::JoinHandle,
+    time::{interval, sleep},
+};
 
-use crate::models::{job_queue::JobQueue, job_schedule::JobSchedule};
+use crate::locale::Text;
+use crate::models::{
+    job_deferral::JobDeferral, job_queue::JobQueue, job_run::JobRun, job_schedule::JobSchedule,
+    merchant::Merchant,
+};
+use crate::notifications::merchant_alert;
+use crate::utils::timezone;
+use crate::webhooks;
 
-use super::actions::{prepare_via_channels, set_job_schedule_to_queue};
+use super::{
+    actions::set_job_schedule_to_queue,
+    context::JobContext,
+    registry::{JobError, JobRegistry},
+    window::{deferral_for_merchant, Deferral},
+};
 
-pub async fn spawn_job_queue(pool: PgPool, schedule: Schedule) {
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_secs(1));
+const JOB_QUEUES_CHANNEL: &'static str = "job_queues";
 
-        loop {
-            interval.tick().await;
+/// Failed runs before a job is marked dead, unless its schedule sets
+/// `retry_count`.
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Seconds before a failed job is retried, doubled for every failure after
+/// the first, unless its schedule sets `retry_interval`.
+const RETRY_INTERVAL_SECONDS: i32 = 30;
+
+/// Starts `concurrency` workers that take jobs from `job_queues`.
+///
+/// Idle workers are woken by a `NOTIFY job_queues` sent when a job is
+/// enqueued, and poll every `poll_interval` in case a notification is missed.
+/// Once `shutdown` flips to true workers finish their current job and stop.
+pub async fn spawn_job_queue(
+    ctx: Arc<JobContext>,
+    registry: Arc<JobRegistry>,
+    concurrency: usize,
+    poll_interval: Duration,
+    shutdown: watch::Receiver<bool>,
+) -> Vec<JoinHandle<()>> {
+    match JobQueue::requeue_stale(&ctx.pool).await {
+        Ok(count) if count > 0 => println!("Requeued {} stale in_progress jobs", count),
+        Ok(_) => (),
+        Err(err) => println!("Failed to requeue stale jobs: {}", err),
+    }
+
+    let wake = Arc::new(Notify::new());
+
+    spawn_job_queue_listener(ctx.pool.clone(), wake.clone(), shutdown.clone()).await;
+
+    let mut handles = Vec::new();
 
-            let job = match JobQueue::get_top_priority_job(&pool).await {
-                Ok(job) => job,
-                Err(_) => {
-                    continue;
+    for _ in 0..concurrency {
+        let ctx = ctx.clone();
+        let registry = registry.clone();
+        let wake = wake.clone();
+        let mut shutdown = shutdown.clone();
+
+        handles.push(tokio::spawn(async move {
+            loop {
+                if *shutdown.borrow() {
+                    break;
                 }
-            };
 
-            if job.job_schedule_id.is_none() {
-                continue;
+                let job = match JobQueue::claim_next(&ctx.pool).await {
+                    Ok(Some(job)) => job,
+                    Ok(None) | Err(_) => {
+                        tokio::select! {
+                            _ = wake.notified() => (),
+                            _ = sleep(poll_interval) => (),
+                            _ = shutdown.changed() => (),
+                        }
+
+                        continue;
+                    }
+                };
+
+                let job_id = job.id;
+
+                ctx.start_job(job_id);
+                run_job(&ctx, &registry, job).await;
+                ctx.finish_job(job_id);
             }
+        }));
+    }
 
-            let job_schedule = JobSchedule::get_schedule_by_id(&pool, job.job_schedule_id.unwrap())
-                .await
-                .expect("Failed to get schedule by id");
+    handles
+}
 
-            if job_schedule.repeat_count.is_some() && job_schedule.repeat_count.unwrap() > 0 {
-                let repeat_count = job_schedule.repeat_count.unwrap();
+async fn spawn_job_queue_listener(
+    pool: PgPool,
+    wake: Arc<Notify>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                println!("Job queue listener unavailable, polling only: {}", err);
+                return;
+            }
+        };
 
-                if job_schedule.repeat_interval.is_some() {
-                    let repeat_interval = job_schedule.repeat_interval.unwrap();
+        if let Err(err) = listener.listen(JOB_QUEUES_CHANNEL).await {
+            println!("Job queue listener unavailable, polling only: {}", err);
+            return;
+        }
 
-                    let new_run_at = job_schedule
-                        .run_at
-                        .add(chrono::Duration::seconds(repeat_interval));
+        loop {
+            tokio::select! {
+                notification = listener.recv() => match notification {
+                    Ok(_) => wake.notify_one(),
+                    // the listener reconnects on the next recv, workers keep polling meanwhile
+                    Err(_) => sleep(Duration::from_secs(1)).await,
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+    });
+}
 
-                    JobSchedule::update_run_at(&pool, job_schedule.id, &new_run_at)
-                        .await
-                        .expect("Failed to update run at");
-                }
+async fn run_job(ctx: &JobContext, registry: &JobRegistry, job: JobQueue) {
+    let pool = &ctx.pool;
 
-                JobSchedule::update_repeat_count(
-                    &pool,
-                    job.job_schedule_id.unwrap(),
-                    repeat_count - 1,
-                )
-                .await
-                .expect("Failed to update repeat count");
-            } else {
-                JobSchedule::update_status(&pool, job.job_schedule_id.unwrap(), "completed")
-                    .await
-                    .expect("Failed to update status");
+    let job_data = match job.job_data.clone() {
+        Some(job_data) => job_data,
+        // retrying can't fix these
+        None => {
+            update_job_status(&pool, &job, "dead").await;
+
+            return;
+        }
+    };
+
+    let handler = match registry.get(&job.job_type) {
+        Some(handler) => handler,
+        // an unknown job type stays unknown on a retry
+        None => {
+            update_job_status(&pool, &job, "dead").await;
+
+            return;
+        }
+    };
+
+    if handler.respects_sending_window() {
+        if let Some(merchant_id) = job_data_uuid(&job_data, "merchant_id") {
+            match deferral_for_merchant(&pool, &merchant_id).await {
+                Ok(Some(deferral)) => {
+                    defer_job(&pool, &job, &deferral).await;
+                    return;
+                }
+                Ok(None) => (),
+                Err(_) => println!("Failed to check sending window of job {}", job.id),
             }
+        }
+    }
 
-            JobQueue::update_status(&pool, &job.id, "in_progress")
-                .await
-                .expect("Failed to update status");
+    // a retried job already moved its schedule to the next occurrence
+    let is_retry = JobRun::count_by_job_queue_id(&pool, job.id)
+        .await
+        .map(|count| count > 0)
+        .unwrap_or(false);
+
+    if let (Some(job_schedule_id), false) = (job.job_schedule_id, is_retry) {
+        let job_schedule = match JobSchedule::get_schedule_by_id(&pool, job_schedule_id).await {
+            Ok(job_schedule) => job_schedule,
+            Err(err) => {
+                println!("Failed to get schedule {}: {}", job_schedule_id, err);
 
-            if job.job_data.is_none() {
-                JobQueue::update_status(&pool, &job.id, "failed")
-                    .await
-                    .expect("Failed to update status");
+                // running it anyway would leave the schedule on this occurrence
+                fail_job(&pool, &job).await;
 
-                continue;
+                return;
             }
+        };
+
+        if job_schedule.repeat_count.is_some() && job_schedule.repeat_count.unwrap() > 0 {
+            let repeat_count = job_schedule.repeat_count.unwrap();
 
-            let job_data = match serde_json::from_value::<serde_json::Value>(job.job_data.unwrap())
-            {
-                Ok(job_data) => job_data,
-                Err(_) => {
-                    JobQueue::update_status(&pool, &job.id, "failed")
-                        .await
-                        .expect("Failed to update status");
+            if job_schedule.repeat_interval.is_some() {
+                let repeat_interval = job_schedule.repeat_interval.unwrap();
 
-                    continue;
+                let tz = schedule_timezone(&pool, &job_schedule).await;
+                let new_run_at = timezone::add_interval(&job_schedule.run_at, repeat_interval, &tz);
+
+                match JobSchedule::update_run_at(&pool, job_schedule.id, &new_run_at).await {
+                    Ok(_) => (),
+                    Err(err) => println!(
+                        "Failed to update run at of schedule {}: {}",
+                        job_schedule.id, err
+                    ),
                 }
-            };
+            }
+
+            match JobSchedule::update_repeat_count(&pool, job_schedule_id, repeat_count - 1).await {
+                Ok(_) => (),
+                Err(err) => println!(
+                    "Failed to update repeat count of schedule {}: {}",
+                    job_schedule_id, err
+                ),
+            }
+        } else {
+            update_schedule_status(&pool, job_schedule_id, "completed").await;
+        }
+    }
+
+    let job_run = match JobRun::create(
+        &pool,
+        job.id,
+        job.job_schedule_id,
+        &job.job_type,
+        job_data_uuid(&job_data, "merchant_id"),
+        job_data_uuid(&job_data, "customer_id"),
+        job_data_uuid(&job_data, "invoice_id"),
+    )
+    .await
+    {
+        Ok(job_run) => job_run,
+        Err(err) => {
+            println!("Failed to create run for job {}: {}", job.id, err);
+
+            fail_job(&pool, &job).await;
+
+            return;
+        }
+    };
+
+    let result = handler.execute(ctx, &job_run, &job_data).await;
+
+    let (run_status, run_error) = match &result {
+        Ok(_) => ("completed", None),
+        Err(err @ JobError::Throttled { .. }) => ("throttled", Some(err.to_string())),
+        Err(err @ JobError::Retry { .. }) => ("retrying", Some(err.to_string())),
+        Err(err) => ("failed", Some(err.to_string())),
+    };
+
+    match JobRun::finish(&pool, job_run.id, run_status, run_error).await {
+        Ok(_) => (),
+        Err(err) => println!("Failed to finish job run {}: {}", job_run.id, err),
+    }
 
-            match prepare_via_channels(&pool, &job_schedule, &schedule).await {
-                Ok(_) => {
-                    JobQueue::update_status(&pool, &job.id, "completed")
-                        .await
-                        .expect("Failed to update status");
-
-                    if job.job_schedule_id.is_some() {
-                        let job_schedule =
-                            JobSchedule::get_schedule_by_id(&pool, job.job_schedule_id.unwrap())
-                                .await
-                                .expect("Failed to get schedule by id");
-
-                        if job_schedule.repeat_count.is_some()
-                            && job_schedule.repeat_count.unwrap() == 0
-                        {
-                            JobSchedule::update_status(
-                                &pool,
-                                job.job_schedule_id.unwrap(),
-                                "completed",
-                            )
-                            .await
-                            .expect("Failed to update status");
-                        } else {
-                            JobSchedule::update_status(
-                                &pool,
-                                job.job_schedule_id.unwrap(),
-                                "in_progress",
-                            )
-                            .await
-                            .expect("Failed to update status");
+    match result {
+        Ok(_) => {
+            update_job_status(&pool, &job, "completed").await;
+
+            if let Some(job_schedule_id) = job.job_schedule_id {
+                let job_schedule =
+                    match JobSchedule::get_schedule_by_id(&pool, job_schedule_id).await {
+                        Ok(job_schedule) => job_schedule,
+                        Err(err) => {
+                            println!("Failed to get schedule {}: {}", job_schedule_id, err);
+                            return;
                         }
-                    }
+                    };
+
+                // paused or cancelled while the job was running
+                if job_schedule.status == "paused" || job_schedule.status == "cancelled" {
+                    return;
                 }
-                Err(_) => {
-                    JobQueue::update_status(&pool, &job.id, "failed")
-                        .await
-                        .expect("Failed to update status");
+
+                if job_schedule.repeat_count.is_some() && job_schedule.repeat_count.unwrap() == 0 {
+                    update_schedule_status(&pool, job_schedule_id, "completed").await;
+                } else {
+                    update_schedule_status(&pool, job_schedule_id, "in_progress").await;
                 }
             }
         }
-    });
+        Err(JobError::Throttled {
+            channel,
+            retry_after,
+        }) => {
+            let retry_after = chrono::Duration::from_std(retry_after)
+                .unwrap_or_else(|_| chrono::Duration::seconds(1))
+                .max(chrono::Duration::seconds(1));
+
+            let deferral = Deferral {
+                until: chrono::Utc::now().naive_utc() + retry_after,
+                reason: format!("rate limited on {}", channel),
+            };
+
+            defer_job(&pool, &job, &deferral).await;
+        }
+        Err(JobError::Retry {
+            reason,
+            retry_after,
+        }) => {
+            let retry_after = chrono::Duration::from_std(retry_after)
+                .unwrap_or_else(|_| chrono::Duration::seconds(1))
+                .max(chrono::Duration::seconds(1));
+
+            let deferral = Deferral {
+                until: chrono::Utc::now().naive_utc() + retry_after,
+                reason,
+            };
+
+            defer_job(&pool, &job, &deferral).await;
+        }
+        Err(JobError::Failed(errors)) => {
+            let is_dead = fail_job(&pool, &job)
+                .await
+                .map(|job_queue| job_queue.status == "dead")
+                .unwrap_or(false);
+
+            // the merchant hears about the job once it is given up, not on
+            // every attempt
+            if !is_dead {
+                return;
+            }
+
+            if let Some(merchant_id) = job_data_uuid(&job_data, "merchant_id") {
+                merchant_alert::send(
+                    &pool,
+                    &ctx.notifier,
+                    &merchant_id,
+                    Text::MerchantAlertJobFailed,
+                    |_| {
+                        HashMap::from([
+                            ("job_type", job.job_type.clone()),
+                            ("error", errors.to_string()),
+                        ])
+                    },
+                )
+                .await;
+
+                // a failed delivery would otherwise announce itself to the
+                // same endpoint
+                if job.job_type != "deliver_webhook" {
+                    webhooks::emit(
+                        &pool,
+                        &merchant_id,
+                        "job.failed",
+                        json!({
+                            "job_id": job.id,
+                            "job_type": job.job_type,
+                            "error": errors.to_string(),
+                        }),
+                    )
+                    .await;
+                }
+            }
+        }
+    }
 }
 
-pub async fn spawn_set_job_schedule_to_queue(pool: PgPool) {
+pub async fn spawn_set_job_schedule_to_queue(
+    pool: PgPool,
+    registry: Arc<JobRegistry>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
         // Use an interval to perform the check at regular intervals.
         let mut interval = interval(Duration::from_secs(15));
 
         loop {
-            interval.tick().await;
-            set_job_schedule_to_queue(pool.clone()).await;
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = shutdown.changed() => break,
+            }
+
+            set_job_schedule_to_queue(pool.clone(), registry.clone()).await;
         }
-    });
+    })
+}
+
+/// Emits the events nothing else triggers, `invoice.overdue` once an invoice
+/// passes its due date and `daily.summary` every morning.
+pub async fn spawn_merchant_events(
+    pool: PgPool,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(300));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = shutdown.changed() => break,
+            }
+
+            webhooks::emit_overdue_invoices(&pool).await;
+            webhooks::emit_daily_summaries(&pool).await;
+        }
+    })
+}
+
+async fn schedule_timezone(pool: &PgPool, job_schedule: &JobSchedule) -> Tz {
+    let merchant_id = job_schedule
+        .job_data
+        .as_ref()
+        .and_then(|job_data| job_data["merchant_id"].as_str())
+        .and_then(|merchant_id| uuid::Uuid::parse_str(merchant_id).ok());
+
+    match merchant_id {
+        Some(merchant_id) => match Merchant::get_by_id(pool, merchant_id).await {
+            Ok(merchant) => merchant.tz(),
+            Err(_) => timezone::parse_or_default(timezone::DEFAULT_TIMEZONE),
+        },
+        None => timezone::parse_or_default(timezone::DEFAULT_TIMEZONE),
+    }
+}
+
+async fn update_job_status(pool: &PgPool, job: &JobQueue, status: &str) {
+    match JobQueue::update_status(&pool, &job.id, status).await {
+        Ok(_) => (),
+        Err(err) => println!("Failed to update status of job {} to {}: {}", job.id, status, err),
+    }
+}
+
+async fn update_schedule_status(pool: &PgPool, job_schedule_id: i32, status: &str) {
+    match JobSchedule::update_status(&pool, job_schedule_id, status).await {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to update status of schedule {} to {}: {}",
+            job_schedule_id, status, err
+        ),
+    }
+}
+
+/// Marks the job failed so it is retried with backoff, or dead once it ran
+/// out of attempts.
+async fn fail_job(pool: &PgPool, job: &JobQueue) -> Option<JobQueue> {
+    let job_schedule = match job.job_schedule_id {
+        Some(job_schedule_id) => JobSchedule::get_schedule_by_id(&pool, job_schedule_id).await.ok(),
+        None => None,
+    };
+
+    // retry_count counts the retries, not the first run
+    let max_attempts = job_schedule
+        .as_ref()
+        .and_then(|job_schedule| job_schedule.retry_count)
+        .map(|retry_count| retry_count + 1)
+        .unwrap_or(MAX_ATTEMPTS);
+    let retry_interval = job_schedule
+        .as_ref()
+        .and_then(|job_schedule| job_schedule.retry_interval)
+        .unwrap_or(RETRY_INTERVAL_SECONDS);
+
+    match JobQueue::fail(&pool, &job.id, max_attempts, retry_interval).await {
+        Ok(job_queue) => Some(job_queue),
+        Err(err) => {
+            println!("Failed to record failure of job {}: {}", job.id, err);
+            None
+        }
+    }
+}
+
+async fn defer_job(pool: &PgPool, job: &JobQueue, deferral: &Deferral) {
+    match JobQueue::defer(&pool, &job.id, &deferral.until).await {
+        Ok(_) => (),
+        Err(err) => {
+            // left in_progress until requeue_stale puts it back
+            println!("Failed to defer job {}: {}", job.id, err);
+            return;
+        }
+    }
+
+    match JobDeferral::create(
+        &pool,
+        job.id,
+        &deferral.reason,
+        &chrono::Utc::now().naive_utc(),
+        &deferral.until,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(err) => println!("Failed to record deferral of job {}: {}", job.id, err),
+    }
+}
+
+fn job_data_uuid(job_data: &serde_json::Value, field: &str) -> Option<uuid::Uuid> {
+    job_data[field]
+        .as_str()
+        .and_then(|value| uuid::Uuid::parse_str(value).ok())
 }
@@ -0,0 +1,242 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::Errors,
+    models::{
+        merchant::Merchant, merchant_holiday::MerchantHoliday,
+        merchant_sending_window::MerchantSendingWindow,
+    },
+    utils::timezone,
+};
+
+/// How far ahead the next allowed slot is searched, a merchant that blocks
+/// every day for longer than this gets its jobs sent anyway.
+const LOOKAHEAD_DAYS: i64 = 400;
+
+/// A job that came due outside the merchant's sending window.
+#[derive(Debug)]
+pub struct Deferral {
+    pub until: NaiveDateTime,
+    pub reason: String,
+}
+
+/// Checks the merchant's sending windows and holidays, returns `None` when
+/// sending is allowed now.
+pub async fn deferral_for_merchant(
+    pool: &PgPool,
+    merchant_id: &Uuid,
+) -> Result<Option<Deferral>, Errors> {
+    let merchant = match Merchant::get_by_id(&pool, *merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "sending_window",
+                "Failed to get merchant",
+            )]));
+        }
+    };
+    let tz = merchant.tz();
+
+    let windows = match MerchantSendingWindow::get_by_merchant_id(&pool, merchant_id).await {
+        Ok(windows) => windows,
+        Err(_) => {
+            return Err(Errors::new(&[(
+                "sending_window",
+                "Failed to get sending windows",
+            )]));
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+    let today = timezone::utc_to_local(&now, &tz).date();
+
+    let holidays =
+        match MerchantHoliday::get_upcoming_by_merchant_id(&pool, merchant_id, &today).await {
+            Ok(holidays) => holidays,
+            Err(_) => {
+                return Err(Errors::new(&[(
+                    "sending_window",
+                    "Failed to get holidays",
+                )]));
+            }
+        };
+
+    Ok(next_allowed_at(&now, &windows, &holidays, &tz))
+}
+
+/// Returns the next allowed slot after `now` (UTC), or `None` when `now` is
+/// already inside a window. A merchant without windows may send any time
+/// except on holidays.
+pub fn next_allowed_at(
+    now: &NaiveDateTime,
+    windows: &[MerchantSendingWindow],
+    holidays: &[MerchantHoliday],
+    tz: &Tz,
+) -> Option<Deferral> {
+    if windows.is_empty() && holidays.is_empty() {
+        return None;
+    }
+
+    let local_now = timezone::utc_to_local(now, tz);
+    let today = local_now.date();
+
+    let reason = match holidays.iter().find(|holiday| holiday.date == today) {
+        Some(holiday) => match &holiday.description {
+            Some(description) => format!("holiday: {}", description),
+            None => "holiday".to_string(),
+        },
+        None => "outside sending window".to_string(),
+    };
+
+    for offset in 0..LOOKAHEAD_DAYS {
+        let date = today + Duration::days(offset);
+
+        if holidays.iter().any(|holiday| holiday.date == date) {
+            continue;
+        }
+
+        for (start, end) in day_windows(&date, windows) {
+            if offset == 0 {
+                if local_now >= start && local_now < end {
+                    return None;
+                }
+
+                if local_now > start {
+                    continue;
+                }
+            }
+
+            return Some(Deferral {
+                until: timezone::local_to_utc(&start, tz),
+                reason,
+            });
+        }
+    }
+
+    None
+}
+
+/// Windows of `date` as local start and end times, sorted by start.
+fn day_windows(
+    date: &NaiveDate,
+    windows: &[MerchantSendingWindow],
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    if windows.is_empty() {
+        return vec![(
+            date.and_time(NaiveTime::from_hms(0, 0, 0)),
+            (*date + Duration::days(1)).and_time(NaiveTime::from_hms(0, 0, 0)),
+        )];
+    }
+
+    let weekday = date.weekday().number_from_monday() as i16;
+
+    let mut day_windows: Vec<(NaiveDateTime, NaiveDateTime)> = windows
+        .iter()
+        .filter(|window| window.day_of_week == weekday)
+        .map(|window| (date.and_time(window.start_time), date.and_time(window.end_time)))
+        .collect();
+
+    day_windows.sort();
+    day_windows
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use super::*;
+
+    fn jakarta() -> Tz {
+        chrono_tz::Asia::Jakarta
+    }
+
+    // 2023-04-03 is a Monday, Jakarta is UTC+7
+    fn utc(local: &str) -> NaiveDateTime {
+        let local = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+        timezone::local_to_utc(&local, &jakarta())
+    }
+
+    fn window(day_of_week: i16, start: (u32, u32), end: (u32, u32)) -> MerchantSendingWindow {
+        MerchantSendingWindow {
+            id: 1,
+            merchant_id: Uuid::nil(),
+            day_of_week,
+            start_time: NaiveTime::from_hms(start.0, start.1, 0),
+            end_time: NaiveTime::from_hms(end.0, end.1, 0),
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+        }
+    }
+
+    fn holiday(date: &str, description: Option<&str>) -> MerchantHoliday {
+        MerchantHoliday {
+            id: 1,
+            merchant_id: Uuid::nil(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            description: description.map(str::to_string),
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn sends_any_time_without_windows_or_holidays() {
+        assert!(next_allowed_at(&utc("2023-04-03 03:00"), &[], &[], &jakarta()).is_none());
+    }
+
+    #[test]
+    fn sends_inside_a_window() {
+        let windows = [window(1, (9, 0), (17, 0))];
+
+        let deferral = next_allowed_at(&utc("2023-04-03 10:00"), &windows, &[], &jakarta());
+
+        assert!(deferral.is_none());
+    }
+
+    #[test]
+    fn defers_to_the_start_of_a_later_window_today() {
+        let windows = [window(1, (13, 0), (17, 0)), window(1, (9, 0), (11, 0))];
+
+        let deferral =
+            next_allowed_at(&utc("2023-04-03 11:30"), &windows, &[], &jakarta()).unwrap();
+
+        assert_eq!(deferral.until, utc("2023-04-03 13:00"));
+        assert_eq!(deferral.reason, "outside sending window");
+    }
+
+    #[test]
+    fn defers_to_the_next_day_with_a_window_once_the_end_passed() {
+        let windows = [window(1, (9, 0), (17, 0)), window(3, (8, 0), (12, 0))];
+
+        // the end of a window is exclusive
+        let deferral =
+            next_allowed_at(&utc("2023-04-03 17:00"), &windows, &[], &jakarta()).unwrap();
+
+        assert_eq!(deferral.until, utc("2023-04-05 08:00"));
+    }
+
+    #[test]
+    fn defers_a_holiday_to_the_next_day() {
+        let holidays = [holiday("2023-04-03", Some("Nyepi"))];
+
+        let deferral =
+            next_allowed_at(&utc("2023-04-03 10:00"), &[], &holidays, &jakarta()).unwrap();
+
+        assert_eq!(deferral.until, utc("2023-04-04 00:00"));
+        assert_eq!(deferral.reason, "holiday: Nyepi");
+    }
+
+    #[test]
+    fn skips_holidays_when_looking_for_the_next_window() {
+        let windows = [window(1, (9, 0), (17, 0)), window(2, (9, 0), (17, 0))];
+        let holidays = [holiday("2023-04-04", None)];
+
+        let deferral =
+            next_allowed_at(&utc("2023-04-03 18:00"), &windows, &holidays, &jakarta()).unwrap();
+
+        assert_eq!(deferral.until, utc("2023-04-10 09:00"));
+        assert_eq!(deferral.reason, "outside sending window");
+    }
+}
@@ -1,32 +1,45 @@
-extern crate cron;
-
-use cron::Schedule;
 use std::net::SocketAddr;
-use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
     http::{HeaderValue, Method},
-    routing::{get, post, put},
-    Router,
+    routing::{delete, get, post, put},
+    Extension, Router,
 };
 
 use dotenvy::dotenv;
-use sqlx::postgres::PgPoolOptions;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tokio::{signal, sync::watch};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::jobs::spawns::{spawn_job_queue, spawn_set_job_schedule_to_queue};
+use crate::jobs::context::JobContext;
+use crate::jobs::limiter::ChannelLimiter;
+use crate::jobs::rate_limiter::RateLimiter;
+use crate::jobs::registry::JobRegistry;
+use crate::jobs::spawns::{
+    spawn_job_queue, spawn_merchant_events, spawn_set_job_schedule_to_queue,
+};
+use crate::notifications::Notifier;
 
+mod bot;
 mod config;
+mod contacts;
+mod conversation_store;
+mod documents;
 mod errors;
-mod functions;
 mod handlers;
 mod jobs;
+mod locale;
 mod logger;
 mod middlewares;
 mod models;
+mod notifications;
 mod repositories;
+mod templates;
 mod utils;
+mod webhooks;
 
 pub async fn axum() {
     // tracing_subscriber::registry()
@@ -36,6 +49,7 @@ pub async fn axum() {
     dotenv().ok();
 
     let config = config::Config::from_env().unwrap();
+    config.check().expect("Invalid configuration");
 
     let pool = PgPoolOptions::new()
         .min_connections(config.pg.as_ref().unwrap().poolminsize)
@@ -44,13 +58,84 @@ pub async fn axum() {
         .await
         .expect("Failed to create pool database connection");
 
-    let expression = "*/30 * * * * * *";
-    let schedule = Schedule::from_str(expression).unwrap();
+    let mode = config.mode();
+    let (shutdown_sender, shutdown) = watch::channel(false);
+
+    let registry = Arc::new(JobRegistry::default());
+    // one notifier for the workers and the API, the SMTP transport and its
+    // pooled connections are set up once
+    let notifier = Arc::new(Notifier::from_env(&pool));
+    let job_context = Arc::new(JobContext::new(
+        pool.clone(),
+        ChannelLimiter::new(&config.worker_channel_limits()),
+        RateLimiter::new(config.worker_channel_rates()),
+        notifier.clone(),
+    ));
+
+    let mut worker_handles = Vec::new();
+
+    if mode.runs_worker() {
+        worker_handles = spawn_job_queue(
+            job_context.clone(),
+            registry.clone(),
+            config.worker_concurrency(),
+            Duration::from_secs(config.worker_poll_interval()),
+            shutdown.clone(),
+        )
+        .await;
+
+        worker_handles.push(
+            spawn_set_job_schedule_to_queue(pool.clone(), registry.clone(), shutdown.clone()).await,
+        );
+        worker_handles.push(spawn_merchant_events(pool.clone(), shutdown.clone()).await);
+    }
+
+    if mode.runs_api() {
+        serve_api(&config, pool.clone(), notifier).await;
+    } else {
+        shutdown_signal().await;
+    }
 
-    spawn_job_queue(pool.clone(), schedule).await;
+    // stop taking new jobs and give in-flight ones time to finish
+    shutdown_sender.send(true).ok();
 
-    spawn_set_job_schedule_to_queue(pool.clone()).await;
+    let drain = async {
+        for handle in worker_handles {
+            handle.await.ok();
+        }
+    };
 
+    let shutdown_timeout = Duration::from_secs(config.worker_shutdown_timeout());
+    if tokio::time::timeout(shutdown_timeout, drain).await.is_err() {
+        job_context.requeue_in_flight().await;
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => (),
+        _ = terminate => (),
+    }
+}
+
+async fn serve_api(config: &config::Config, pool: PgPool, notifier: Arc<Notifier>) {
     let auth_middleware = axum::middleware::from_fn_with_state(
         pool.clone(),
         middlewares::authentication::check_authentication,
@@ -85,6 +170,14 @@ pub async fn axum() {
             "/merchant/:id/invoice/:id/set-schedule",
             put(handlers::invoice::set_invoice_scheduler),
         )
+        .route(
+            "/merchant/:id/invoice/:id/history",
+            get(handlers::invoice::get_history),
+        )
+        .route(
+            "/merchant/:id/invoice/:id/preview-schedule",
+            post(handlers::invoice::preview_invoice_scheduler),
+        )
         .route(
             "/merchant/:id/invoice/:id/update-status-schedule",
             put(handlers::invoice::set_invoice_status),
@@ -97,6 +190,32 @@ pub async fn axum() {
             "/merchant/:id/invoice",
             get(handlers::invoice::get_by_merchant_id).post(handlers::invoice::create),
         )
+        .route(
+            "/merchant/:id/customer/:id/channels/:id/primary",
+            put(handlers::customer_contact_channel::set_primary_channel),
+        )
+        .route(
+            "/merchant/:id/customer/:id/channels/:id",
+            put(handlers::customer_contact_channel::update_channel)
+                .delete(handlers::customer_contact_channel::delete_channel),
+        )
+        .route(
+            "/merchant/:id/customer/:id/channels",
+            get(handlers::customer_contact_channel::get_channels)
+                .post(handlers::customer_contact_channel::create_channel),
+        )
+        .route(
+            "/merchant/:id/customer/:id/history",
+            get(handlers::customer::get_history),
+        )
+        .route(
+            "/merchant/:id/customer/:id/conversation",
+            get(handlers::customer::get_conversation),
+        )
+        .route(
+            "/merchant/:id/customer/:id/telegram-invite",
+            post(handlers::telegram_invite::create_invite),
+        )
         .route(
             "/merchant/:id/customer/:id/scheduled-job",
             get(handlers::customer::get_job_schedule_by_customer),
@@ -117,10 +236,87 @@ pub async fn axum() {
             "/merchant/:id/tags",
             get(handlers::customer::get_tags_by_merchant_id),
         )
+        .route(
+            "/merchant/:id/telegram-link",
+            get(handlers::telegram_invite::get_merchant_link),
+        )
+        .route(
+            "/merchant/:id/sending-windows",
+            get(handlers::merchant::get_sending_windows)
+                .put(handlers::merchant::set_sending_windows),
+        )
+        .route(
+            "/merchant/:id/rate-limits",
+            get(handlers::merchant::get_rate_limits).put(handlers::merchant::set_rate_limits),
+        )
+        .route(
+            "/merchant/:id/holidays/:id",
+            delete(handlers::merchant::delete_holiday),
+        )
+        .route(
+            "/merchant/:id/holidays",
+            get(handlers::merchant::get_holidays).post(handlers::merchant::create_holiday),
+        )
+        .route(
+            "/merchant/:id/message-templates/preview",
+            post(handlers::message_template::preview_template),
+        )
+        .route(
+            "/merchant/:id/message-templates/:id",
+            put(handlers::message_template::update_template)
+                .delete(handlers::message_template::delete_template),
+        )
+        .route(
+            "/merchant/:id/message-templates",
+            get(handlers::message_template::get_templates)
+                .post(handlers::message_template::create_template),
+        )
+        .route(
+            "/merchant/:id/webhooks/:id/deliveries",
+            get(handlers::webhook_subscription::get_deliveries),
+        )
+        .route(
+            "/merchant/:id/webhooks/:id/test",
+            post(handlers::webhook_subscription::test_subscription),
+        )
+        .route(
+            "/merchant/:id/webhooks/:id",
+            put(handlers::webhook_subscription::update_subscription)
+                .delete(handlers::webhook_subscription::delete_subscription),
+        )
+        .route(
+            "/merchant/:id/webhooks",
+            get(handlers::webhook_subscription::get_subscriptions)
+                .post(handlers::webhook_subscription::create_subscription),
+        )
         .route(
             "/merchant/:id/set-schedule",
             put(handlers::job_schedule::set_scheduler),
         )
+        .route(
+            "/merchant/:id/scheduled-job/preview",
+            post(handlers::job_schedule::preview_scheduler),
+        )
+        .route(
+            "/merchant/:id/scheduled-job/:id/pause",
+            put(handlers::job_schedule::pause_schedule),
+        )
+        .route(
+            "/merchant/:id/scheduled-job/:id/resume",
+            put(handlers::job_schedule::resume_schedule),
+        )
+        .route(
+            "/merchant/:id/scheduled-job/:id/cancel",
+            put(handlers::job_schedule::cancel_schedule),
+        )
+        .route(
+            "/merchant/:id/scheduled-job/:id/skip",
+            put(handlers::job_schedule::skip_schedule),
+        )
+        .route(
+            "/merchant/:id/scheduled-job/:id",
+            get(handlers::job_schedule::get_schedule).put(handlers::job_schedule::update_schedule),
+        )
         .route(
             "/merchant/:id/scheduled-job",
             get(handlers::merchant::get_job_schedule_by_merchant_id),
@@ -144,13 +340,26 @@ pub async fn axum() {
             "/contact-channels",
             get(handlers::customer::get_contact_channels),
         )
+        .route(
+            "/user/telegram-link",
+            post(handlers::user::create_telegram_link)
+                .delete(handlers::user::delete_telegram_link),
+        )
         .route_layer(auth_middleware)
         .route("/login", post(handlers::auth::login))
         .route("/register", post(handlers::auth::register))
         .route("/verify", get(handlers::verification::auth))
+        .route(
+            "/unsubscribe",
+            get(handlers::unsubscribe::confirm).post(handlers::unsubscribe::unsubscribe),
+        )
         .route("/webhook/telegram", post(handlers::webhook::telegram))
+        .route("/webhook/whatsapp", post(handlers::webhook::whatsapp))
+        .route("/webhook/xendit", post(handlers::webhook::xendit))
         .route_layer(check_headers)
         .route("/", get(handlers::user::hello_world))
+        .layer(Extension(conversation_store::from_env(&pool)))
+        .layer(Extension(notifier))
         .layer(
             CorsLayer::new()
                 .allow_origin(origins)
@@ -171,6 +380,7 @@ pub async fn axum() {
     // tracing::debug!("listening on {}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
 }
@@ -0,0 +1,322 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+
+pub const DEFAULT_LOCALE: &'static str = "id";
+
+/// Languages customer-facing text is available in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    #[default]
+    Id,
+    En,
+}
+
+impl Locale {
+    /// Accepts `id`, `en` and region tags such as `en-US` as sent by Telegram.
+    pub fn parse(name: &str) -> Option<Locale> {
+        let language = name.split(|c| c == '-' || c == '_').next()?;
+
+        match language.to_lowercase().as_str() {
+            "id" => Some(Locale::Id),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn parse_or_default(name: &str) -> Locale {
+        Locale::parse(name).unwrap_or_default()
+    }
+
+    /// The customer's own locale, or the merchant's default.
+    pub fn resolve(customer_locale: Option<&str>, merchant_default_locale: &str) -> Locale {
+        match customer_locale.and_then(Locale::parse) {
+            Some(locale) => locale,
+            None => Locale::parse_or_default(merchant_default_locale),
+        }
+    }
+
+    /// Whole rupiah with grouped thousands, `Rp1.250.000` or `Rp1,250,000`.
+    pub fn format_currency(&self, amount: i64) -> String {
+        let separator = match self {
+            Locale::Id => '.',
+            Locale::En => ',',
+        };
+
+        let digits = amount.unsigned_abs().to_string();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if amount < 0 { "-" } else { "" };
+
+        format!("{}Rp{}", sign, grouped)
+    }
+
+    /// `18 Oktober 2026` or `18 October 2026`.
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        format!(
+            "{} {} {}",
+            date.day(),
+            self.month_name(date.month()),
+            date.year()
+        )
+    }
+
+    /// Date followed by the 24-hour time, for wall-clock times.
+    pub fn format_datetime(&self, local: &NaiveDateTime) -> String {
+        format!(
+            "{} {}",
+            self.format_date(&local.date()),
+            local.format("%H:%M")
+        )
+    }
+
+    fn month_name(&self, month: u32) -> &'static str {
+        let months = match self {
+            Locale::Id => [
+                "Januari", "Februari", "Maret", "April", "Mei", "Juni", "Juli", "Agustus",
+                "September", "Oktober", "November", "Desember",
+            ],
+            Locale::En => [
+                "January", "February", "March", "April", "May", "June", "July", "August",
+                "September", "October", "November", "December",
+            ],
+        };
+
+        months[(month as usize - 1) % 12]
+    }
+
+    /// Built-in customer-facing text. Placeholders are filled with
+    /// `templates::render`.
+    pub fn text(&self, text: Text) -> &'static str {
+        match (text, self) {
+            (Text::VerificationExpired, Locale::Id) => "<h1>Tautan verifikasi sudah kedaluwarsa</h1>",
+            (Text::VerificationExpired, Locale::En) => "<h1>Verification link has expired</h1>",
+            (Text::VerificationUsed, Locale::Id) => "<h1>Tautan verifikasi sudah digunakan</h1>",
+            (Text::VerificationUsed, Locale::En) => "<h1>Verification link has already been used</h1>",
+            (Text::VerificationDone, Locale::Id) => "<h1>Terima kasih sudah melakukan verifikasi!</h1>",
+            (Text::VerificationDone, Locale::En) => "<h1>Thank you for verifying!</h1>",
+            (Text::VerificationMessage, Locale::Id) => "Hai {{name}}, silakan verifikasi pendaftaran Anda melalui tautan ini: {{url}}",
+            (Text::VerificationMessage, Locale::En) => "Hi {{name}}, please verify your registration by clicking this link: {{url}}",
+            (Text::VerificationEmail, Locale::Id) => "Halo {{name}}, terima kasih sudah mendaftar di Inving. Silakan klik tautan ini untuk memverifikasi akun Anda: \n\n{{url}}",
+            (Text::VerificationEmail, Locale::En) => "Hello {{name}}, thank you for registering in Inving. Please click this link to verify your account: \n\n{{url}}",
+            (Text::VerificationEmailSubject, Locale::Id) => "Inving - Verifikasi Email",
+            (Text::VerificationEmailSubject, Locale::En) => "Inving - Email Verification",
+            (Text::ReminderSubject, Locale::Id) => "{{title}} - {{merchant_name}}",
+            (Text::ReminderSubject, Locale::En) => "{{title}} - {{merchant_name}}",
+            (Text::InvoiceSubject, Locale::Id) => "Tagihan {{invoice_number}} dari {{merchant_name}}",
+            (Text::InvoiceSubject, Locale::En) => "Invoice {{invoice_number}} from {{merchant_name}}",
+            (Text::InvoiceTitle, Locale::Id) => "Tagihan",
+            (Text::InvoiceTitle, Locale::En) => "Invoice",
+            (Text::InvoiceBilledTo, Locale::Id) => "Kepada",
+            (Text::InvoiceBilledTo, Locale::En) => "Billed to",
+            (Text::InvoiceDueDate, Locale::Id) => "Jatuh tempo",
+            (Text::InvoiceDueDate, Locale::En) => "Due date",
+            (Text::InvoiceItem, Locale::Id) => "Barang",
+            (Text::InvoiceItem, Locale::En) => "Item",
+            (Text::InvoiceQuantity, Locale::Id) => "Jml",
+            (Text::InvoiceQuantity, Locale::En) => "Qty",
+            (Text::InvoicePrice, Locale::Id) => "Harga",
+            (Text::InvoicePrice, Locale::En) => "Price",
+            (Text::InvoiceLineTotal, Locale::Id) => "Jumlah",
+            (Text::InvoiceLineTotal, Locale::En) => "Amount",
+            (Text::InvoiceSubtotal, Locale::Id) => "Subtotal",
+            (Text::InvoiceSubtotal, Locale::En) => "Subtotal",
+            (Text::InvoiceTax, Locale::Id) => "Pajak",
+            (Text::InvoiceTax, Locale::En) => "Tax",
+            (Text::InvoiceTotal, Locale::Id) => "Total",
+            (Text::InvoiceTotal, Locale::En) => "Total",
+            (Text::InvoicePayNow, Locale::Id) => "Bayar sekarang",
+            (Text::InvoicePayNow, Locale::En) => "Pay now",
+            (Text::Unsubscribe, Locale::Id) => "Berhenti berlangganan",
+            (Text::Unsubscribe, Locale::En) => "Unsubscribe",
+            (Text::UnsubscribeConfirm, Locale::Id) => "<h1>Berhenti menerima pesan melalui kanal ini?</h1>",
+            (Text::UnsubscribeConfirm, Locale::En) => "<h1>Stop receiving messages on this channel?</h1>",
+            (Text::UnsubscribeDone, Locale::Id) => "<h1>Anda tidak akan menerima pesan lagi melalui kanal ini</h1>",
+            (Text::UnsubscribeDone, Locale::En) => "<h1>You will no longer receive messages on this channel</h1>",
+            (Text::UnsubscribeInvalid, Locale::Id) => "<h1>Tautan berhenti berlangganan tidak valid</h1>",
+            (Text::UnsubscribeInvalid, Locale::En) => "<h1>Unsubscribe link is not valid</h1>",
+            (Text::BotOptedOut, Locale::Id) => "Anda tidak akan menerima pesan lagi. Kirim /start untuk berlangganan kembali.",
+            (Text::BotOptedOut, Locale::En) => "You will no longer receive messages. Send /start to subscribe again.",
+            (Text::BotOptedIn, Locale::Id) => "Anda akan menerima pesan kembali. Kirim /stop untuk berhenti.",
+            (Text::BotOptedIn, Locale::En) => "You will receive messages again. Send /stop to unsubscribe.",
+            (Text::WhatsappOptedOut, Locale::Id) => "Anda tidak akan menerima pesan lagi. Balas START untuk berlangganan kembali.",
+            (Text::WhatsappOptedOut, Locale::En) => "You will no longer receive messages. Reply START to subscribe again.",
+            (Text::WhatsappOptedIn, Locale::Id) => "Anda akan menerima pesan kembali. Balas STOP untuk berhenti.",
+            (Text::WhatsappOptedIn, Locale::En) => "You will receive messages again. Reply STOP to unsubscribe.",
+            (Text::BotHelp, Locale::Id) => "Perintah yang tersedia:\n/invoices - tagihan yang belum dibayar\n/invoice <nomor> - detail dan tautan pembayaran tagihan\n/history - pembayaran terakhir\n/connect - hubungkan ke merchant\n/stop - berhenti menerima pesan\n/help - daftar perintah",
+            (Text::BotHelp, Locale::En) => "Available commands:\n/invoices - outstanding invoices\n/invoice <number> - invoice details and payment link\n/history - recent payments\n/connect - connect to a merchant\n/stop - stop receiving messages\n/help - list the commands",
+            (Text::BotNotConnected, Locale::Id) => "Chat ini belum terhubung ke merchant. Kirim /connect untuk terhubung.",
+            (Text::BotNotConnected, Locale::En) => "This chat isn't connected to a merchant yet. Send /connect to connect.",
+            (Text::BotNoInvoices, Locale::Id) => "Tidak ada tagihan yang belum dibayar.",
+            (Text::BotNoInvoices, Locale::En) => "You have no outstanding invoices.",
+            (Text::BotInvoices, Locale::Id) => "Tagihan yang belum dibayar:",
+            (Text::BotInvoices, Locale::En) => "Outstanding invoices:",
+            (Text::BotInvoiceUsage, Locale::Id) => "Kirim /invoice <nomor>, misalnya /invoice INV-0001",
+            (Text::BotInvoiceUsage, Locale::En) => "Send /invoice <number>, for example /invoice INV-0001",
+            (Text::BotInvoiceNotFound, Locale::Id) => "Tagihan tidak ditemukan.",
+            (Text::BotInvoiceNotFound, Locale::En) => "Invoice not found.",
+            (Text::BotNoPayments, Locale::Id) => "Belum ada pembayaran.",
+            (Text::BotNoPayments, Locale::En) => "You have no payments yet.",
+            (Text::BotHistory, Locale::Id) => "Pembayaran terakhir:",
+            (Text::BotHistory, Locale::En) => "Recent payments:",
+            (Text::InvoiceStatus, Locale::Id) => "Status",
+            (Text::InvoiceStatus, Locale::En) => "Status",
+            (Text::InvoiceUnpaid, Locale::Id) => "Belum dibayar",
+            (Text::InvoiceUnpaid, Locale::En) => "Unpaid",
+            (Text::InvoicePaid, Locale::Id) => "Lunas",
+            (Text::InvoicePaid, Locale::En) => "Paid",
+            (Text::InvoiceExpired, Locale::Id) => "Kedaluwarsa",
+            (Text::InvoiceExpired, Locale::En) => "Expired",
+            (Text::BotShowItems, Locale::Id) => "Lihat barang",
+            (Text::BotShowItems, Locale::En) => "Show items",
+            (Text::BotRemindLater, Locale::Id) => "Ingatkan nanti",
+            (Text::BotRemindLater, Locale::En) => "Remind me later",
+            (Text::BotChooseMerchant, Locale::Id) => "Pilih merchant:",
+            (Text::BotChooseMerchant, Locale::En) => "Choose a merchant:",
+            (Text::BotSnoozed, Locale::Id) => "Baik, kami akan mengingatkan Anda lagi pada {{date}}.",
+            (Text::BotSnoozed, Locale::En) => "OK, we'll remind you again on {{date}}.",
+            (Text::BotSnoozeFailed, Locale::Id) => "Pengingat tidak dapat dijadwalkan.",
+            (Text::BotSnoozeFailed, Locale::En) => "Unable to schedule the reminder.",
+            (Text::BotInvoiceAlreadyPaid, Locale::Id) => "Tagihan ini sudah lunas.",
+            (Text::BotInvoiceAlreadyPaid, Locale::En) => "This invoice is already paid.",
+            (Text::BotUserLinked, Locale::Id) => "Halo {{name}}, akun Anda sudah terhubung. Anda akan menerima notifikasi pembayaran, pengiriman yang gagal dan pelanggan yang terhubung di sini.\n\n/today - penagihan hari ini\n/remind <nomor> - kirim ulang tagihan ke pelanggan\n/logout - putuskan akun dari chat ini",
+            (Text::BotUserLinked, Locale::En) => "Hi {{name}}, your account is connected. You'll get alerts here for payments, failed sends and customers connecting.\n\n/today - today's collections\n/remind <number> - send an invoice to the customer again\n/logout - disconnect your account from this chat",
+            (Text::BotUserNotLinked, Locale::Id) => "Chat ini belum terhubung ke akun merchant. Buka tautan Telegram dari dashboard untuk menghubungkannya.",
+            (Text::BotUserNotLinked, Locale::En) => "This chat isn't connected to a merchant account yet. Open the Telegram link from the dashboard to connect it.",
+            (Text::BotUserUnlinked, Locale::Id) => "Akun Anda sudah diputuskan dari chat ini.",
+            (Text::BotUserUnlinked, Locale::En) => "Your account is disconnected from this chat.",
+            (Text::BotToday, Locale::Id) => "Penagihan hari ini:",
+            (Text::BotToday, Locale::En) => "Today's collections:",
+            (Text::BotTodayEmpty, Locale::Id) => "Belum ada tagihan yang dibayar hari ini.",
+            (Text::BotTodayEmpty, Locale::En) => "No invoices were paid today yet.",
+            (Text::BotRemindUsage, Locale::Id) => "Kirim /remind <nomor> untuk mengirim ulang tagihan ke pelanggan.",
+            (Text::BotRemindUsage, Locale::En) => "Send /remind <number> to send an invoice to the customer again.",
+            (Text::BotReminderQueued, Locale::Id) => "Tagihan {{invoice_number}} akan dikirim ulang ke {{customer_name}}.",
+            (Text::BotReminderQueued, Locale::En) => "Invoice {{invoice_number}} will be sent to {{customer_name}} again.",
+            (Text::BotRemindFailed, Locale::Id) => "Tagihan {{invoice_number}} belum pernah dikirim, atur jadwalnya terlebih dahulu.",
+            (Text::BotRemindFailed, Locale::En) => "Invoice {{invoice_number}} was never sent, set its schedule first.",
+            (Text::MerchantAlertInvoicePaid, Locale::Id) => "Tagihan {{invoice_number}} dari {{customer_name}} sudah dibayar: {{amount}}.",
+            (Text::MerchantAlertInvoicePaid, Locale::En) => "Invoice {{invoice_number}} of {{customer_name}} was paid: {{amount}}.",
+            (Text::MerchantAlertJobFailed, Locale::Id) => "Pengiriman {{job_type}} di {{merchant_name}} gagal: {{error}}",
+            (Text::MerchantAlertJobFailed, Locale::En) => "Sending {{job_type}} for {{merchant_name}} failed: {{error}}",
+            (Text::MerchantAlertCustomerConnected, Locale::Id) => "{{customer_name}} sudah menghubungkan Telegram ke {{merchant_name}}.",
+            (Text::MerchantAlertCustomerReplied, Locale::Id) => "{{customer_name}} membalas di WhatsApp: {{message}}",
+            (Text::MerchantAlertCustomerReplied, Locale::En) => "{{customer_name}} replied on WhatsApp: {{message}}",
+            (Text::MerchantAlertCustomerConnected, Locale::En) => "{{customer_name}} connected their Telegram to {{merchant_name}}.",
+            (Text::IntegrationInvoicePaid, Locale::Id) => "Tagihan {{invoice_number}} sudah dibayar: {{amount}}.",
+            (Text::IntegrationInvoicePaid, Locale::En) => "Invoice {{invoice_number}} was paid: {{amount}}.",
+            (Text::IntegrationInvoiceOverdue, Locale::Id) => "Tagihan {{invoice_number}} sebesar {{amount}} sudah lewat jatuh tempo dan belum dibayar.",
+            (Text::IntegrationInvoiceOverdue, Locale::En) => "Invoice {{invoice_number}} of {{amount}} is past its due date and still unpaid.",
+            (Text::IntegrationJobFailed, Locale::Id) => "Pengiriman {{job_type}} gagal: {{error}}",
+            (Text::IntegrationJobFailed, Locale::En) => "Sending {{job_type}} failed: {{error}}",
+            (Text::IntegrationDailySummary, Locale::Id) => "Ringkasan {{date}}: {{paid_count}} tagihan dibayar, total {{paid_amount}}. {{unpaid_count}} tagihan belum dibayar, {{overdue_count}} di antaranya lewat jatuh tempo.",
+            (Text::IntegrationDailySummary, Locale::En) => "Summary of {{date}}: {{paid_count}} invoices paid, {{paid_amount}} in total. {{unpaid_count}} invoices unpaid, {{overdue_count}} of them overdue.",
+            (Text::IntegrationPing, Locale::Id) => "Integrasi terhubung, notifikasi akan dikirim ke sini.",
+            (Text::IntegrationPing, Locale::En) => "Integration connected, notifications will be posted here.",
+            (Text::BotWelcome, Locale::Id) => "Hai, selamat datang di bot telegram. Kirim /connect untuk terhubung ke merchant",
+            (Text::BotWelcome, Locale::En) => "Hi, welcome to the telegram bot. Send /connect to connect to the merchant",
+            (Text::BotAskMerchantCode, Locale::Id) => "OK. Kirimkan kode merchant yang Anda dapat dari merchant",
+            (Text::BotAskMerchantCode, Locale::En) => "OK. Send me the merchant code that you get from the merchant",
+            (Text::BotSendConnect, Locale::Id) => "Kirim /connect untuk terhubung ke merchant",
+            (Text::BotSendConnect, Locale::En) => "Send /connect to connect to the merchant",
+            (Text::BotInvalidMerchantCode, Locale::Id) => "Kode merchant tidak valid, silakan periksa kembali.",
+            (Text::BotInvalidMerchantCode, Locale::En) => "The merchant code is not valid, please check again.",
+            (Text::BotInviteInvalid, Locale::Id) => "Tautan undangan sudah tidak berlaku, silakan minta tautan baru ke merchant.",
+            (Text::BotInviteInvalid, Locale::En) => "This invite link is no longer valid, please ask the merchant for a new one.",
+            (Text::BotNotRegistered, Locale::Id) => "Anda belum terdaftar di merchant ini, silakan minta admin mendaftarkan username telegram Anda.",
+            (Text::BotNotRegistered, Locale::En) => "You're not registered in this merchant, please ask admin to register your telegram username.",
+            (Text::BotVerificationFailed, Locale::Id) => "Verifikasi tidak dapat dikirim",
+            (Text::BotVerificationFailed, Locale::En) => "Unable to sent verification",
+            (Text::BotContactChannelFailed, Locale::Id) => "Kanal kontak pelanggan tidak dapat diambil",
+            (Text::BotContactChannelFailed, Locale::En) => "Unable to get customer contact channel",
+            (Text::BotRegistered, Locale::Id) => "Terima kasih sudah mendaftar sebagai pelanggan",
+            (Text::BotRegistered, Locale::En) => "Thank you for register as customer",
+        }
+    }
+}
+
+/// Keys of the built-in customer-facing text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Text {
+    VerificationExpired,
+    VerificationUsed,
+    VerificationDone,
+    /// `{{name}}` and `{{url}}`
+    VerificationMessage,
+    /// `{{name}}` and `{{url}}`
+    VerificationEmail,
+    VerificationEmailSubject,
+    /// `{{title}}` and `{{merchant_name}}`
+    ReminderSubject,
+    /// `{{invoice_number}}` and `{{merchant_name}}`
+    InvoiceSubject,
+    InvoiceTitle,
+    InvoiceBilledTo,
+    InvoiceDueDate,
+    InvoiceItem,
+    InvoiceQuantity,
+    InvoicePrice,
+    InvoiceLineTotal,
+    InvoiceSubtotal,
+    InvoiceTax,
+    InvoiceTotal,
+    InvoicePayNow,
+    Unsubscribe,
+    UnsubscribeConfirm,
+    UnsubscribeDone,
+    UnsubscribeInvalid,
+    BotOptedOut,
+    BotOptedIn,
+    WhatsappOptedOut,
+    WhatsappOptedIn,
+    InvoiceStatus,
+    InvoiceUnpaid,
+    InvoicePaid,
+    InvoiceExpired,
+    BotHelp,
+    BotNotConnected,
+    BotNoInvoices,
+    BotInvoices,
+    BotInvoiceUsage,
+    BotInvoiceNotFound,
+    BotNoPayments,
+    BotHistory,
+    BotShowItems,
+    BotRemindLater,
+    BotChooseMerchant,
+    /// `{{date}}`
+    BotSnoozed,
+    BotSnoozeFailed,
+    BotInvoiceAlreadyPaid,
+    BotUserLinked,
+    BotUserNotLinked,
+    BotUserUnlinked,
+    BotToday,
+    BotTodayEmpty,
+    BotRemindUsage,
+    BotReminderQueued,
+    BotRemindFailed,
+    MerchantAlertInvoicePaid,
+    MerchantAlertJobFailed,
+    MerchantAlertCustomerConnected,
+    MerchantAlertCustomerReplied,
+    IntegrationInvoicePaid,
+    IntegrationInvoiceOverdue,
+    IntegrationJobFailed,
+    IntegrationDailySummary,
+    IntegrationPing,
+    BotWelcome,
+    BotAskMerchantCode,
+    BotSendConnect,
+    BotInvalidMerchantCode,
+    BotInviteInvalid,
+    BotNotRegistered,
+    BotVerificationFailed,
+    BotContactChannelFailed,
+    BotRegistered,
+}
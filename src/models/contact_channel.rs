@@ -54,4 +54,24 @@ impl ContactChannel {
 
         Ok(contact_channel)
     }
+
+    pub async fn get_by_name (
+        db: &sqlx::PgPool,
+        name: &str,
+    ) -> Result<ContactChannel, sqlx::Error> {
+        let contact_channel = sqlx::query_as!(
+            ContactChannel,
+            r#"
+            SELECT *
+            FROM contact_channels
+            WHERE name = $1
+            AND deleted_at IS NULL
+            "#,
+            name,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(contact_channel)
+    }
 }
\ No newline at end of file
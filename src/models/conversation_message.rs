@@ -0,0 +1,71 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A message of the conversation with a customer on a two-way channel,
+/// inbound from the customer or outbound to them.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConversationMessage {
+    pub id: Uuid,
+    pub customer_contact_channel_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    pub channel: String,
+    pub direction: String,
+    pub address: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl ConversationMessage {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        customer_contact_channel_id: Option<Uuid>,
+        customer_id: Option<Uuid>,
+        channel: &str,
+        direction: &str,
+        address: &str,
+        body: &str,
+    ) -> Result<ConversationMessage, sqlx::Error> {
+        let conversation_message = sqlx::query_as!(
+            ConversationMessage,
+            r#"
+            INSERT INTO conversation_messages (customer_contact_channel_id, customer_id, channel, direction, address, body)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            customer_contact_channel_id,
+            customer_id,
+            channel,
+            direction,
+            address,
+            body
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(conversation_message)
+    }
+
+    /// Conversation with the merchant's customer, oldest first.
+    pub async fn get_by_customer_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        customer_id: &Uuid,
+    ) -> Result<Vec<ConversationMessage>, sqlx::Error> {
+        let conversation_messages = sqlx::query_as!(
+            ConversationMessage,
+            r#"
+            SELECT a.* FROM conversation_messages a
+                INNER JOIN customers b ON b.id = a.customer_id
+            WHERE a.customer_id = $1 AND b.merchant_id = $2
+            ORDER BY a.created_at
+            "#,
+            customer_id,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(conversation_messages)
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// What the bot is waiting for in a conversation, kept until `expires_at`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConversationState {
+    pub key: String,
+    pub value: String,
+    pub expires_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ConversationState {
+    pub async fn get_by_key(
+        db: &sqlx::PgPool,
+        key: &str,
+    ) -> Result<Option<ConversationState>, sqlx::Error> {
+        let conversation_state = sqlx::query_as!(
+            ConversationState,
+            r#"
+            SELECT * FROM conversation_states
+            WHERE key = $1 AND expires_at > NOW()
+            "#,
+            key
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(conversation_state)
+    }
+
+    pub async fn upsert(
+        db: &sqlx::PgPool,
+        key: &str,
+        value: &str,
+        expires_at: &NaiveDateTime,
+    ) -> Result<ConversationState, sqlx::Error> {
+        let conversation_state = sqlx::query_as!(
+            ConversationState,
+            r#"
+            INSERT INTO conversation_states (key, value, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (key) DO UPDATE
+            SET value = EXCLUDED.value, expires_at = EXCLUDED.expires_at, updated_at = NOW()
+            RETURNING *
+            "#,
+            key,
+            value,
+            expires_at
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(conversation_state)
+    }
+
+    pub async fn delete_by_key(db: &sqlx::PgPool, key: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM conversation_states
+            WHERE key = $1
+            "#,
+            key
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_expired(db: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM conversation_states
+            WHERE expires_at <= NOW()
+            "#
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -3,6 +3,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::{Execute, QueryBuilder, Row};
 use uuid::Uuid;
 
+use crate::contacts;
+use crate::locale::Locale;
+
+use super::merchant::Merchant;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Customer {
     pub id: Uuid,
@@ -13,6 +18,7 @@ pub struct Customer {
     pub deleted_at: Option<NaiveDateTime>,
     pub tags: Vec<String>,
     pub verified_at: Option<NaiveDateTime>,
+    pub locale: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -25,6 +31,7 @@ pub struct CustomerWithContactChannels {
     pub deleted_at: Option<NaiveDateTime>,
     pub tags: Vec<String>,
     pub verified_at: Option<NaiveDateTime>,
+    pub locale: Option<String>,
     pub contact_channel_id: Uuid,
     pub customer_contact_channel_id: Uuid,
     pub contact_channel_value: String,
@@ -32,22 +39,29 @@ pub struct CustomerWithContactChannels {
 }
 
 impl Customer {
+    /// The customer's locale, or `merchant`'s default.
+    pub fn locale(&self, merchant: &Merchant) -> Locale {
+        Locale::resolve(self.locale.as_deref(), &merchant.default_locale)
+    }
+
     pub async fn create(
         db: &sqlx::PgPool,
         name: &String,
         tags: &Vec<String>,
         merchant_id: &Uuid,
+        locale: Option<String>,
     ) -> Result<Customer, sqlx::Error> {
         let customer = sqlx::query_as!(
             Customer,
             r#"
-            INSERT INTO customers (name, merchant_id, tags)
-            VALUES ($1, $2, $3)
+            INSERT INTO customers (name, merchant_id, tags, locale)
+            VALUES ($1, $2, $3, $4)
             RETURNING *
             "#,
             name,
             merchant_id,
-            tags
+            tags,
+            locale
         )
         .fetch_one(db)
         .await?;
@@ -60,17 +74,19 @@ impl Customer {
         name: &String,
         tags: &Vec<String>,
         merchant_id: &Uuid,
+        locale: Option<String>,
     ) -> Result<Customer, sqlx::Error> {
         let customer = sqlx::query_as!(
             Customer,
             r#"
-            INSERT INTO customers (name, merchant_id, tags)
-            VALUES ($1, $2, $3)
+            INSERT INTO customers (name, merchant_id, tags, locale)
+            VALUES ($1, $2, $3, $4)
             RETURNING *
             "#,
             name,
             merchant_id,
-            tags
+            tags,
+            locale
         )
         .fetch_one(db)
         .await?;
@@ -84,19 +100,21 @@ impl Customer {
         name: &String,
         tags: &Vec<String>,
         merchant_id: &Uuid,
+        locale: Option<String>,
     ) -> Result<Customer, sqlx::Error> {
         let customer = sqlx::query_as!(
             Customer,
             r#"
             UPDATE customers
-            SET name = $1, tags = $2, updated_at = NOW()
+            SET name = $1, tags = $2, locale = COALESCE($5, locale), updated_at = NOW()
             WHERE id = $3 AND merchant_id = $4 AND deleted_at IS NULL
             RETURNING *
             "#,
             name,
             tags,
             id,
-            merchant_id
+            merchant_id,
+            locale
         )
         .fetch_one(db)
         .await?;
@@ -124,6 +142,8 @@ impl Customer {
         Ok(customers)
     }
 
+    /// Customers tagged with any of `tags`, or all of them when `tags` is
+    /// empty, with their primary contact channel.
     pub async fn get_by_merchant_id_tags(
         db: &sqlx::PgPool,
         merchant_id: &Uuid,
@@ -141,6 +161,8 @@ impl Customer {
             FROM
                 customers
                 INNER JOIN customer_contact_channels ON customer_contact_channels.customer_id = customers.id
+                    AND customer_contact_channels.is_primary
+                    AND customer_contact_channels.deleted_at IS NULL
                 INNER JOIN contact_channels ON contact_channels.id = customer_contact_channels.contact_channel_id
             WHERE
                 merchant_id = $1
@@ -156,12 +178,46 @@ impl Customer {
         Ok(customers)
     }
 
+    /// Customers tagged with any of `tags`, one row per customer.
+    pub async fn get_distinct_by_merchant_id_tags(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        tags: &Vec<String>,
+    ) -> Result<Vec<Customer>, sqlx::Error> {
+        let customers = sqlx::query_as!(
+            Customer,
+            r#"
+            SELECT * FROM customers
+            WHERE merchant_id = $1 AND tags && $2 AND deleted_at IS NULL
+            ORDER BY created_at ASC
+            "#,
+            merchant_id,
+            tags
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(customers)
+    }
+
+    /// The customer reached at `customer_contact_channel_value`, normalized
+    /// like stored values so a Telegram `@Username` finds `username` and a
+    /// national number gets the merchant's calling code.
     pub async fn get_by_merchant_id_contact_channel(
         db: &sqlx::PgPool,
         merchant_id: &Uuid,
         contact_channel_name: &String,
         customer_contact_channel_value: &String,
     ) -> Result<CustomerWithContactChannels, sqlx::Error> {
+        let merchant = Merchant::get_by_id(db, *merchant_id).await?;
+
+        let customer_contact_channel_value = contacts::normalize(
+            contact_channel_name,
+            customer_contact_channel_value,
+            &contacts::country_code(&merchant),
+        )
+        .unwrap_or_else(|_| customer_contact_channel_value.to_string());
+
         let customer = sqlx::query_as!(
                 CustomerWithContactChannels,
             r#"
@@ -178,7 +234,8 @@ impl Customer {
             WHERE
             	merchant_id = $1
             	AND contact_channels.name = $2
-            	AND customer_contact_channels.value = $3
+            	AND LOWER(customer_contact_channels.value) = LOWER($3)
+            	AND customer_contact_channels.deleted_at IS NULL
             	AND customers.deleted_at IS NULL
             "#,
             merchant_id,
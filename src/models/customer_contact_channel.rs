@@ -12,6 +12,8 @@ pub struct CustomerContactChannel {
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
     pub additional_value: Option<String>,
+    pub opted_out_at: Option<NaiveDateTime>,
+    pub is_primary: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -21,9 +23,12 @@ pub struct CustomerContactChannelWithContactChannel {
     pub value: String,
     pub name: String,
     pub additional_value: Option<String>,
+    pub opted_out_at: Option<NaiveDateTime>,
+    pub is_primary: bool,
 }
 
 impl CustomerContactChannel {
+    /// The first channel of a new customer, which is its primary one.
     pub async fn create_using_transaction(
         db: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         customer_id: &Uuid,
@@ -33,8 +38,9 @@ impl CustomerContactChannel {
         let customer_contact_channel = sqlx::query_as!(
             CustomerContactChannel,
             r#"
-            INSERT INTO customer_contact_channels (customer_id, contact_channel_id, value)
-            VALUES ($1, $2, $3)
+            INSERT INTO customer_contact_channels
+                (customer_id, contact_channel_id, value, is_primary)
+            VALUES ($1, $2, $3, TRUE)
             RETURNING *
             "#,
             customer_id,
@@ -47,6 +53,31 @@ impl CustomerContactChannel {
         Ok(customer_contact_channel)
     }
 
+    pub async fn create(
+        db: &sqlx::PgPool,
+        customer_id: &Uuid,
+        contact_channel_id: &Uuid,
+        value: &str,
+        additional_value: Option<String>,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            INSERT INTO customer_contact_channels (customer_id, contact_channel_id, value, additional_value)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            customer_id,
+            contact_channel_id,
+            value,
+            additional_value
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(customer_contact_channel)
+    }
+
     pub async fn get_customer_contact_channels_by_customer_and_merchant(
         db: &sqlx::PgPool,
         customer_id: &Uuid,
@@ -60,7 +91,9 @@ impl CustomerContactChannel {
                 a.customer_id,
                 a.value,
                 c.name,
-                a.additional_value
+                a.additional_value,
+                a.opted_out_at,
+                a.is_primary
             FROM
                 customer_contact_channels a
                 LEFT JOIN customers b ON b.id = a.customer_id
@@ -69,6 +102,8 @@ impl CustomerContactChannel {
                 a.customer_id = $1 AND b.merchant_id = $2 AND a.deleted_at IS NULL
             GROUP BY
                 a.id, c.name
+            ORDER BY
+                a.is_primary DESC, a.created_at
             "#,
             customer_id,
             merchant_id
@@ -100,4 +135,328 @@ impl CustomerContactChannel {
 
         Ok(customer)
     }
+
+    pub async fn get_by_id(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            SELECT * FROM customer_contact_channels
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(customer_contact_channel)
+    }
+
+    /// Opts the channel out, or back in when `opted_out` is false.
+    pub async fn set_opted_out(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        opted_out: bool,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            UPDATE customer_contact_channels
+            SET opted_out_at = CASE WHEN $2 THEN COALESCE(opted_out_at, NOW()) ELSE NULL END,
+                updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            opted_out
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(customer_contact_channel)
+    }
+
+    /// Opts out, or back in, the channels named `channel_name` that reach
+    /// `value` or, for Telegram, the chat `value`. Only the customers of
+    /// `merchant_id` are changed, or those of every merchant when it is
+    /// `None`. Returns only the channels that changed.
+    pub async fn set_opted_out_by_value(
+        db: &sqlx::PgPool,
+        channel_name: &str,
+        value: &str,
+        merchant_id: Option<&Uuid>,
+        opted_out: bool,
+    ) -> Result<Vec<CustomerContactChannel>, sqlx::Error> {
+        let customer_contact_channels = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            UPDATE customer_contact_channels
+            SET opted_out_at = CASE WHEN $4 THEN NOW() ELSE NULL END, updated_at = NOW()
+            WHERE
+                contact_channel_id IN (SELECT id FROM contact_channels WHERE name = $1)
+                AND (value = $2 OR additional_value = $2)
+                AND ($3::uuid IS NULL
+                    OR customer_id IN (SELECT id FROM customers WHERE merchant_id = $3))
+                AND (opted_out_at IS NULL) = $4
+                AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            channel_name,
+            value,
+            merchant_id,
+            opted_out
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(customer_contact_channels)
+    }
+
+    /// Channels named `channel_name` whose `additional_value` is `value`, the
+    /// customers a Telegram chat is connected to.
+    pub async fn get_by_additional_value(
+        db: &sqlx::PgPool,
+        channel_name: &str,
+        value: &str,
+    ) -> Result<Vec<CustomerContactChannel>, sqlx::Error> {
+        let customer_contact_channels = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            SELECT a.* FROM customer_contact_channels a
+                INNER JOIN contact_channels c ON c.id = a.contact_channel_id
+                INNER JOIN customers b ON b.id = a.customer_id
+            WHERE
+                c.name = $1 AND a.additional_value = $2
+                AND a.deleted_at IS NULL AND b.deleted_at IS NULL
+            ORDER BY a.created_at
+            "#,
+            channel_name,
+            value
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(customer_contact_channels)
+    }
+
+    /// Channels named `channel_name` that reach `value`, whichever merchant
+    /// they belong to.
+    pub async fn get_by_value(
+        db: &sqlx::PgPool,
+        channel_name: &str,
+        value: &str,
+    ) -> Result<Vec<CustomerContactChannel>, sqlx::Error> {
+        let customer_contact_channels = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            SELECT a.* FROM customer_contact_channels a
+                INNER JOIN contact_channels c ON c.id = a.contact_channel_id
+                INNER JOIN customers b ON b.id = a.customer_id
+            WHERE
+                c.name = $1 AND a.value = $2
+                AND a.deleted_at IS NULL AND b.deleted_at IS NULL
+            ORDER BY a.created_at
+            "#,
+            channel_name,
+            value
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(customer_contact_channels)
+    }
+
+    /// The channel of the customer, only when the customer belongs to the
+    /// merchant.
+    pub async fn get_by_id_and_customer_id(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        customer_id: &Uuid,
+        merchant_id: &Uuid,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            SELECT a.* FROM customer_contact_channels a
+                INNER JOIN customers b ON b.id = a.customer_id
+            WHERE
+                a.id = $1 AND a.customer_id = $2 AND b.merchant_id = $3
+                AND a.deleted_at IS NULL AND b.deleted_at IS NULL
+            "#,
+            id,
+            customer_id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(customer_contact_channel)
+    }
+
+    /// Whether another customer channel of the merchant already reaches
+    /// `value` through the same contact channel, `except_id` is left out so a
+    /// channel can be saved with its own value.
+    pub async fn value_exists(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        contact_channel_id: &Uuid,
+        value: &str,
+        except_id: Option<Uuid>,
+    ) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM customer_contact_channels a
+                    INNER JOIN customers b ON b.id = a.customer_id
+                WHERE
+                    b.merchant_id = $1 AND a.contact_channel_id = $2
+                    AND LOWER(a.value) = LOWER($3)
+                    AND ($4::uuid IS NULL OR a.id <> $4)
+                    AND a.deleted_at IS NULL AND b.deleted_at IS NULL
+            ) AS "exists!"
+            "#,
+            merchant_id,
+            contact_channel_id,
+            value,
+            except_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(row.exists)
+    }
+
+    pub async fn count_by_customer_id(
+        db: &sqlx::PgPool,
+        customer_id: &Uuid,
+    ) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM customer_contact_channels
+            WHERE customer_id = $1 AND deleted_at IS NULL
+            "#,
+            customer_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(row.count)
+    }
+
+    /// Changes what the channel reaches, `additional_value` is replaced too
+    /// since it belongs to the old value.
+    pub async fn update(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        contact_channel_id: &Uuid,
+        value: &str,
+        additional_value: Option<String>,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            UPDATE customer_contact_channels
+            SET contact_channel_id = $2, value = $3, additional_value = $4, updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            contact_channel_id,
+            value,
+            additional_value
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(customer_contact_channel)
+    }
+
+    /// Makes the channel the customer's primary one in place of the current.
+    pub async fn set_primary(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        customer_id: &Uuid,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let mut db_transaction = db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE customer_contact_channels
+            SET is_primary = FALSE, updated_at = NOW()
+            WHERE customer_id = $1 AND is_primary AND id <> $2
+            "#,
+            customer_id,
+            id
+        )
+        .execute(&mut db_transaction)
+        .await?;
+
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            UPDATE customer_contact_channels
+            SET is_primary = TRUE, updated_at = NOW()
+            WHERE id = $1 AND customer_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            customer_id
+        )
+        .fetch_one(&mut db_transaction)
+        .await?;
+
+        db_transaction.commit().await?;
+
+        Ok(customer_contact_channel)
+    }
+
+    /// Removes the channel, the customer's oldest remaining channel becomes
+    /// primary when it was the primary one.
+    pub async fn delete(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        customer_id: &Uuid,
+    ) -> Result<CustomerContactChannel, sqlx::Error> {
+        let mut db_transaction = db.begin().await?;
+
+        let customer_contact_channel = sqlx::query_as!(
+            CustomerContactChannel,
+            r#"
+            UPDATE customer_contact_channels
+            SET deleted_at = NOW(), is_primary = FALSE, updated_at = NOW()
+            WHERE id = $1 AND customer_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            customer_id
+        )
+        .fetch_one(&mut db_transaction)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE customer_contact_channels
+            SET is_primary = TRUE, updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM customer_contact_channels
+                WHERE customer_id = $1 AND deleted_at IS NULL
+                ORDER BY created_at
+                LIMIT 1
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM customer_contact_channels
+                WHERE customer_id = $1 AND is_primary AND deleted_at IS NULL
+            )
+            "#,
+            customer_id
+        )
+        .execute(&mut db_transaction)
+        .await?;
+
+        db_transaction.commit().await?;
+
+        Ok(customer_contact_channel)
+    }
 }
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Delivery {
+    pub id: i32,
+    pub job_run_id: i32,
+    pub job_queue_id: i32,
+    pub customer_contact_channel_id: Uuid,
+    pub channel: String,
+    pub recipient: String,
+    pub status: String,
+    pub provider_message_id: Option<String>,
+    pub provider_response: Option<String>,
+    pub error: Option<String>,
+    pub attempted_at: NaiveDateTime,
+}
+
+impl Delivery {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        job_run_id: i32,
+        job_queue_id: i32,
+        customer_contact_channel_id: &Uuid,
+        channel: &str,
+        recipient: &str,
+        status: &str,
+        provider_message_id: Option<String>,
+        provider_response: Option<String>,
+        error: Option<String>,
+    ) -> Result<Delivery, sqlx::Error> {
+        let delivery = sqlx::query_as!(
+            Delivery,
+            r#"
+            INSERT INTO deliveries (job_run_id, job_queue_id, customer_contact_channel_id, channel, recipient, status, provider_message_id, provider_response, error)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            job_run_id,
+            job_queue_id,
+            customer_contact_channel_id,
+            channel,
+            recipient,
+            status,
+            provider_message_id,
+            provider_response,
+            error
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    /// Contact channels that were already sent or suppressed the message of a
+    /// queued job, mapped to whether they received it. Retries of the job skip
+    /// them.
+    pub async fn get_settled_channels_by_job_queue_id(
+        db: &sqlx::PgPool,
+        job_queue_id: i32,
+    ) -> Result<HashMap<Uuid, bool>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT customer_contact_channel_id, BOOL_OR(status = 'sent') AS "sent!"
+            FROM deliveries
+            WHERE job_queue_id = $1 AND status IN ('sent', 'suppressed')
+            GROUP BY customer_contact_channel_id
+            "#,
+            job_queue_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.customer_contact_channel_id, row.sent))
+            .collect())
+    }
+
+    /// Merchant of the customer that was last sent a message on `channel` at
+    /// `value`, or for Telegram at the chat `value`.
+    pub async fn get_last_merchant_id_by_value(
+        db: &sqlx::PgPool,
+        channel: &str,
+        value: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT customers.merchant_id FROM deliveries
+            INNER JOIN customer_contact_channels
+                ON customer_contact_channels.id = deliveries.customer_contact_channel_id
+            INNER JOIN customers ON customers.id = customer_contact_channels.customer_id
+            WHERE deliveries.channel = $1
+                AND (customer_contact_channels.value = $2
+                    OR customer_contact_channels.additional_value = $2)
+            ORDER BY deliveries.attempted_at DESC
+            LIMIT 1
+            "#,
+            channel,
+            value
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(row.map(|row| row.merchant_id))
+    }
+
+    pub async fn get_by_job_run_ids(
+        db: &sqlx::PgPool,
+        job_run_ids: &[i32],
+    ) -> Result<Vec<Delivery>, sqlx::Error> {
+        let deliveries = sqlx::query_as!(
+            Delivery,
+            r#"
+            SELECT * FROM deliveries
+            WHERE job_run_id = ANY($1)
+            ORDER BY attempted_at ASC
+            "#,
+            job_run_ids
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn get_by_job_run_ids_and_customer_id(
+        db: &sqlx::PgPool,
+        job_run_ids: &[i32],
+        customer_id: &Uuid,
+    ) -> Result<Vec<Delivery>, sqlx::Error> {
+        let deliveries = sqlx::query_as!(
+            Delivery,
+            r#"
+            SELECT deliveries.* FROM deliveries
+            JOIN customer_contact_channels
+            ON customer_contact_channels.id = deliveries.customer_contact_channel_id
+            WHERE deliveries.job_run_id = ANY($1) AND customer_contact_channels.customer_id = $2
+            ORDER BY deliveries.attempted_at ASC
+            "#,
+            job_run_ids,
+            customer_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(deliveries)
+    }
+}
@@ -23,6 +23,9 @@ pub struct Invoice {
     pub xendit_invoice_payload: Option<Value>,
     pub title: Option<String>,
     pub description: Option<String>,
+    pub status: String,
+    pub paid_at: Option<NaiveDateTime>,
+    pub overdue_at: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,9 +42,36 @@ pub struct InvoiceWithCustomerItems {
     pub job_schedule: Option<Value>,
     pub title: Option<String>,
     pub description: Option<String>,
+    pub status: String,
+    pub paid_at: Option<NaiveDateTime>,
     pub items: Option<Vec<SimpleItem>>
 }
 
+/// An invoice as the customer sees it, with the merchant it is from.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CustomerInvoice {
+    pub id: Uuid,
+    pub invoice_number: String,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+    pub customer_id: Uuid,
+    pub title: Option<String>,
+    pub total_amount: i32,
+    pub status: String,
+    pub invoice_date: NaiveDateTime,
+    pub paid_at: Option<NaiveDateTime>,
+    pub pay_url: Option<String>,
+}
+
+/// Invoice counts of a merchant for the daily summary.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvoiceSummary {
+    pub paid_count: i64,
+    pub paid_amount: i64,
+    pub unpaid_count: i64,
+    pub overdue_count: i64,
+}
+
 impl Invoice {
     pub async fn create(
         db: &sqlx::PgPool,
@@ -161,6 +191,8 @@ impl Invoice {
                 row_to_json(job_schedules) as job_schedule,
                 invoices.title,
                 invoices.description,
+                invoices.status,
+                invoices.paid_at,
                 coalesce(array_agg(items), '{}') AS "items: Vec<SimpleItem>"
             FROM invoices
                 INNER JOIN merchants ON merchants.id = invoices.merchant_id
@@ -200,6 +232,8 @@ impl Invoice {
                 row_to_json(job_schedules) as job_schedule,
                 invoices.title,
                 invoices.description,
+                invoices.status,
+                invoices.paid_at,
                 coalesce(array_agg(items), '{}') AS "items: Vec<SimpleItem>"
             FROM invoices
                 INNER JOIN customers ON customers.id = invoices.customer_id
@@ -257,4 +291,278 @@ impl Invoice {
 
         Ok(invoice)
     }
+
+    /// Sets the payment status of the invoice Xendit knows as `xendit_id`.
+    /// Returns `None` when the invoice already has `status`, so a redelivered
+    /// callback changes nothing.
+    pub async fn update_status_by_xendit_id(
+        db: &sqlx::PgPool,
+        xendit_id: &str,
+        status: &str,
+        paid_at: Option<NaiveDateTime>,
+    ) -> Result<Option<Invoice>, sqlx::Error> {
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            UPDATE invoices
+            SET status = $2, paid_at = $3, updated_at = NOW()
+            WHERE xendit_invoice_payload->>'id' = $1 AND status <> $2
+            RETURNING *
+            "#,
+            xendit_id,
+            status,
+            paid_at
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Invoices of the customers with `status`, newest first. Paid invoices
+    /// are ordered by when they were paid.
+    pub async fn get_by_customer_ids_and_status(
+        db: &sqlx::PgPool,
+        customer_ids: &[Uuid],
+        status: &str,
+        limit: i64,
+    ) -> Result<Vec<CustomerInvoice>, sqlx::Error> {
+        let invoices = sqlx::query_as!(
+            CustomerInvoice,
+            r#"
+            SELECT
+                invoices.id,
+                invoices.invoice_number,
+                invoices.merchant_id,
+                merchants.name AS merchant_name,
+                invoices.customer_id,
+                invoices.title,
+                invoices.total_amount,
+                invoices.status,
+                invoices.invoice_date,
+                invoices.paid_at,
+                invoices.xendit_invoice_payload->>'invoice_url' AS pay_url
+            FROM invoices
+                INNER JOIN merchants ON merchants.id = invoices.merchant_id
+                INNER JOIN customers ON customers.id = invoices.customer_id
+            WHERE
+                invoices.customer_id = ANY($1) AND invoices.status = $2
+                AND invoices.deleted_at IS NULL AND customers.deleted_at IS NULL
+            ORDER BY COALESCE(invoices.paid_at, invoices.invoice_date) DESC
+            LIMIT $3
+            "#,
+            customer_ids,
+            status,
+            limit
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    pub async fn get_by_customer_ids_and_invoice_number(
+        db: &sqlx::PgPool,
+        customer_ids: &[Uuid],
+        invoice_number: &str,
+    ) -> Result<CustomerInvoice, sqlx::Error> {
+        let invoice = sqlx::query_as!(
+            CustomerInvoice,
+            r#"
+            SELECT
+                invoices.id,
+                invoices.invoice_number,
+                invoices.merchant_id,
+                merchants.name AS merchant_name,
+                invoices.customer_id,
+                invoices.title,
+                invoices.total_amount,
+                invoices.status,
+                invoices.invoice_date,
+                invoices.paid_at,
+                invoices.xendit_invoice_payload->>'invoice_url' AS pay_url
+            FROM invoices
+                INNER JOIN merchants ON merchants.id = invoices.merchant_id
+                INNER JOIN customers ON customers.id = invoices.customer_id
+            WHERE
+                invoices.customer_id = ANY($1) AND UPPER(invoices.invoice_number) = UPPER($2)
+                AND invoices.deleted_at IS NULL AND customers.deleted_at IS NULL
+            "#,
+            customer_ids,
+            invoice_number
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    pub async fn get_by_customer_ids_and_id(
+        db: &sqlx::PgPool,
+        customer_ids: &[Uuid],
+        id: &Uuid,
+    ) -> Result<CustomerInvoice, sqlx::Error> {
+        let invoice = sqlx::query_as!(
+            CustomerInvoice,
+            r#"
+            SELECT
+                invoices.id,
+                invoices.invoice_number,
+                invoices.merchant_id,
+                merchants.name AS merchant_name,
+                invoices.customer_id,
+                invoices.title,
+                invoices.total_amount,
+                invoices.status,
+                invoices.invoice_date,
+                invoices.paid_at,
+                invoices.xendit_invoice_payload->>'invoice_url' AS pay_url
+            FROM invoices
+                INNER JOIN merchants ON merchants.id = invoices.merchant_id
+                INNER JOIN customers ON customers.id = invoices.customer_id
+            WHERE
+                invoices.customer_id = ANY($1) AND invoices.id = $2
+                AND invoices.deleted_at IS NULL AND customers.deleted_at IS NULL
+            "#,
+            customer_ids,
+            id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Invoices of the merchant paid since `paid_since`, first paid first.
+    pub async fn get_paid_by_merchant_id_since(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        paid_since: &NaiveDateTime,
+    ) -> Result<Vec<CustomerInvoice>, sqlx::Error> {
+        let invoices = sqlx::query_as!(
+            CustomerInvoice,
+            r#"
+            SELECT
+                invoices.id,
+                invoices.invoice_number,
+                invoices.merchant_id,
+                merchants.name AS merchant_name,
+                invoices.customer_id,
+                invoices.title,
+                invoices.total_amount,
+                invoices.status,
+                invoices.invoice_date,
+                invoices.paid_at,
+                invoices.xendit_invoice_payload->>'invoice_url' AS pay_url
+            FROM invoices
+                INNER JOIN merchants ON merchants.id = invoices.merchant_id
+                INNER JOIN customers ON customers.id = invoices.customer_id
+            WHERE
+                invoices.merchant_id = $1 AND invoices.status = 'paid' AND invoices.paid_at >= $2
+                AND invoices.deleted_at IS NULL
+            ORDER BY invoices.paid_at
+            "#,
+            merchant_id,
+            paid_since
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    pub async fn get_by_merchant_ids_and_invoice_number(
+        db: &sqlx::PgPool,
+        merchant_ids: &[Uuid],
+        invoice_number: &str,
+    ) -> Result<CustomerInvoice, sqlx::Error> {
+        let invoice = sqlx::query_as!(
+            CustomerInvoice,
+            r#"
+            SELECT
+                invoices.id,
+                invoices.invoice_number,
+                invoices.merchant_id,
+                merchants.name AS merchant_name,
+                invoices.customer_id,
+                invoices.title,
+                invoices.total_amount,
+                invoices.status,
+                invoices.invoice_date,
+                invoices.paid_at,
+                invoices.xendit_invoice_payload->>'invoice_url' AS pay_url
+            FROM invoices
+                INNER JOIN merchants ON merchants.id = invoices.merchant_id
+                INNER JOIN customers ON customers.id = invoices.customer_id
+            WHERE
+                invoices.merchant_id = ANY($1) AND UPPER(invoices.invoice_number) = UPPER($2)
+                AND invoices.deleted_at IS NULL AND customers.deleted_at IS NULL
+            "#,
+            merchant_ids,
+            invoice_number
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Marks unpaid invoices sent before `due_before` as overdue and returns
+    /// them, each invoice is returned once.
+    pub async fn mark_overdue(
+        db: &sqlx::PgPool,
+        due_before: &NaiveDateTime,
+    ) -> Result<Vec<Invoice>, sqlx::Error> {
+        let invoices = sqlx::query_as!(
+            Invoice,
+            r#"
+            UPDATE invoices
+            SET overdue_at = NOW(), updated_at = NOW()
+            WHERE
+                status = 'unpaid' AND overdue_at IS NULL AND invoice_date < $1
+                AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            due_before
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    /// Invoices of the merchant paid between `paid_from` and `paid_to`, and
+    /// the ones still unpaid.
+    pub async fn get_summary_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        paid_from: &NaiveDateTime,
+        paid_to: &NaiveDateTime,
+    ) -> Result<InvoiceSummary, sqlx::Error> {
+        let invoice_summary = sqlx::query_as!(
+            InvoiceSummary,
+            r#"
+            SELECT
+                COUNT(*) FILTER (
+                    WHERE status = 'paid' AND paid_at >= $2 AND paid_at < $3
+                ) AS "paid_count!",
+                COALESCE(SUM(total_amount) FILTER (
+                    WHERE status = 'paid' AND paid_at >= $2 AND paid_at < $3
+                ), 0) AS "paid_amount!",
+                COUNT(*) FILTER (WHERE status = 'unpaid') AS "unpaid_count!",
+                COUNT(*) FILTER (
+                    WHERE status = 'unpaid' AND overdue_at IS NOT NULL
+                ) AS "overdue_count!"
+            FROM invoices
+            WHERE merchant_id = $1 AND deleted_at IS NULL
+            "#,
+            merchant_id,
+            paid_from,
+            paid_to
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(invoice_summary)
+    }
 }
@@ -115,4 +115,23 @@ impl Item {
 
         Ok(item)
     }
+
+    pub async fn get_by_invoice_id(
+        db: &sqlx::PgPool,
+        invoice_id: &Uuid,
+    ) -> Result<Vec<Item>, sqlx::Error> {
+        let items = sqlx::query_as!(
+            Item,
+            r#"
+            SELECT * FROM items
+            WHERE invoice_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+            invoice_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
 }
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JobDeferral {
+    pub id: i32,
+    pub job_queue_id: i32,
+    pub reason: String,
+    pub deferred_from: NaiveDateTime,
+    pub deferred_until: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+impl JobDeferral {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        job_queue_id: i32,
+        reason: &str,
+        deferred_from: &NaiveDateTime,
+        deferred_until: &NaiveDateTime,
+    ) -> Result<JobDeferral, sqlx::Error> {
+        let job_deferral = sqlx::query_as!(
+            JobDeferral,
+            r#"
+            INSERT INTO job_deferrals (job_queue_id, reason, deferred_from, deferred_until)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            job_queue_id,
+            reason,
+            deferred_from,
+            deferred_until
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_deferral)
+    }
+
+    pub async fn get_by_job_queue_ids(
+        db: &sqlx::PgPool,
+        job_queue_ids: &[i32],
+    ) -> Result<Vec<JobDeferral>, sqlx::Error> {
+        let job_deferrals = sqlx::query_as!(
+            JobDeferral,
+            r#"
+            SELECT * FROM job_deferrals
+            WHERE job_queue_id = ANY($1)
+            ORDER BY created_at DESC
+            "#,
+            job_queue_ids
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_deferrals)
+    }
+}
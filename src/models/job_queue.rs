@@ -13,6 +13,8 @@ pub struct JobQueue {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
+    pub available_at: NaiveDateTime,
+    pub attempts: i32,
 }
 
 impl JobQueue {
@@ -52,7 +54,7 @@ impl JobQueue {
             JobQueue,
             r#"
             UPDATE job_queues
-            SET status = $2
+            SET status = $2, updated_at = NOW()
             WHERE id = $1
             RETURNING *
             "#,
@@ -88,22 +90,145 @@ impl JobQueue {
         Ok(job_queue)
     }
 
-    pub async fn get_top_priority_job(db: &sqlx::PgPool) -> Result<JobQueue, sqlx::Error> {
+    /// Atomically takes the top priority job and marks it `in_progress`, rows
+    /// locked by other workers are skipped. Failed jobs come back once their
+    /// backoff set by `fail` has passed, dead ones never do.
+    pub async fn claim_next(db: &sqlx::PgPool) -> Result<Option<JobQueue>, sqlx::Error> {
         let job_queue = sqlx::query_as!(
             JobQueue,
             r#"
-            SELECT * FROM job_queues
-            WHERE status = 'pending' OR status = 'failed'
-            ORDER BY priority ASC, created_at ASC
-            LIMIT 1
+            UPDATE job_queues
+            SET status = 'in_progress', updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM job_queues
+                WHERE status IN ('pending', 'failed') AND available_at <= NOW()
+                ORDER BY priority ASC, created_at ASC
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING *
             "#,
         )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(job_queue)
+    }
+
+    /// Records a failed run. The job is retried after `retry_interval` seconds,
+    /// doubled for every earlier failure, and marked `dead` once it failed
+    /// `max_attempts` times.
+    pub async fn fail(
+        db: &sqlx::PgPool,
+        id: &i32,
+        max_attempts: i32,
+        retry_interval: i32,
+    ) -> Result<JobQueue, sqlx::Error> {
+        let job_queue = sqlx::query_as!(
+            JobQueue,
+            r#"
+            UPDATE job_queues
+            SET
+                attempts = attempts + 1,
+                status = CASE WHEN attempts + 1 >= $2 THEN 'dead' ELSE 'failed' END,
+                available_at = NOW() + $3::INTEGER * INTERVAL '1 second' * POWER(2, attempts),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            max_attempts,
+            retry_interval
+        )
         .fetch_one(db)
         .await?;
 
         Ok(job_queue)
     }
 
+    /// Puts the job back to `pending`, workers won't pick it before `available_at`.
+    pub async fn defer(
+        db: &sqlx::PgPool,
+        id: &i32,
+        available_at: &NaiveDateTime,
+    ) -> Result<JobQueue, sqlx::Error> {
+        let job_queue = sqlx::query_as!(
+            JobQueue,
+            r#"
+            UPDATE job_queues
+            SET status = 'pending', available_at = $2, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            available_at
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_queue)
+    }
+
+    /// Puts jobs left `in_progress` by a worker that died back to `pending`.
+    pub async fn requeue_stale(db: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE job_queues
+            SET status = 'pending', updated_at = NOW()
+            WHERE status = 'in_progress' AND updated_at < NOW() - INTERVAL '1 hour'
+            "#,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Moves the queued jobs of a schedule that are in one of `from_statuses`
+    /// to `status`.
+    pub async fn update_status_by_schedule_id(
+        db: &sqlx::PgPool,
+        job_schedule_id: i32,
+        from_statuses: &[String],
+        status: &str,
+    ) -> Result<Vec<JobQueue>, sqlx::Error> {
+        let job_queues = sqlx::query_as!(
+            JobQueue,
+            r#"
+            UPDATE job_queues
+            SET status = $3, updated_at = NOW()
+            WHERE job_schedule_id = $1 AND status = ANY($2)
+            RETURNING *
+            "#,
+            job_schedule_id,
+            from_statuses,
+            status
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_queues)
+    }
+
+    pub async fn get_by_schedule_id(
+        db: &sqlx::PgPool,
+        job_schedule_id: i32,
+    ) -> Result<Vec<JobQueue>, sqlx::Error> {
+        let job_queues = sqlx::query_as!(
+            JobQueue,
+            r#"
+            SELECT * FROM job_queues
+            WHERE job_schedule_id = $1
+            ORDER BY created_at DESC
+            "#,
+            job_schedule_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_queues)
+    }
+
     pub async fn get_queue_not_completed_by_schedule_id(
         db: &sqlx::PgPool,
         job_schedule_id: i32,
@@ -112,7 +237,7 @@ impl JobQueue {
             JobQueue,
             r#"
             SELECT * FROM job_queues
-            WHERE job_schedule_id = $1 AND status != 'completed'
+            WHERE job_schedule_id = $1 AND status NOT IN ('completed', 'cancelled', 'skipped', 'dead')
             "#,
             job_schedule_id
         )
@@ -0,0 +1,242 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::delivery::Delivery;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JobRun {
+    pub id: i32,
+    pub job_queue_id: i32,
+    pub job_schedule_id: Option<i32>,
+    pub job_type: String,
+    pub merchant_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    pub invoice_id: Option<Uuid>,
+    pub status: String,
+    pub error: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub recipient_count: Option<i32>,
+    pub reached_count: Option<i32>,
+    pub failed_count: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct JobRunWithDeliveries {
+    #[serde(flatten)]
+    pub job_run: JobRun,
+    pub deliveries: Vec<Delivery>,
+}
+
+impl JobRun {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        job_queue_id: i32,
+        job_schedule_id: Option<i32>,
+        job_type: &str,
+        merchant_id: Option<Uuid>,
+        customer_id: Option<Uuid>,
+        invoice_id: Option<Uuid>,
+    ) -> Result<JobRun, sqlx::Error> {
+        let job_run = sqlx::query_as!(
+            JobRun,
+            r#"
+            INSERT INTO job_runs (job_queue_id, job_schedule_id, job_type, merchant_id, customer_id, invoice_id, status)
+            VALUES ($1, $2, $3, $4, $5, $6, 'in_progress')
+            RETURNING *
+            "#,
+            job_queue_id,
+            job_schedule_id,
+            job_type,
+            merchant_id,
+            customer_id,
+            invoice_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_run)
+    }
+
+    pub async fn finish(
+        db: &sqlx::PgPool,
+        id: i32,
+        status: &str,
+        error: Option<String>,
+    ) -> Result<JobRun, sqlx::Error> {
+        let job_run = sqlx::query_as!(
+            JobRun,
+            r#"
+            UPDATE job_runs
+            SET status = $2, error = $3, finished_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            status,
+            error
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_run)
+    }
+
+    pub async fn set_recipient_counts(
+        db: &sqlx::PgPool,
+        id: i32,
+        recipient_count: i32,
+        reached_count: i32,
+        failed_count: i32,
+    ) -> Result<JobRun, sqlx::Error> {
+        let job_run = sqlx::query_as!(
+            JobRun,
+            r#"
+            UPDATE job_runs
+            SET recipient_count = $2, reached_count = $3, failed_count = $4
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            recipient_count,
+            reached_count,
+            failed_count
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_run)
+    }
+
+    pub async fn count_by_job_queue_id(
+        db: &sqlx::PgPool,
+        job_queue_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM job_runs
+            WHERE job_queue_id = $1
+            "#,
+            job_queue_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(row.count)
+    }
+
+    pub async fn get_by_invoice_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        invoice_id: &Uuid,
+    ) -> Result<Vec<JobRun>, sqlx::Error> {
+        let job_runs = sqlx::query_as!(
+            JobRun,
+            r#"
+            SELECT * FROM job_runs
+            WHERE merchant_id = $1 AND invoice_id = $2
+            ORDER BY started_at DESC
+            "#,
+            merchant_id,
+            invoice_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_runs)
+    }
+
+    pub async fn get_by_customer_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        customer_id: &Uuid,
+    ) -> Result<Vec<JobRun>, sqlx::Error> {
+        let job_runs = sqlx::query_as!(
+            JobRun,
+            r#"
+            SELECT * FROM job_runs
+            WHERE merchant_id = $1 AND (
+                customer_id = $2
+                -- tag reminders reach many customers in one run
+                OR id IN (
+                    SELECT deliveries.job_run_id FROM deliveries
+                    INNER JOIN customer_contact_channels
+                        ON customer_contact_channels.id = deliveries.customer_contact_channel_id
+                    WHERE customer_contact_channels.customer_id = $2
+                )
+            )
+            ORDER BY started_at DESC
+            "#,
+            merchant_id,
+            customer_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_runs)
+    }
+
+    pub async fn get_by_job_schedule_id(
+        db: &sqlx::PgPool,
+        job_schedule_id: i32,
+    ) -> Result<Vec<JobRun>, sqlx::Error> {
+        let job_runs = sqlx::query_as!(
+            JobRun,
+            r#"
+            SELECT * FROM job_runs
+            WHERE job_schedule_id = $1
+            ORDER BY started_at DESC
+            "#,
+            job_schedule_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(job_runs)
+    }
+
+    pub async fn with_deliveries(
+        db: &sqlx::PgPool,
+        job_runs: Vec<JobRun>,
+    ) -> Result<Vec<JobRunWithDeliveries>, sqlx::Error> {
+        let job_run_ids: Vec<i32> = job_runs.iter().map(|job_run| job_run.id).collect();
+        let deliveries = Delivery::get_by_job_run_ids(db, &job_run_ids).await?;
+
+        Ok(attach_deliveries(job_runs, deliveries))
+    }
+
+    /// Like `with_deliveries` but keeps only the deliveries to the customer,
+    /// a tag reminder run also sent to every other customer with the tag.
+    pub async fn with_customer_deliveries(
+        db: &sqlx::PgPool,
+        job_runs: Vec<JobRun>,
+        customer_id: &Uuid,
+    ) -> Result<Vec<JobRunWithDeliveries>, sqlx::Error> {
+        let job_run_ids: Vec<i32> = job_runs.iter().map(|job_run| job_run.id).collect();
+        let deliveries =
+            Delivery::get_by_job_run_ids_and_customer_id(db, &job_run_ids, customer_id).await?;
+
+        Ok(attach_deliveries(job_runs, deliveries))
+    }
+}
+
+fn attach_deliveries(
+    job_runs: Vec<JobRun>,
+    mut deliveries: Vec<Delivery>,
+) -> Vec<JobRunWithDeliveries> {
+    job_runs
+        .into_iter()
+        .map(|job_run| {
+            let (run_deliveries, rest): (Vec<Delivery>, Vec<Delivery>) = deliveries
+                .drain(..)
+                .partition(|delivery| delivery.job_run_id == job_run.id);
+            deliveries = rest;
+
+            JobRunWithDeliveries {
+                job_run,
+                deliveries: run_deliveries,
+            }
+        })
+        .collect()
+}
@@ -18,6 +18,21 @@ pub struct JobSchedule {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
+    pub end_at: Option<NaiveDateTime>,
+}
+
+/// Converts a `repeat_interval_type` into the interval in seconds stored on
+/// `job_schedules.repeat_interval`.
+pub fn repeat_interval_seconds(repeat_interval_type: &str) -> i64 {
+    match repeat_interval_type {
+        "ONCE" => 5,
+        "PERMINUTE" => chrono::Duration::minutes(1).num_seconds(),
+        "HOURLY" => chrono::Duration::hours(1).num_seconds(),
+        "DAILY" => chrono::Duration::days(1).num_seconds(),
+        "WEEKLY" => chrono::Duration::weeks(1).num_seconds(),
+        "MONTHLY" => chrono::Duration::weeks(4).num_seconds(),
+        _ => chrono::Duration::weeks(1).num_seconds(),
+    }
 }
 
 impl JobSchedule {
@@ -33,12 +48,13 @@ impl JobSchedule {
         status: &str,
         retry_count: Option<i32>,
         retry_interval: Option<i32>,
+        end_at: Option<NaiveDateTime>,
     ) -> Result<JobSchedule, sqlx::Error> {
         let job_schedule = sqlx::query_as!(
             JobSchedule,
             r#"
-            INSERT INTO job_schedules (job_type, job_data, run_at, repeat_interval, repeat_count, total_repeat_count, dependencies, status, retry_count, retry_interval)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            INSERT INTO job_schedules (job_type, job_data, run_at, repeat_interval, repeat_count, total_repeat_count, dependencies, status, retry_count, retry_interval, end_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *
             "#,
             job_type,
@@ -50,7 +66,8 @@ impl JobSchedule {
             dependencies,
             status,
             retry_count,
-            retry_interval
+            retry_interval,
+            end_at
         )
         .fetch_one(db)
         .await?;
@@ -76,6 +93,58 @@ impl JobSchedule {
         Ok(job_schedule)
     }
 
+    pub async fn get_by_id_and_merchant_id(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &str,
+    ) -> Result<JobSchedule, sqlx::Error> {
+        let job_schedule = sqlx::query_as!(
+            JobSchedule,
+            r#"
+            SELECT * FROM job_schedules
+            WHERE id = $1 AND job_data->>'merchant_id' = $2 AND deleted_at IS NULL
+            "#,
+            id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_schedule)
+    }
+
+    pub async fn update_timing(
+        db: &sqlx::PgPool,
+        id: i32,
+        run_at: &NaiveDateTime,
+        repeat_interval: Option<i64>,
+        repeat_count: Option<i32>,
+        total_repeat_count: Option<i32>,
+        end_at: Option<NaiveDateTime>,
+        status: &str,
+    ) -> Result<JobSchedule, sqlx::Error> {
+        let job_schedule = sqlx::query_as!(
+            JobSchedule,
+            r#"
+            UPDATE job_schedules
+            SET run_at = $1, repeat_interval = $2, repeat_count = $3, total_repeat_count = $4, end_at = $5, status = $6, updated_at = NOW()
+            WHERE id = $7
+            RETURNING *
+            "#,
+            run_at,
+            repeat_interval,
+            repeat_count,
+            total_repeat_count,
+            end_at,
+            status,
+            id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(job_schedule)
+    }
+
     pub async fn get_scheduled_jobs(db: &sqlx::PgPool) -> Result<Vec<JobSchedule>, sqlx::Error> {
         let job_schedules = sqlx::query_as!(
             JobSchedule,
@@ -198,6 +267,29 @@ impl JobSchedule {
         Ok(job_schedules)
     }
 
+    /// The snoozed `send_invoice` schedule of the invoice that hasn't been
+    /// queued yet, snoozing again moves it instead of adding another.
+    pub async fn get_snoozed_by_invoice_id(
+        db: &sqlx::PgPool,
+        invoice_id: &str,
+    ) -> Result<Option<JobSchedule>, sqlx::Error> {
+        let job_schedule = sqlx::query_as!(
+            JobSchedule,
+            r#"
+            SELECT * FROM job_schedules
+            WHERE job_type = 'send_invoice' AND job_data->>'invoice_id' = $1
+            AND job_data->>'snoozed' = 'true' AND status = 'scheduled'
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+            invoice_id
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(job_schedule)
+    }
+
     pub async fn get_by_job_data_json_by_customer_id(
         db: &sqlx::PgPool,
         customer_id: &str,
@@ -1,7 +1,11 @@
 use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::locale::Locale;
+use crate::utils::timezone;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Merchant {
     pub id: Uuid,
@@ -15,7 +19,9 @@ pub struct Merchant {
     pub phone_country_code: Option<String>,
     pub phone_number: Option<String>,
     pub tax: Option<f32>,
-    pub merchant_code: Option<String>
+    pub merchant_code: Option<String>,
+    pub timezone: String,
+    pub default_locale: String,
 }
 
 impl Merchant {
@@ -29,12 +35,14 @@ impl Merchant {
         phone_number: Option<String>,
         tax: Option<f32>,
         code: &String,
+        timezone: &str,
+        default_locale: &str,
     ) -> Result<Merchant, sqlx::Error> {
         let merchant = sqlx::query_as!(
             Merchant,
             r#"
-            INSERT INTO merchants (name, description, user_id, address, phone_country_code, phone_number, tax, merchant_code)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO merchants (name, description, user_id, address, phone_country_code, phone_number, tax, merchant_code, timezone, default_locale)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             RETURNING *
             "#,
             name,
@@ -44,7 +52,9 @@ impl Merchant {
             phone_country_code,
             phone_number,
             tax,
-            code
+            code,
+            timezone,
+            default_locale
         )
         .fetch_one(db)
         .await?;
@@ -61,14 +71,16 @@ impl Merchant {
         address: Option<String>,
         phone_country_code: Option<String>,
         phone_number: Option<String>,
-        tax: Option<f32>
+        tax: Option<f32>,
+        timezone: &str,
+        default_locale: &str,
     ) -> Result<Merchant, sqlx::Error> {
         let merchant = sqlx::query_as!(
             Merchant,
             r#"
             UPDATE merchants
-            SET name = $1, description = $2, address = $3, phone_country_code = $4, phone_number = $5, tax = $6
-            WHERE id = $7 AND user_id = $8 AND deleted_at IS NULL
+            SET name = $1, description = $2, address = $3, phone_country_code = $4, phone_number = $5, tax = $6, timezone = $7, default_locale = $10
+            WHERE id = $8 AND user_id = $9 AND deleted_at IS NULL
             RETURNING *
             "#,
             name,
@@ -77,8 +89,10 @@ impl Merchant {
             phone_country_code,
             phone_number,
             tax,
+            timezone,
             id,
             user_id,
+            default_locale,
         )
         .fetch_one(db)
         .await?;
@@ -159,6 +173,14 @@ impl Merchant {
         Ok(merchants)
     }
 
+    pub fn tz(&self) -> Tz {
+        timezone::parse_or_default(&self.timezone)
+    }
+
+    pub fn locale(&self) -> Locale {
+        Locale::parse_or_default(&self.default_locale)
+    }
+
     pub fn generate_merchant_code(name: &String) -> String {
         let code: u32 = rand::random();
         let code = code.to_string();
@@ -0,0 +1,98 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MerchantHoliday {
+    pub id: i32,
+    pub merchant_id: Uuid,
+    pub date: NaiveDate,
+    pub description: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl MerchantHoliday {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        date: &NaiveDate,
+        description: Option<String>,
+    ) -> Result<MerchantHoliday, sqlx::Error> {
+        let holiday = sqlx::query_as!(
+            MerchantHoliday,
+            r#"
+            INSERT INTO merchant_holidays (merchant_id, date, description)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            merchant_id,
+            date,
+            description
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(holiday)
+    }
+
+    pub async fn get_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+    ) -> Result<Vec<MerchantHoliday>, sqlx::Error> {
+        let holidays = sqlx::query_as!(
+            MerchantHoliday,
+            r#"
+            SELECT * FROM merchant_holidays
+            WHERE merchant_id = $1
+            ORDER BY date ASC
+            "#,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(holidays)
+    }
+
+    pub async fn get_upcoming_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        from: &NaiveDate,
+    ) -> Result<Vec<MerchantHoliday>, sqlx::Error> {
+        let holidays = sqlx::query_as!(
+            MerchantHoliday,
+            r#"
+            SELECT * FROM merchant_holidays
+            WHERE merchant_id = $1 AND date >= $2
+            ORDER BY date ASC
+            "#,
+            merchant_id,
+            from
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(holidays)
+    }
+
+    pub async fn delete(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+    ) -> Result<MerchantHoliday, sqlx::Error> {
+        let holiday = sqlx::query_as!(
+            MerchantHoliday,
+            r#"
+            DELETE FROM merchant_holidays
+            WHERE id = $1 AND merchant_id = $2
+            RETURNING *
+            "#,
+            id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(holiday)
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::jobs::rate_limiter::RateLimit;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerchantRateLimit {
+    pub id: i32,
+    pub merchant_id: Uuid,
+    pub channel: String,
+    pub per_minute: i32,
+    pub burst: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl MerchantRateLimit {
+    pub fn rate_limit(&self) -> RateLimit {
+        RateLimit {
+            per_minute: self.per_minute.max(0) as u32,
+            burst: self.burst.max(1) as u32,
+        }
+    }
+
+    pub async fn get_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+    ) -> Result<Vec<MerchantRateLimit>, sqlx::Error> {
+        let rate_limits = sqlx::query_as!(
+            MerchantRateLimit,
+            r#"
+            SELECT * FROM merchant_rate_limits
+            WHERE merchant_id = $1
+            ORDER BY channel ASC
+            "#,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rate_limits)
+    }
+
+    /// Replaces every limit of the merchant with `rate_limits`, given as
+    /// `(channel, per_minute, burst)`.
+    pub async fn replace_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        rate_limits: &[(String, i32, i32)],
+    ) -> Result<Vec<MerchantRateLimit>, sqlx::Error> {
+        let mut db_transaction = db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM merchant_rate_limits
+            WHERE merchant_id = $1
+            "#,
+            merchant_id
+        )
+        .execute(&mut db_transaction)
+        .await?;
+
+        let mut created = Vec::new();
+
+        for (channel, per_minute, burst) in rate_limits {
+            let rate_limit = sqlx::query_as!(
+                MerchantRateLimit,
+                r#"
+                INSERT INTO merchant_rate_limits (merchant_id, channel, per_minute, burst)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+                "#,
+                merchant_id,
+                channel,
+                per_minute,
+                burst
+            )
+            .fetch_one(&mut db_transaction)
+            .await?;
+
+            created.push(rate_limit);
+        }
+
+        db_transaction.commit().await?;
+
+        Ok(created)
+    }
+}
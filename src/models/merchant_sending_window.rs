@@ -0,0 +1,80 @@
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MerchantSendingWindow {
+    pub id: i32,
+    pub merchant_id: Uuid,
+    pub day_of_week: i16,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl MerchantSendingWindow {
+    pub async fn get_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+    ) -> Result<Vec<MerchantSendingWindow>, sqlx::Error> {
+        let windows = sqlx::query_as!(
+            MerchantSendingWindow,
+            r#"
+            SELECT * FROM merchant_sending_windows
+            WHERE merchant_id = $1
+            ORDER BY day_of_week ASC, start_time ASC
+            "#,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(windows)
+    }
+
+    /// Replaces every window of the merchant with `windows`, given as
+    /// `(day_of_week, start_time, end_time)`.
+    pub async fn replace_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        windows: &[(i16, NaiveTime, NaiveTime)],
+    ) -> Result<Vec<MerchantSendingWindow>, sqlx::Error> {
+        let mut db_transaction = db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM merchant_sending_windows
+            WHERE merchant_id = $1
+            "#,
+            merchant_id
+        )
+        .execute(&mut db_transaction)
+        .await?;
+
+        let mut created = Vec::new();
+
+        for (day_of_week, start_time, end_time) in windows {
+            let window = sqlx::query_as!(
+                MerchantSendingWindow,
+                r#"
+                INSERT INTO merchant_sending_windows (merchant_id, day_of_week, start_time, end_time)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+                "#,
+                merchant_id,
+                day_of_week,
+                start_time,
+                end_time
+            )
+            .fetch_one(&mut db_transaction)
+            .await?;
+
+            created.push(window);
+        }
+
+        db_transaction.commit().await?;
+
+        Ok(created)
+    }
+}
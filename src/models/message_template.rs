@@ -0,0 +1,134 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MessageTemplate {
+    pub id: i32,
+    pub merchant_id: Uuid,
+    pub event_type: String,
+    pub channel: String,
+    pub subject: Option<String>,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl MessageTemplate {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        event_type: &str,
+        channel: &str,
+        subject: Option<String>,
+        body: &str,
+    ) -> Result<MessageTemplate, sqlx::Error> {
+        let message_template = sqlx::query_as!(
+            MessageTemplate,
+            r#"
+            INSERT INTO message_templates (merchant_id, event_type, channel, subject, body)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+            merchant_id,
+            event_type,
+            channel,
+            subject,
+            body
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(message_template)
+    }
+
+    pub async fn get_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+    ) -> Result<Vec<MessageTemplate>, sqlx::Error> {
+        let message_templates = sqlx::query_as!(
+            MessageTemplate,
+            r#"
+            SELECT * FROM message_templates
+            WHERE merchant_id = $1
+            ORDER BY event_type, channel
+            "#,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(message_templates)
+    }
+
+    pub async fn get_by_merchant_id_and_event_type(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        event_type: &str,
+    ) -> Result<Vec<MessageTemplate>, sqlx::Error> {
+        let message_templates = sqlx::query_as!(
+            MessageTemplate,
+            r#"
+            SELECT * FROM message_templates
+            WHERE merchant_id = $1 AND event_type = $2
+            "#,
+            merchant_id,
+            event_type
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(message_templates)
+    }
+
+    pub async fn update(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+        event_type: &str,
+        channel: &str,
+        subject: Option<String>,
+        body: &str,
+    ) -> Result<MessageTemplate, sqlx::Error> {
+        let message_template = sqlx::query_as!(
+            MessageTemplate,
+            r#"
+            UPDATE message_templates
+            SET event_type = $3, channel = $4, subject = $5, body = $6, updated_at = NOW()
+            WHERE id = $1 AND merchant_id = $2
+            RETURNING *
+            "#,
+            id,
+            merchant_id,
+            event_type,
+            channel,
+            subject,
+            body
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(message_template)
+    }
+
+    pub async fn delete(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+    ) -> Result<MessageTemplate, sqlx::Error> {
+        let message_template = sqlx::query_as!(
+            MessageTemplate,
+            r#"
+            DELETE FROM message_templates
+            WHERE id = $1 AND merchant_id = $2
+            RETURNING *
+            "#,
+            id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(message_template)
+    }
+}
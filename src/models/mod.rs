@@ -11,4 +11,17 @@ pub mod job_schedule;
 pub mod job_queue;
 pub mod tester;
 pub mod verification;
-pub mod item;
\ No newline at end of file
+pub mod item;pub mod job_run;
+pub mod delivery;
+pub mod merchant_sending_window;
+pub mod merchant_holiday;
+pub mod job_deferral;
+pub mod merchant_rate_limit;
+pub mod notification_outbox;
+pub mod message_template;
+pub mod telegram_invite;
+
+pub mod conversation_message;
+pub mod conversation_state;
+pub mod webhook_subscription;
+pub mod webhook_delivery;
\ No newline at end of file
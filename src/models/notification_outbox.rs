@@ -0,0 +1,52 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotificationOutbox {
+    pub id: i32,
+    pub channel: String,
+    pub recipient: String,
+    pub additional_value: Option<String>,
+    pub subject: Option<String>,
+    pub sender_name: Option<String>,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub html: Option<String>,
+    /// Filenames of the attachments.
+    pub attachments: Vec<String>,
+}
+
+impl NotificationOutbox {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        channel: &str,
+        recipient: &str,
+        additional_value: Option<String>,
+        subject: Option<String>,
+        sender_name: Option<String>,
+        body: &str,
+        html: Option<String>,
+        attachments: &[String],
+    ) -> Result<NotificationOutbox, sqlx::Error> {
+        let notification_outbox = sqlx::query_as!(
+            NotificationOutbox,
+            r#"
+            INSERT INTO notification_outbox (channel, recipient, additional_value, subject, sender_name, body, html, attachments)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+            channel,
+            recipient,
+            additional_value,
+            subject,
+            sender_name,
+            body,
+            html,
+            attachments
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(notification_outbox)
+    }
+}
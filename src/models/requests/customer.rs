@@ -1,7 +1,11 @@
+use std::borrow::Cow;
+
 use serde::Deserialize;
 use uuid::Uuid;
 use validator_derive::Validate;
 
+use crate::locale::Locale;
+
 #[derive(Deserialize, Validate, Debug)]
 pub struct RequestCreateCustomer {
     #[validate(length(min = 4, max = 24), required)]
@@ -15,6 +19,9 @@ pub struct RequestCreateCustomer {
     
     #[validate(required)]
     pub contact_channel_value: Option<String>,
+
+    #[validate(custom = "validate_locale")]
+    pub locale: Option<String>,
 }
 
 #[derive(Deserialize, Validate, Debug)]
@@ -24,9 +31,38 @@ pub struct RequestUpdateCustomer {
 
     #[validate(required)]
     pub tags: Option<Vec<String>>,
+
+    #[validate(custom = "validate_locale")]
+    pub locale: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestCustomerContactChannel {
+    #[validate(required)]
+    pub contact_channel_id: Option<Uuid>,
+
+    #[validate(length(min = 1, max = 255), required)]
+    pub contact_channel_value: Option<String>,
+
+    /// Makes the channel the customer's primary one.
+    pub is_primary: Option<bool>,
 }
 
 #[derive(Deserialize, Validate, Debug)]
 pub struct  RequestGetCustomers {
     pub tags: Option<String>,
-}
\ No newline at end of file
+}
+
+pub fn validate_locale(locale: &str) -> Result<(), validator::ValidationError> {
+    if Locale::parse(locale).is_some() {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_locale"),
+        message: Some(Cow::from("Locale must be id or en")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
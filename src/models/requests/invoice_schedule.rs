@@ -14,6 +14,8 @@ pub struct RequestInvoiceSchedule {
     pub start_at: Option<NaiveDateTime>,
     #[serde(with = "default_date_format")]
     pub end_at: Option<NaiveDateTime>,
+    /// Attach the invoice as PDF to emails.
+    pub attach_pdf: Option<bool>,
 }
 #[derive(Deserialize, Validate, Debug)]
 pub struct RequestSetStatusInvoiceSchedule {
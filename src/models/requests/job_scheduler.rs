@@ -27,6 +27,16 @@ pub struct RequestSetStatusSchedule {
     pub status: String,
 }
 
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestUpdateSchedule {
+    #[serde(default, with = "default_date_format")]
+    pub run_at: Option<NaiveDateTime>,
+    #[validate(custom = "validate_repeat_interval_type")]
+    pub repeat_interval_type: Option<String>,
+    #[serde(default, with = "default_date_format")]
+    pub end_at: Option<NaiveDateTime>,
+}
+
 impl RequestSchedule {
     pub fn to_string(&self) -> String {
         let mut result = String::new();
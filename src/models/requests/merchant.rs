@@ -1,6 +1,13 @@
+use std::borrow::Cow;
+
+use chrono::{NaiveDate, NaiveTime};
 use serde::Deserialize;
+use validator::Validate;
 use validator_derive::Validate;
 
+use crate::models::requests::customer::validate_locale;
+use crate::utils::timezone;
+
 #[derive(Deserialize, Validate, Debug)]
 pub struct RequestCreateMerchant {
     #[validate(required, length(min = 4, max = 24))]
@@ -14,6 +21,10 @@ pub struct RequestCreateMerchant {
     pub phone_number: Option<String>,
     #[validate(range(min = 0.0, max = 1))]
     pub tax: Option<f32>,
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<String>,
+    #[validate(custom = "validate_locale")]
+    pub default_locale: Option<String>,
 }
 
 #[derive(Deserialize, Validate, Debug)]
@@ -28,4 +39,119 @@ pub struct RequestUpdateMerchant {
     #[validate(length(min = 11, max = 15))]
     pub phone_number: Option<String>,
     pub tax: Option<f32>,
-}
\ No newline at end of file
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<String>,
+    #[validate(custom = "validate_locale")]
+    pub default_locale: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestSendingWindow {
+    #[validate(custom = "validate_days")]
+    pub days: Vec<i16>,
+    pub start_time: String,
+    pub end_time: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestSetSendingWindows {
+    #[validate]
+    pub windows: Vec<RequestSendingWindow>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestCreateHoliday {
+    pub date: NaiveDate,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestRateLimit {
+    #[validate(custom = "validate_channel")]
+    pub channel: String,
+    #[validate(range(min = 1, max = 6000))]
+    pub per_minute: i32,
+    #[validate(range(min = 1, max = 1000))]
+    pub burst: Option<i32>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestSetRateLimits {
+    #[validate]
+    pub rate_limits: Vec<RequestRateLimit>,
+}
+
+impl RequestSendingWindow {
+    /// Expands the window to one `(day_of_week, start_time, end_time)` row per day.
+    pub fn to_rows(&self) -> Result<Vec<(i16, NaiveTime, NaiveTime)>, String> {
+        let start_time = match NaiveTime::parse_from_str(&self.start_time, "%H:%M") {
+            Ok(start_time) => start_time,
+            Err(_) => return Err(format!("start_time {} must be formatted as HH:MM", self.start_time)),
+        };
+
+        let end_time = match NaiveTime::parse_from_str(&self.end_time, "%H:%M") {
+            Ok(end_time) => end_time,
+            Err(_) => return Err(format!("end_time {} must be formatted as HH:MM", self.end_time)),
+        };
+
+        if end_time <= start_time {
+            return Err(format!(
+                "end_time {} must be after start_time {}",
+                self.end_time, self.start_time
+            ));
+        }
+
+        Ok(self
+            .days
+            .iter()
+            .map(|day| (*day, start_time, end_time))
+            .collect())
+    }
+}
+
+fn validate_days(days: &Vec<i16>) -> Result<(), validator::ValidationError> {
+    if !days.is_empty() && days.iter().all(|day| (1..=7).contains(day)) {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_days"),
+        message: Some(Cow::from(
+            "Days must be ISO days of week, 1 (Monday) to 7 (Sunday)",
+        )),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
+
+fn validate_channel(channel: &str) -> Result<(), validator::ValidationError> {
+    if channel == "whatsapp" || channel == "email" || channel == "telegram" {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_channel"),
+        message: Some(Cow::from("Channel must be whatsapp, email or telegram")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
+
+fn validate_timezone(name: &str) -> Result<(), validator::ValidationError> {
+    if timezone::parse(name).is_some() {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_timezone"),
+        message: Some(Cow::from(
+            "Timezone must be a valid IANA timezone, e.g. Asia/Jakarta",
+        )),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
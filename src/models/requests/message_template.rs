@@ -0,0 +1,83 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+use crate::templates::{self, ALL_CHANNELS};
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestMessageTemplate {
+    #[validate(custom = "validate_event_type")]
+    pub event_type: String,
+    #[validate(custom = "validate_template_channel")]
+    pub channel: Option<String>,
+    #[validate(length(max = 255))]
+    pub subject: Option<String>,
+    #[validate(length(min = 1, max = 4096))]
+    pub body: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestPreviewMessageTemplate {
+    #[serde(flatten)]
+    #[validate]
+    pub template: RequestMessageTemplate,
+    /// Overrides the sample values of the event's variables.
+    pub values: Option<HashMap<String, String>>,
+}
+
+impl RequestMessageTemplate {
+    pub fn channel(&self) -> &str {
+        self.channel.as_deref().unwrap_or(ALL_CHANNELS)
+    }
+
+    /// Rejects placeholders the event type doesn't fill, they would be sent
+    /// to customers as they are.
+    pub fn validate_variables(&self) -> Result<(), String> {
+        let text = format!("{} {}", self.subject.as_deref().unwrap_or_default(), self.body);
+        let unknown = templates::unknown_variables(&text, &self.event_type);
+
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        let known = templates::event_variables(&self.event_type).unwrap_or(&[]);
+
+        Err(format!(
+            "unknown variables {} for {}, available variables are {}",
+            unknown.join(", "),
+            self.event_type,
+            known.join(", ")
+        ))
+    }
+}
+
+fn validate_event_type(event_type: &str) -> Result<(), validator::ValidationError> {
+    if templates::event_variables(event_type).is_some() {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_event_type"),
+        message: Some(Cow::from("Event type must be invoice or reminder")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
+
+fn validate_template_channel(channel: &str) -> Result<(), validator::ValidationError> {
+    if channel == ALL_CHANNELS || channel == "whatsapp" || channel == "email" || channel == "telegram"
+    {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_channel"),
+        message: Some(Cow::from("Channel must be all, whatsapp, email or telegram")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
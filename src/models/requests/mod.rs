@@ -5,4 +5,8 @@ pub mod customer;
 pub mod invoice;
 pub mod invoice_schedule;
 pub mod job_scheduler;
-pub mod telegram;
\ No newline at end of file
+pub mod telegram;
+pub mod message_template;
+pub mod whatsapp;
+pub mod xendit;
+pub mod webhook_subscription;
\ No newline at end of file
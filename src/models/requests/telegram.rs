@@ -4,6 +4,7 @@ use validator_derive::Validate;
 pub struct TelegramUpdateItem {
     pub update_id: Option<i64>,
     pub message: Option<TelegramMessage>,
+    pub callback_query: Option<TelegramCallbackQuery>,
 }
 #[derive(Deserialize, Validate, Debug)]
 pub struct TelegramMessage {
@@ -26,4 +27,13 @@ pub struct TelegramChat {
     pub id: Option<i64>,
     pub first_name: Option<String>,
     pub username: Option<String>,
-}
\ No newline at end of file
+}
+/// Sent when a customer presses a button of an inline keyboard.
+#[derive(Deserialize, Validate, Debug)]
+pub struct TelegramCallbackQuery {
+    pub id: String,
+    pub from: Option<TelegramUser>,
+    /// Message the keyboard belongs to.
+    pub message: Option<TelegramMessage>,
+    pub data: Option<String>,
+}
@@ -0,0 +1,80 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use validator_derive::Validate;
+
+use crate::webhooks::{CHAT_EVENT_TYPES, EVENT_TYPES, KINDS};
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RequestWebhookSubscription {
+    /// `webhook` when left out on create, kept when left out on update.
+    #[validate(custom = "validate_kind")]
+    pub kind: Option<String>,
+    /// The https endpoint, or the incoming webhook url of Slack or Discord.
+    #[validate(url, length(max = 2048))]
+    pub url: String,
+    /// Generated when left out on create, kept when left out on update.
+    #[validate(length(min = 16, max = 255))]
+    pub secret: Option<String>,
+    #[validate(length(min = 1), custom = "validate_event_types")]
+    pub event_types: Vec<String>,
+    pub is_active: Option<bool>,
+}
+
+impl RequestWebhookSubscription {
+    /// Slack and Discord only get the events that have a message.
+    pub fn validate_kind_event_types(&self, kind: &str) -> Result<(), String> {
+        if kind == "webhook" {
+            return Ok(());
+        }
+
+        let unsupported = self
+            .event_types
+            .iter()
+            .filter(|event_type| !CHAT_EVENT_TYPES.contains(&event_type.as_str()))
+            .map(|event_type| event_type.as_str())
+            .collect::<Vec<&str>>();
+
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+
+        Err(format!(
+            "{} can't be sent to {}, available event types are {}",
+            unsupported.join(", "),
+            kind,
+            CHAT_EVENT_TYPES.join(", ")
+        ))
+    }
+}
+
+fn validate_kind(kind: &str) -> Result<(), validator::ValidationError> {
+    if KINDS.contains(&kind) {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_kind"),
+        message: Some(Cow::from("Kind must be webhook, slack or discord")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
+
+fn validate_event_types(event_types: &Vec<String>) -> Result<(), validator::ValidationError> {
+    if event_types
+        .iter()
+        .all(|event_type| EVENT_TYPES.contains(&event_type.as_str()))
+    {
+        return Ok(());
+    }
+
+    let err = validator::ValidationError {
+        code: Cow::from("invalid_event_types"),
+        message: Some(Cow::from("Event types must be webhook event types")),
+        params: Default::default(),
+    };
+
+    return Err(err);
+}
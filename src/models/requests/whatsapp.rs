@@ -0,0 +1,10 @@
+use serde::Deserialize;
+use validator_derive::Validate;
+
+/// Incoming message forwarded by the WhatsApp gateway, named like the
+/// parameters of its send API.
+#[derive(Deserialize, Validate, Debug)]
+pub struct WhatsappInboundMessage {
+    pub number: String,
+    pub message: String,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Body of the Xendit invoice callback, only the fields that are used.
+#[derive(Deserialize, Debug)]
+pub struct XenditInvoiceCallback {
+    /// Xendit's invoice id, `id` of `invoices.xendit_invoice_payload`.
+    pub id: String,
+    pub external_id: String,
+    /// `PENDING`, `PAID`, `SETTLED` or `EXPIRED`.
+    pub status: String,
+    pub paid_at: Option<DateTime<Utc>>,
+}
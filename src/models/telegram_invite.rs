@@ -0,0 +1,88 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A link that connects the Telegram chat opening it to one customer.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TelegramInvite {
+    pub id: i32,
+    pub token: String,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub chat_id: Option<i64>,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl TelegramInvite {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        token: &str,
+        merchant_id: &Uuid,
+        customer_id: &Uuid,
+        expires_at: &NaiveDateTime,
+    ) -> Result<TelegramInvite, sqlx::Error> {
+        let telegram_invite = sqlx::query_as!(
+            TelegramInvite,
+            r#"
+            INSERT INTO telegram_invites (token, merchant_id, customer_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            token,
+            merchant_id,
+            customer_id,
+            expires_at
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(telegram_invite)
+    }
+
+    pub async fn get_by_token(
+        db: &sqlx::PgPool,
+        token: &str,
+    ) -> Result<TelegramInvite, sqlx::Error> {
+        let telegram_invite = sqlx::query_as!(
+            TelegramInvite,
+            r#"
+            SELECT * FROM telegram_invites
+            WHERE token = $1
+            "#,
+            token
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(telegram_invite)
+    }
+
+    pub async fn mark_used(
+        db: &sqlx::PgPool,
+        id: i32,
+        chat_id: &i64,
+    ) -> Result<TelegramInvite, sqlx::Error> {
+        let telegram_invite = sqlx::query_as!(
+            TelegramInvite,
+            r#"
+            UPDATE telegram_invites
+            SET chat_id = $2, used_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            chat_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(telegram_invite)
+    }
+
+    /// Invites can be opened once, before they expire.
+    pub fn is_valid(&self, now: &NaiveDateTime) -> bool {
+        self.used_at.is_none() && self.expires_at > *now
+    }
+}
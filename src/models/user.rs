@@ -13,6 +13,7 @@ pub struct User {
     pub deleted_at: Option<NaiveDateTime>,
     pub status: String,
     pub verified_at: Option<NaiveDateTime>,
+    pub telegram_chat_id: Option<i64>,
 }
 
 impl User {
@@ -118,4 +119,62 @@ impl User {
 
         Ok(user)
     }
+
+    pub async fn get_by_telegram_chat_id(
+        db: &sqlx::PgPool,
+        telegram_chat_id: &i64,
+    ) -> Result<User, sqlx::Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT * FROM users
+            WHERE telegram_chat_id = $1 AND deleted_at IS NULL
+            "#,
+            telegram_chat_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Links the Telegram chat to the user, a chat belongs to one user so it
+    /// is unlinked from any other first. `None` unlinks the user's chat.
+    pub async fn update_telegram_chat_id(
+        db: &sqlx::PgPool,
+        id: &Uuid,
+        telegram_chat_id: Option<i64>,
+    ) -> Result<User, sqlx::Error> {
+        let mut db_transaction = db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET telegram_chat_id = NULL
+            WHERE telegram_chat_id = $1 AND id <> $2
+            "#,
+            telegram_chat_id,
+            id
+        )
+        .execute(&mut db_transaction)
+        .await?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET telegram_chat_id = $1
+            WHERE id = $2
+            RETURNING *
+            "#,
+            telegram_chat_id,
+            id
+        )
+        .fetch_one(&mut db_transaction)
+        .await?;
+
+        db_transaction.commit().await?;
+
+        Ok(user)
+    }
 }
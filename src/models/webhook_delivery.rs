@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// An event posted, or to be posted, to a webhook subscription with the
+/// outcome of its last attempt.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_subscription_id: i32,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub attempted_at: Option<NaiveDateTime>,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl WebhookDelivery {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        webhook_subscription_id: i32,
+        event_type: &str,
+        payload: &Value,
+    ) -> Result<WebhookDelivery, sqlx::Error> {
+        let webhook_delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            INSERT INTO webhook_deliveries (webhook_subscription_id, event_type, payload)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            webhook_subscription_id,
+            event_type,
+            payload
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_delivery)
+    }
+
+    pub async fn get_by_id(db: &sqlx::PgPool, id: i32) -> Result<WebhookDelivery, sqlx::Error> {
+        let webhook_delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_delivery)
+    }
+
+    /// Delivery log of the subscription, newest first.
+    pub async fn get_by_subscription_id(
+        db: &sqlx::PgPool,
+        webhook_subscription_id: i32,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        let webhook_deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_subscription_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            webhook_subscription_id,
+            limit
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(webhook_deliveries)
+    }
+
+    /// Records an attempt, `delivered_at` is set when `status` is delivered.
+    pub async fn record_attempt(
+        db: &sqlx::PgPool,
+        id: i32,
+        status: &str,
+        response_status: Option<i32>,
+        response_body: Option<String>,
+        error: Option<String>,
+    ) -> Result<WebhookDelivery, sqlx::Error> {
+        let webhook_delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            UPDATE webhook_deliveries
+            SET
+                status = $2,
+                attempts = attempts + 1,
+                response_status = $3,
+                response_body = $4,
+                error = $5,
+                attempted_at = NOW(),
+                delivered_at = CASE WHEN $2::VARCHAR = 'delivered' THEN NOW() ELSE NULL END
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            status,
+            response_status,
+            response_body,
+            error
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_delivery)
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An endpoint of the merchant that receives the events it subscribed to.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookSubscription {
+    pub id: i32,
+    pub merchant_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub deleted_at: Option<NaiveDateTime>,
+    /// `webhook`, `slack` or `discord`.
+    pub kind: String,
+    pub summary_sent_at: Option<NaiveDateTime>,
+}
+
+impl WebhookSubscription {
+    pub async fn create(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        kind: &str,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+        is_active: bool,
+    ) -> Result<WebhookSubscription, sqlx::Error> {
+        let webhook_subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            INSERT INTO webhook_subscriptions
+                (merchant_id, kind, url, secret, event_types, is_active)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            merchant_id,
+            kind,
+            url,
+            secret,
+            event_types,
+            is_active
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_subscription)
+    }
+
+    pub async fn update(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+        kind: &str,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+        is_active: bool,
+    ) -> Result<WebhookSubscription, sqlx::Error> {
+        let webhook_subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            UPDATE webhook_subscriptions
+            SET
+                kind = $3, url = $4, secret = $5, event_types = $6, is_active = $7,
+                updated_at = NOW()
+            WHERE id = $1 AND merchant_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            merchant_id,
+            kind,
+            url,
+            secret,
+            event_types,
+            is_active
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_subscription)
+    }
+
+    pub async fn delete(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+    ) -> Result<WebhookSubscription, sqlx::Error> {
+        let webhook_subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            UPDATE webhook_subscriptions
+            SET deleted_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND merchant_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+            id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_subscription)
+    }
+
+    pub async fn get_by_id(
+        db: &sqlx::PgPool,
+        id: i32,
+        merchant_id: &Uuid,
+    ) -> Result<WebhookSubscription, sqlx::Error> {
+        let webhook_subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE id = $1 AND merchant_id = $2 AND deleted_at IS NULL
+            "#,
+            id,
+            merchant_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(webhook_subscription)
+    }
+
+    pub async fn get_by_merchant_id(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+    ) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+        let webhook_subscriptions = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE merchant_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+            merchant_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(webhook_subscriptions)
+    }
+
+    /// Active subscriptions of the merchant to `event_type`.
+    pub async fn get_active_by_event_type(
+        db: &sqlx::PgPool,
+        merchant_id: &Uuid,
+        event_type: &str,
+    ) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+        let webhook_subscriptions = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE
+                merchant_id = $1 AND $2 = ANY(event_types)
+                AND is_active AND deleted_at IS NULL
+            "#,
+            merchant_id,
+            event_type
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(webhook_subscriptions)
+    }
+
+    /// Active subscriptions to `event_type` of every merchant.
+    pub async fn get_all_active_by_event_type(
+        db: &sqlx::PgPool,
+        event_type: &str,
+    ) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+        let webhook_subscriptions = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT * FROM webhook_subscriptions
+            WHERE $1 = ANY(event_types) AND is_active AND deleted_at IS NULL
+            ORDER BY merchant_id
+            "#,
+            event_type
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(webhook_subscriptions)
+    }
+
+    /// Records that the daily summary was queued, unless it already was after
+    /// `sent_since`. Returns `None` when another worker got there first.
+    pub async fn claim_summary(
+        db: &sqlx::PgPool,
+        id: i32,
+        sent_since: &NaiveDateTime,
+    ) -> Result<Option<WebhookSubscription>, sqlx::Error> {
+        let webhook_subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            UPDATE webhook_subscriptions
+            SET summary_sent_at = NOW()
+            WHERE
+                id = $1 AND deleted_at IS NULL
+                AND (summary_sent_at IS NULL OR summary_sent_at < $2)
+            RETURNING *
+            "#,
+            id,
+            sent_since
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(webhook_subscription)
+    }
+}
@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{
+        header::{ContentType, Header, HeaderName, HeaderValue},
+        Attachment, Mailbox, MultiPart, SinglePart,
+    },
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use crate::errors::DefaultError;
+
+use super::{Notification, NotificationChannel, Recipient};
+
+/// Sends email through any SMTP server, SendGrid unless `SMTP_HOST` is set.
+pub struct SmtpEmailChannel {
+    host: String,
+    /// Built once and shared by every send, it keeps a pool of connections.
+    mailer: Result<SmtpTransport, String>,
+    from_address: String,
+}
+
+impl SmtpEmailChannel {
+    /// Reads `SMTP_HOST`, `SMTP_PORT`, `SMTP_TLS`, `SMTP_USERNAME`,
+    /// `SMTP_PASSWORD` and `SMTP_FROM`. The password falls back to
+    /// `EMAIL_SENDGRID_API_KEY` for existing setups.
+    pub fn from_env() -> Self {
+        let env = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+
+        let host = env("SMTP_HOST").unwrap_or_else(|| "smtp.sendgrid.net".to_string());
+        let port = env("SMTP_PORT").and_then(|port| port.parse().ok());
+        let tls = env("SMTP_TLS").map(|tls| tls != "false").unwrap_or(true);
+        let username = env("SMTP_USERNAME").unwrap_or_else(|| "apikey".to_string());
+        let password = env("SMTP_PASSWORD")
+            .or_else(|| env("EMAIL_SENDGRID_API_KEY"))
+            .unwrap_or_default();
+
+        Self {
+            mailer: mailer(&host, port, tls, username, password),
+            host,
+            from_address: env("SMTP_FROM").unwrap_or_else(|| "hello@inving.co".to_string()),
+        }
+    }
+}
+
+fn mailer(
+    host: &str,
+    port: Option<u16>,
+    tls: bool,
+    username: String,
+    password: String,
+) -> Result<SmtpTransport, String> {
+    let builder = if tls {
+        SmtpTransport::relay(host).map_err(|err| err.to_string())?
+    } else {
+        // local catchers such as MailHog speak plain SMTP
+        SmtpTransport::builder_dangerous(host)
+    };
+
+    let builder = match port {
+        Some(port) => builder.port(port),
+        None => builder,
+    };
+
+    let builder = if username.is_empty() || password.is_empty() {
+        builder
+    } else {
+        builder.credentials(Credentials::new(username, password))
+    };
+
+    Ok(builder.build())
+}
+
+#[async_trait]
+impl NotificationChannel for SmtpEmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn send(
+        &self,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError> {
+        let to: Mailbox = match recipient.value.parse() {
+            Ok(to) => to,
+            Err(_) => {
+                return Err(DefaultError::new(
+                    recipient.value.clone(),
+                    "invalid email address".to_string(),
+                ))
+            }
+        };
+
+        let sender_name = notification.sender_name.as_deref().unwrap_or("Inving");
+        // a merchant name with commas or quotes stays a display name
+        let from = match self.from_address.parse() {
+            Ok(address) => Mailbox::new(Some(sender_name.to_string()), address),
+            Err(err) => {
+                return Err(DefaultError::new(
+                    self.from_address.clone(),
+                    err.to_string(),
+                ))
+            }
+        };
+
+        let builder = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(notification.subject.as_deref().unwrap_or("Inving"));
+
+        // lets mail clients show their own unsubscribe button (RFC 8058)
+        let builder = match &notification.unsubscribe_url {
+            Some(url) => builder
+                .header(ListUnsubscribe(url.clone()))
+                .header(ListUnsubscribePost),
+            None => builder,
+        };
+
+        let email = if notification.html.is_none() && notification.attachments.is_empty() {
+            builder.body(notification.body.clone())
+        } else {
+            builder.multipart(multipart(notification))
+        };
+
+        let email = match email {
+            Ok(email) => email,
+            Err(err) => return Err(DefaultError::new(recipient.value.clone(), err.to_string())),
+        };
+
+        let mailer = match &self.mailer {
+            Ok(mailer) => mailer.clone(),
+            Err(err) => return Err(DefaultError::new(self.host.clone(), err.clone())),
+        };
+
+        // SmtpTransport blocks while it talks to the server
+        let sent = tokio::task::spawn_blocking(move || {
+            mailer
+                .send(&email)
+                .map(|response| response.message().collect::<Vec<&str>>().join(" "))
+                .map_err(|err| err.to_string())
+        })
+        .await;
+
+        match sent {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(err)) => Err(DefaultError::new(recipient.value.clone(), err)),
+            Err(err) => Err(DefaultError::new(recipient.value.clone(), err.to_string())),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ListUnsubscribe(String);
+
+impl Header for ListUnsubscribe {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe")
+    }
+
+    fn parse(s: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(
+            s.trim_start_matches('<').trim_end_matches('>').to_string(),
+        ))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), format!("<{}>", self.0))
+    }
+}
+
+/// Marks `List-Unsubscribe` as a URL that opts out on a POST, without any
+/// page to confirm on.
+#[derive(Clone)]
+struct ListUnsubscribePost;
+
+impl Header for ListUnsubscribePost {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe-Post")
+    }
+
+    fn parse(_: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self)
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), "List-Unsubscribe=One-Click".to_string())
+    }
+}
+
+/// Text and HTML as alternatives, wrapped together with the attachments in a
+/// mixed part when there are any.
+fn multipart(notification: &Notification) -> MultiPart {
+    let content = match &notification.html {
+        Some(html) => MultiPart::alternative_plain_html(notification.body.clone(), html.clone()),
+        None => MultiPart::mixed().singlepart(SinglePart::plain(notification.body.clone())),
+    };
+
+    if notification.attachments.is_empty() {
+        return content;
+    }
+
+    notification.attachments.iter().fold(
+        MultiPart::mixed().multipart(content),
+        |multipart, attachment| {
+            let content_type =
+                ContentType::parse(&attachment.content_type).unwrap_or(ContentType::TEXT_PLAIN);
+
+            multipart.singlepart(
+                Attachment::new(attachment.filename.clone())
+                    .body(attachment.content.clone(), content_type),
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_click_unsubscribe_headers() {
+        let email = Message::builder()
+            .from("Toko Maju <hello@inving.co>".parse().unwrap())
+            .to("budi@example.com".parse().unwrap())
+            .subject("Invoice")
+            .header(ListUnsubscribe(
+                "https://api.inving.co/unsubscribe?id=1&token=2".to_string(),
+            ))
+            .header(ListUnsubscribePost)
+            .body("Halo".to_string())
+            .unwrap();
+
+        let formatted = String::from_utf8(email.formatted()).unwrap();
+
+        assert!(formatted
+            .contains("List-Unsubscribe: <https://api.inving.co/unsubscribe?id=1&token=2>\r\n"));
+        assert!(formatted.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    locale::{Locale, Text},
+    models::{merchant::Merchant, user::User},
+    templates,
+};
+
+use super::{Notification, Notifier, Recipient};
+
+/// Alerts the owner of the merchant in the Telegram chat they linked, nothing
+/// is sent when they didn't link one. `values` fills the placeholders of
+/// `text` in the merchant's locale, `{{merchant_name}}` is always set.
+///
+/// Alerts are best effort, failures are only logged so they never fail what
+/// triggered them.
+pub async fn send<F>(db: &PgPool, notifier: &Notifier, merchant_id: &Uuid, text: Text, values: F)
+where
+    F: FnOnce(Locale) -> HashMap<&'static str, String>,
+{
+    let merchant = match Merchant::get_by_id(db, *merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            println!("Failed to get merchant {} to alert: {}", merchant_id, err);
+            return;
+        }
+    };
+
+    let user = match User::get_by_id(db, merchant.user_id).await {
+        Ok(user) => user,
+        Err(err) => {
+            println!("Failed to get owner of merchant {} to alert: {}", merchant_id, err);
+            return;
+        }
+    };
+
+    let chat_id = match user.telegram_chat_id {
+        Some(chat_id) => chat_id,
+        None => return,
+    };
+
+    let locale = merchant.locale();
+    let mut values = values(locale);
+    values.insert("merchant_name", merchant.name.clone());
+
+    let message = templates::render(locale.text(text), &values);
+
+    match notifier
+        .send(
+            "telegram",
+            &Recipient::telegram_chat(&chat_id),
+            &Notification::new(&message),
+        )
+        .await
+    {
+        Ok(_) => (),
+        Err(err) => println!(
+            "Failed to alert merchant {}: {}",
+            merchant_id,
+            err.to_string()
+        ),
+    }
+}
@@ -0,0 +1,230 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::{
+    errors::DefaultError,
+    models::customer_contact_channel::CustomerContactChannelWithContactChannel,
+};
+
+pub mod email;
+pub mod merchant_alert;
+pub mod outbox;
+pub mod telegram;
+pub mod unsubscribe;
+pub mod whatsapp;
+
+use self::{
+    email::SmtpEmailChannel, outbox::OutboxChannel, telegram::TelegramChannel,
+    whatsapp::WhatsappChannel,
+};
+
+/// Names in `contact_channels.name` that have an adapter.
+pub const CHANNELS: [&str; 3] = ["email", "whatsapp", "telegram"];
+
+/// Where a notification goes, taken from a customer contact channel.
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    /// Email address, phone number or Telegram username.
+    pub value: String,
+    /// Telegram chat id once the customer connected to the bot.
+    pub additional_value: Option<String>,
+}
+
+impl Recipient {
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            additional_value: None,
+        }
+    }
+
+    pub fn telegram_chat(chat_id: &i64) -> Self {
+        Self {
+            value: chat_id.to_string(),
+            additional_value: Some(chat_id.to_string()),
+        }
+    }
+}
+
+impl From<&CustomerContactChannelWithContactChannel> for Recipient {
+    fn from(contact_channel: &CustomerContactChannelWithContactChannel) -> Self {
+        Self {
+            value: contact_channel.value.clone(),
+            additional_value: contact_channel.additional_value.clone(),
+        }
+    }
+}
+
+/// A file sent along with an email.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn pdf(filename: &str, content: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            content_type: "application/pdf".to_string(),
+            content,
+        }
+    }
+}
+
+/// A button under a Telegram message.
+#[derive(Debug, Clone)]
+pub enum Button {
+    /// Opens `url`.
+    Url { text: String, url: String },
+    /// Sends `data` back to the bot as a callback query, at most 64 bytes.
+    Callback { text: String, data: String },
+}
+
+impl Button {
+    pub fn url(text: &str, url: &str) -> Self {
+        Button::Url {
+            text: text.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    pub fn callback(text: &str, data: &str) -> Self {
+        Button::Callback {
+            text: text.to_string(),
+            data: data.to_string(),
+        }
+    }
+}
+
+/// A message to send, subject, sender name, HTML and attachments are only
+/// used by email, the keyboard only by Telegram.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub subject: Option<String>,
+    pub sender_name: Option<String>,
+    /// Plain text, also the text part of emails that have HTML.
+    pub body: String,
+    pub html: Option<String>,
+    pub attachments: Vec<Attachment>,
+    /// Rows of buttons.
+    pub keyboard: Vec<Vec<Button>>,
+    /// One-click unsubscribe URL, sent in the `List-Unsubscribe` header.
+    pub unsubscribe_url: Option<String>,
+}
+
+impl Notification {
+    pub fn new(body: &str) -> Self {
+        Self {
+            subject: None,
+            sender_name: None,
+            body: body.to_string(),
+            html: None,
+            attachments: Vec::new(),
+            keyboard: Vec::new(),
+            unsubscribe_url: None,
+        }
+    }
+
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn with_sender_name(mut self, sender_name: &str) -> Self {
+        self.sender_name = Some(sender_name.to_string());
+        self
+    }
+
+    pub fn with_html(mut self, html: &str) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    pub fn with_keyboard(mut self, keyboard: Vec<Vec<Button>>) -> Self {
+        self.keyboard = keyboard;
+        self
+    }
+}
+
+/// A way of reaching a recipient, one per `contact_channels.name`.
+///
+/// New channels are added by implementing this trait and registering the
+/// adapter in `Notifier::from_env`.
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Value of `contact_channels.name` the adapter sends through.
+    fn name(&self) -> &str;
+
+    /// Sends `notification` and returns the provider's response.
+    async fn send(
+        &self,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError>;
+}
+
+/// Picks the adapter of a contact channel by its name.
+pub struct Notifier {
+    channels: HashMap<String, Arc<dyn NotificationChannel>>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self {
+            channels: HashMap::new(),
+        }
+    }
+
+    pub fn register<C: NotificationChannel + 'static>(&mut self, channel: C) {
+        self.channels
+            .insert(channel.name().to_string(), Arc::new(channel));
+    }
+
+    /// Real providers, or the outbox table for every channel when
+    /// `NOTIFICATION_SINK=outbox` so development and tests send nothing out.
+    pub fn from_env(pool: &PgPool) -> Self {
+        let mut notifier = Self::new();
+
+        let sink = std::env::var("NOTIFICATION_SINK").unwrap_or_default();
+
+        if sink == "outbox" {
+            for name in CHANNELS {
+                notifier.register(OutboxChannel::new(pool.clone(), name));
+            }
+        } else {
+            notifier.register(SmtpEmailChannel::from_env());
+            notifier.register(WhatsappChannel);
+            notifier.register(TelegramChannel);
+        }
+
+        notifier
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn NotificationChannel>> {
+        self.channels.get(name).cloned()
+    }
+
+    pub async fn send(
+        &self,
+        channel: &str,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError> {
+        match self.get(channel) {
+            Some(adapter) => adapter.send(recipient, notification).await,
+            None => Err(DefaultError::new(
+                channel.to_string(),
+                "contact channel is not supported".to_string(),
+            )),
+        }
+    }
+}
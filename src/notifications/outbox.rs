@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{errors::DefaultError, models::notification_outbox::NotificationOutbox};
+
+use super::{Notification, NotificationChannel, Recipient};
+
+/// Stores notifications in `notification_outbox` instead of sending them,
+/// stands in for every channel in development and tests.
+pub struct OutboxChannel {
+    pool: PgPool,
+    name: String,
+}
+
+impl OutboxChannel {
+    pub fn new(pool: PgPool, name: &str) -> Self {
+        Self {
+            pool,
+            name: name.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for OutboxChannel {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(
+        &self,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError> {
+        match NotificationOutbox::create(
+            &self.pool,
+            &self.name,
+            &recipient.value,
+            recipient.additional_value.clone(),
+            notification.subject.clone(),
+            notification.sender_name.clone(),
+            &notification.body,
+            notification.html.clone(),
+            &notification
+                .attachments
+                .iter()
+                .map(|attachment| attachment.filename.clone())
+                .collect::<Vec<String>>(),
+        )
+        .await
+        {
+            Ok(outbox) => Ok(json!({ "id": format!("outbox-{}", outbox.id) }).to_string()),
+            Err(err) => Err(DefaultError::new(recipient.value.clone(), err.to_string())),
+        }
+    }
+}
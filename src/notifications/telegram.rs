@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::{errors::DefaultError, repositories::telegram::telegram_send_message};
+
+use super::{Button, Notification, NotificationChannel, Recipient};
+
+/// Sends to the chat the customer opened with the bot.
+pub struct TelegramChannel;
+
+#[async_trait]
+impl NotificationChannel for TelegramChannel {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn send(
+        &self,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError> {
+        let chat_id = match recipient
+            .additional_value
+            .as_ref()
+            .and_then(|additional_value| additional_value.parse::<i64>().ok())
+        {
+            Some(chat_id) => chat_id,
+            None => {
+                return Err(DefaultError::new(
+                    recipient.value.clone(),
+                    "No telegram chat id registered".to_string(),
+                ))
+            }
+        };
+
+        telegram_send_message(&chat_id, &notification.body, reply_markup(notification)).await
+    }
+}
+
+/// The keyboard of the notification as an `InlineKeyboardMarkup`.
+fn reply_markup(notification: &Notification) -> Option<Value> {
+    if notification.keyboard.is_empty() {
+        return None;
+    }
+
+    let rows: Vec<Vec<Value>> = notification
+        .keyboard
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|button| match button {
+                    Button::Url { text, url } => json!({ "text": text, "url": url }),
+                    Button::Callback { text, data } => {
+                        json!({ "text": text, "callback_data": data })
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Some(json!({ "inline_keyboard": rows }))
+}
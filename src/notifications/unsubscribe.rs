@@ -0,0 +1,145 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::locale::{Locale, Text};
+
+use super::Notification;
+
+/// HMAC of the contact channel id keyed with `APPKEY`, so unsubscribe links
+/// can't be made up for other customers.
+fn mac(customer_contact_channel_id: &Uuid) -> Hmac<Sha256> {
+    // checked by Config::check at startup
+    let appkey = std::env::var("APPKEY").expect("APPKEY must be set");
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(appkey.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("unsubscribe:{}", customer_contact_channel_id).as_bytes());
+
+    mac
+}
+
+pub fn token(customer_contact_channel_id: &Uuid) -> String {
+    mac(customer_contact_channel_id)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Compares in constant time so the token can't be guessed byte by byte.
+pub fn is_valid(customer_contact_channel_id: &Uuid, token: &str) -> bool {
+    match decode_hex(token) {
+        Some(token) => mac(customer_contact_channel_id)
+            .verify_slice(&token)
+            .is_ok(),
+        None => false,
+    }
+}
+
+/// `APP_SCHEME` defaults to https, set it to http for local setups.
+pub fn url(customer_contact_channel_id: &Uuid) -> String {
+    let scheme = std::env::var("APP_SCHEME")
+        .ok()
+        .filter(|scheme| !scheme.is_empty())
+        .unwrap_or_else(|| "https".to_string());
+    let base_url = std::env::var("APP_HOST").unwrap_or_default();
+
+    format!(
+        "{}://{}/unsubscribe?id={}&token={}",
+        scheme,
+        base_url,
+        customer_contact_channel_id,
+        token(customer_contact_channel_id)
+    )
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Appends the unsubscribe link of the contact channel to the text and the
+/// HTML of an email.
+pub fn with_link(
+    notification: &Notification,
+    customer_contact_channel_id: &Uuid,
+    locale: Locale,
+) -> Notification {
+    let url = url(customer_contact_channel_id);
+    let label = locale.text(Text::Unsubscribe);
+
+    let mut notification = notification.clone();
+    notification.body = format!("{}\n\n{}: {}", notification.body, label, url);
+    notification.unsubscribe_url = Some(url.clone());
+
+    notification.html = notification.html.map(|html| {
+        let footer = format!(
+            "<p style=\"text-align:center;font-size:12px;color:#6b7280;\"><a href=\"{}\" style=\"color:#6b7280;\">{}</a></p>",
+            url, label
+        );
+
+        match html.rfind("</body>") {
+            Some(end) => format!("{}{}\n{}", &html[..end], footer, &html[end..]),
+            None => format!("{}{}", html, footer),
+        }
+    });
+
+    notification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel_id() -> Uuid {
+        std::env::set_var("APPKEY", "test-appkey");
+
+        Uuid::parse_str("6f1c2a9e-3b7d-4c1a-9e2f-5d8b7a6c4e21").unwrap()
+    }
+
+    #[test]
+    fn token_is_valid_for_its_channel_only() {
+        let id = channel_id();
+        let token = token(&id);
+
+        assert!(is_valid(&id, &token));
+        assert!(!is_valid(&Uuid::new_v4(), &token));
+    }
+
+    #[test]
+    fn tampered_or_malformed_tokens_are_invalid() {
+        let id = channel_id();
+        let mut tampered = token(&id);
+        let last = if tampered.ends_with('0') { "1" } else { "0" };
+        tampered.replace_range(tampered.len() - 1.., last);
+
+        assert!(!is_valid(&id, &tampered));
+        assert!(!is_valid(&id, ""));
+        assert!(!is_valid(&id, "abc"));
+        assert!(!is_valid(&id, &"zz".repeat(32)));
+        assert!(!is_valid(&id, &"é".repeat(32)));
+    }
+
+    #[test]
+    fn link_is_added_to_the_body_and_the_headers() {
+        let id = channel_id();
+        std::env::set_var("APP_HOST", "api.inving.co");
+
+        let notification = with_link(&Notification::new("Halo"), &id, Locale::Id);
+        let url = notification.unsubscribe_url.clone().unwrap();
+
+        assert!(url.starts_with("https://api.inving.co/unsubscribe?id="));
+        assert!(url.ends_with(&format!("&token={}", token(&id))));
+        assert!(notification
+            .body
+            .ends_with(&format!("Berhenti berlangganan: {}", url)));
+    }
+}
@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+
+use crate::{errors::DefaultError, repositories::whatsapp::whatsapp_send_message};
+
+use super::{Notification, NotificationChannel, Recipient};
+
+/// Sends to the phone number through the WhatsApp gateway.
+pub struct WhatsappChannel;
+
+#[async_trait]
+impl NotificationChannel for WhatsappChannel {
+    fn name(&self) -> &str {
+        "whatsapp"
+    }
+
+    async fn send(
+        &self,
+        recipient: &Recipient,
+        notification: &Notification,
+    ) -> Result<String, DefaultError> {
+        whatsapp_send_message(&recipient.value, &notification.body).await
+    }
+}
@@ -1,32 +1,71 @@
-use serde_json::json;
+use serde_json::{json, Value};
 
 use crate::errors::DefaultError;
 
-pub async fn telegram_send_message(chat_id: &i64, message: &str) -> Result<(), DefaultError> {
-    let client = reqwest::Client::new();
+/// Sends `message` with an optional inline keyboard and returns the Bot API
+/// response body.
+pub async fn telegram_send_message(
+    chat_id: &i64,
+    message: &str,
+    reply_markup: Option<Value>,
+) -> Result<String, DefaultError> {
+    let mut body = json!({
+        "chat_id": chat_id,
+        "text": message,
+    });
 
-    let host = std::env::var("TELEGRAM_BASE_URL").unwrap();
-    let telegram_bot_token = std::env::var("TELEGRAM_BOT_TOKEN").unwrap();
+    if let Some(reply_markup) = reply_markup {
+        body["reply_markup"] = reply_markup;
+    }
+
+    telegram_request("sendMessage", &body, "unable to send message telegram").await
+}
 
+/// Stops the loading indicator of the button the customer pressed.
+pub async fn telegram_answer_callback_query(
+    callback_query_id: &str,
+) -> Result<String, DefaultError> {
     let body = json!({
-        "chat_id": chat_id,
-        "text": message,
+        "callback_query_id": callback_query_id,
     });
 
-    let result = match client
-        .post(format!("{}/bot{}/sendMessage", host, telegram_bot_token))
-        .json(&body)
+    telegram_request(
+        "answerCallbackQuery",
+        &body,
+        "unable to answer callback query telegram",
+    )
+    .await
+}
+
+async fn telegram_request(
+    method: &str,
+    body: &Value,
+    error_message: &str,
+) -> Result<String, DefaultError> {
+    let client = reqwest::Client::new();
+
+    let host = std::env::var("TELEGRAM_BASE_URL").unwrap();
+    let telegram_bot_token = std::env::var("TELEGRAM_BOT_TOKEN").unwrap();
+
+    let res = match client
+        .post(format!("{}/bot{}/{}", host, telegram_bot_token, method))
+        .json(body)
         .send()
         .await
     {
-        Ok(_) => (),
-        Err(err) => {
-            return Err(DefaultError::new(
-                err.to_string(),
-                "unable to send message telegram".to_string(),
-            ))
-        }
+        Ok(res) => res,
+        Err(err) => return Err(DefaultError::new(err.to_string(), error_message.to_string())),
     };
 
-    Ok(result)
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+
+    if !status.is_success() {
+        return Err(DefaultError::new(
+            body,
+            format!("{} ({})", error_message, status),
+        ));
+    }
+
+    Ok(body)
 }
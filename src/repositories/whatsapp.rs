@@ -2,7 +2,11 @@ use axum::http::HeaderValue;
 
 use crate::errors::DefaultError;
 
-pub async fn whatsapp_send_message(phone_number: &str, message: &str) -> Result<(), DefaultError> {
+/// Sends `message` and returns the provider's response body.
+pub async fn whatsapp_send_message(
+    phone_number: &str,
+    message: &str,
+) -> Result<String, DefaultError> {
     let client = reqwest::Client::new();
 
     let host = std::env::var("WHATSAPP_BASE_URL").unwrap();
@@ -14,7 +18,7 @@ pub async fn whatsapp_send_message(phone_number: &str, message: &str) -> Result<
         HeaderValue::from_str(&whatsapp_api_key.as_str()).unwrap(),
     );
 
-    match client
+    let res = match client
         .post(format!("{}/api/send", host))
         .headers(headers)
         .query(&[("number", phone_number), ("message", message)])
@@ -28,5 +32,15 @@ pub async fn whatsapp_send_message(phone_number: &str, message: &str) -> Result<
         }),
     };
 
-    Ok(())
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+
+    if !status.is_success() {
+        return Err(DefaultError {
+            value: phone_number.to_string(),
+            message: format!("{}: {}", status, body),
+        });
+    }
+
+    Ok(body)
 }
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{models::message_template::MessageTemplate, notifications::Notification};
+
+/// Channel of a template that applies to every channel without its own.
+pub const ALL_CHANNELS: &str = "all";
+
+/// Event types merchants can write templates for, with the variables each
+/// one fills.
+pub const EVENT_TYPES: [(&str, &[&str]); 2] = [
+    (
+        "invoice",
+        &[
+            "customer_name",
+            "merchant_name",
+            "invoice_number",
+            "amount",
+            "due_date",
+            "pay_url",
+            "items",
+        ],
+    ),
+    (
+        "reminder",
+        &["customer_name", "merchant_name", "title", "description"],
+    ),
+];
+
+pub fn event_variables(event_type: &str) -> Option<&'static [&'static str]> {
+    EVENT_TYPES
+        .iter()
+        .find(|(name, _)| *name == event_type)
+        .map(|(_, variables)| *variables)
+}
+
+/// Names of the `{{variable}}` placeholders in `text`, in order.
+pub fn variables(text: &str) -> Vec<String> {
+    let mut variables = Vec::new();
+    let mut rest = text;
+
+    while let Some((start, end)) = next_placeholder(rest) {
+        variables.push(rest[start + 2..end].trim().to_string());
+        rest = &rest[end + 2..];
+    }
+
+    variables
+}
+
+/// Placeholders of `text` that `event_type` doesn't fill.
+pub fn unknown_variables(text: &str, event_type: &str) -> Vec<String> {
+    let known = event_variables(event_type).unwrap_or(&[]);
+
+    let mut unknown: Vec<String> = Vec::new();
+    for variable in variables(text) {
+        if !known.contains(&variable.as_str()) && !unknown.contains(&variable) {
+            unknown.push(variable);
+        }
+    }
+
+    unknown
+}
+
+/// Replaces each `{{variable}}` with its value, placeholders without a value
+/// are left as they are.
+pub fn render(text: &str, values: &HashMap<&str, String>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some((start, end)) = next_placeholder(rest) {
+        rendered.push_str(&rest[..start]);
+
+        match values.get(rest[start + 2..end].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..end + 2]),
+        }
+
+        rest = &rest[end + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Escapes text for use in HTML elements and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Example values used when previewing a template.
+pub fn sample_values(event_type: &str) -> HashMap<&'static str, String> {
+    let samples = [
+        ("customer_name", "Budi Santoso"),
+        ("merchant_name", "Toko Makmur"),
+        ("invoice_number", "INV-0001"),
+        ("amount", "Rp150.000"),
+        ("due_date", "18 Oktober 2026 09:00"),
+        ("pay_url", "<payment link>"),
+        ("items", "- Kopi Arabika 250g x2 @ Rp75.000"),
+        ("title", "Iuran bulanan"),
+        ("description", "Mohon lunasi iuran bulan ini."),
+    ];
+
+    let known = event_variables(event_type).unwrap_or(&[]);
+
+    samples
+        .iter()
+        .filter(|(name, _)| known.contains(name))
+        .map(|(name, value)| (*name, value.to_string()))
+        .collect()
+}
+
+/// Byte offsets of the next `{{` and its closing `}}`.
+fn next_placeholder(text: &str) -> Option<(usize, usize)> {
+    let start = text.find("{{")?;
+    let end = start + text[start..].find("}}")?;
+
+    Some((start, end))
+}
+
+/// The message of one event for each channel, rendered from the merchant's
+/// templates with the built-in message as fallback.
+#[derive(Debug, Clone)]
+pub struct ChannelMessages {
+    default: Notification,
+    channels: HashMap<String, Notification>,
+}
+
+impl ChannelMessages {
+    pub fn new(default: Notification) -> Self {
+        Self {
+            default,
+            channels: HashMap::new(),
+        }
+    }
+
+    /// Renders the merchant's templates of `event_type`, or the built-in
+    /// `default` template when it has none.
+    pub async fn for_merchant(
+        pool: &PgPool,
+        merchant_id: &Uuid,
+        event_type: &str,
+        values: &HashMap<&str, String>,
+        default: Notification,
+    ) -> Result<Self, sqlx::Error> {
+        let templates =
+            MessageTemplate::get_by_merchant_id_and_event_type(pool, merchant_id, event_type)
+                .await?;
+
+        Ok(Self::render(&templates, values, default))
+    }
+
+    /// A template for all channels replaces `default`, a channel's own
+    /// template wins over both.
+    pub fn render(
+        templates: &[MessageTemplate],
+        values: &HashMap<&str, String>,
+        default: Notification,
+    ) -> Self {
+        let mut messages = Self::new(Notification {
+            subject: default.subject.map(|subject| render(&subject, values)),
+            sender_name: default.sender_name.map(|sender_name| render(&sender_name, values)),
+            body: render(&default.body, values),
+            ..default
+        });
+
+        // channel templates without a subject take the one of the all channels template
+        let mut templates: Vec<&MessageTemplate> = templates.iter().collect();
+        templates.sort_by_key(|template| template.channel != ALL_CHANNELS);
+
+        for template in templates {
+            let mut notification = Notification::new(&render(&template.body, values));
+            notification.sender_name = messages.default.sender_name.clone();
+            notification.subject = match &template.subject {
+                Some(subject) => Some(render(subject, values)),
+                None => messages.default.subject.clone(),
+            };
+
+            if template.channel == ALL_CHANNELS {
+                messages.default = notification;
+            } else {
+                messages.channels.insert(template.channel.clone(), notification);
+            }
+        }
+
+        messages
+    }
+
+    pub fn for_channel(&self, channel: &str) -> &Notification {
+        self.channels.get(channel).unwrap_or(&self.default)
+    }
+
+    /// Changes the message of `channel`, starting from the one of all channels
+    /// when it has no template of its own.
+    pub fn map_channel(&mut self, channel: &str, f: impl FnOnce(Notification) -> Notification) {
+        let notification = match self.channels.remove(channel) {
+            Some(notification) => notification,
+            None => self.default.clone(),
+        };
+
+        self.channels.insert(channel.to_string(), f(notification));
+    }
+}
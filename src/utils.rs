@@ -1,7 +1,7 @@
 // pub type PoolPostgres = sqlx::pool::PoolConnection<sqlx::Postgres>;
 
 pub mod default_date_format {
-    use chrono::{Local, NaiveDateTime};
+    use chrono::NaiveDateTime;
     use serde::{self, Deserialize, Deserializer, Serializer};
 
     const FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
@@ -14,6 +14,8 @@ pub mod default_date_format {
         serializer.serialize_str(&s)
     }
 
+    // The value is kept as the merchant's wall-clock time, convert it with
+    // `timezone::local_to_utc` once the merchant is known.
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
     where
         D: Deserializer<'de>,
@@ -33,8 +35,348 @@ pub mod default_date_format {
             Err(err) => return Err(serde::de::Error::custom(err)),
         };
 
-        let dt = dt.and_local_timezone(Local).unwrap();
+        Ok(Some(dt))
+    }
+}
+
+pub mod timezone {
+    use chrono::{Duration, LocalResult, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    pub const DEFAULT_TIMEZONE: &'static str = "Asia/Jakarta";
+
+    pub fn parse(name: &str) -> Option<Tz> {
+        name.parse::<Tz>().ok()
+    }
+
+    pub fn parse_or_default(name: &str) -> Tz {
+        parse(name).unwrap_or(chrono_tz::Asia::Jakarta)
+    }
+
+    /// Converts a wall-clock time in `tz` to UTC.
+    ///
+    /// Ambiguous times (clocks going back) resolve to the earlier instant, and
+    /// times skipped by a DST gap are moved forward past the gap.
+    pub fn local_to_utc(local: &NaiveDateTime, tz: &Tz) -> NaiveDateTime {
+        match tz.from_local_datetime(local) {
+            LocalResult::Single(dt) => dt.naive_utc(),
+            LocalResult::Ambiguous(earliest, _) => earliest.naive_utc(),
+            LocalResult::None => {
+                let mut shifted = *local;
+                for _ in 0..4 {
+                    shifted = shifted + Duration::minutes(30);
+                    if let LocalResult::Single(dt) = tz.from_local_datetime(&shifted) {
+                        return dt.naive_utc();
+                    }
+                }
+
+                Utc.from_utc_datetime(local).naive_utc()
+            }
+        }
+    }
+
+    pub fn utc_to_local(utc: &NaiveDateTime, tz: &Tz) -> NaiveDateTime {
+        Utc.from_utc_datetime(utc).with_timezone(tz).naive_local()
+    }
+
+    pub fn format(utc: &NaiveDateTime, tz: &Tz, format: &str) -> String {
+        Utc.from_utc_datetime(utc)
+            .with_timezone(tz)
+            .format(format)
+            .to_string()
+    }
+
+    /// Advances `run_at` (UTC) by `interval_seconds`.
+    ///
+    /// Whole-day intervals are applied on the merchant's wall clock so a
+    /// recurring 09:00 job stays at 09:00 local time across DST changes.
+    pub fn add_interval(run_at: &NaiveDateTime, interval_seconds: i64, tz: &Tz) -> NaiveDateTime {
+        let interval = Duration::seconds(interval_seconds);
+
+        if interval_seconds % Duration::days(1).num_seconds() != 0 {
+            return *run_at + interval;
+        }
+
+        let local = utc_to_local(run_at, tz);
+        local_to_utc(&(local + interval), tz)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn at(value: &str) -> NaiveDateTime {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+        }
+
+        const DAY: i64 = 24 * 60 * 60;
+
+        #[test]
+        fn add_interval_keeps_the_wall_clock_across_dst() {
+            let tz = chrono_tz::America::New_York;
+
+            // 09:00 EST, clocks move forward on 2023-03-12
+            let run_at = add_interval(&at("2023-03-11 14:00"), DAY, &tz);
+            assert_eq!(run_at, at("2023-03-12 13:00"));
+            assert_eq!(format(&run_at, &tz, "%H:%M"), "09:00");
+
+            // 09:00 EDT, clocks move back on 2023-11-05
+            let run_at = add_interval(&at("2023-11-04 13:00"), 7 * DAY, &tz);
+            assert_eq!(run_at, at("2023-11-11 14:00"));
+            assert_eq!(format(&run_at, &tz, "%H:%M"), "09:00");
+        }
+
+        #[test]
+        fn add_interval_adds_shorter_intervals_as_elapsed_time() {
+            let tz = chrono_tz::America::New_York;
+
+            assert_eq!(
+                add_interval(&at("2023-03-12 06:30"), 60 * 60, &tz),
+                at("2023-03-12 07:30")
+            );
+            assert_eq!(
+                add_interval(&at("2023-03-12 06:30"), 60, &tz),
+                at("2023-03-12 06:31")
+            );
+        }
+
+        #[test]
+        fn add_interval_without_dst() {
+            let tz = chrono_tz::Asia::Jakarta;
+
+            assert_eq!(
+                add_interval(&at("2023-01-31 02:00"), 28 * DAY, &tz),
+                at("2023-02-28 02:00")
+            );
+        }
+
+        #[test]
+        fn local_to_utc_resolves_gaps_and_overlaps() {
+            let tz = chrono_tz::America::New_York;
+
+            // 02:30 doesn't exist on 2023-03-12, it moves past the gap to 03:00 EDT
+            assert_eq!(local_to_utc(&at("2023-03-12 02:30"), &tz), at("2023-03-12 07:00"));
+            // 01:30 happens twice on 2023-11-05, the earlier one is EDT
+            assert_eq!(local_to_utc(&at("2023-11-05 01:30"), &tz), at("2023-11-05 05:30"));
+        }
+    }
+}
+
+pub mod schedule {
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use chrono_tz::Tz;
+
+    use super::timezone;
+    use crate::models::job_schedule::repeat_interval_seconds;
+
+    pub const MIN_RECURRING_DAYS: i64 = 5;
+
+    const DISPLAY_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S %Z";
+
+    /// Run time and recurrence of a schedule, all in UTC.
+    #[derive(Debug)]
+    pub struct ScheduleTiming {
+        pub start_at: NaiveDateTime,
+        pub end_at: Option<NaiveDateTime>,
+        pub repeat_interval_type: String,
+        pub repeat_interval: i64,
+        pub repeat_count: i64,
+    }
+
+    impl ScheduleTiming {
+        /// Computes the timing of a new schedule from the merchant's wall-clock
+        /// `start_at`/`end_at`. A one-off schedule runs a few seconds from now.
+        pub fn compute(
+            is_recurring: bool,
+            repeat_interval_type: Option<&str>,
+            start_at: Option<NaiveDateTime>,
+            end_at: Option<NaiveDateTime>,
+            tz: &Tz,
+        ) -> Result<ScheduleTiming, String> {
+            let now = Utc::now().naive_utc();
 
-        Ok(Some(dt.naive_utc()))
+            if !is_recurring {
+                return Ok(ScheduleTiming {
+                    start_at: now + Duration::seconds(5),
+                    end_at: None,
+                    repeat_interval_type: "ONCE".to_string(),
+                    repeat_interval: repeat_interval_seconds("ONCE"),
+                    repeat_count: 0,
+                });
+            }
+
+            let (start_at, end_at) = match (start_at, end_at) {
+                (Some(start_at), Some(end_at)) => (
+                    timezone::local_to_utc(&start_at, tz),
+                    timezone::local_to_utc(&end_at, tz),
+                ),
+                _ => return Err("start_at and end_at are required for a recurring schedule".to_string()),
+            };
+
+            let repeat_interval_type = match repeat_interval_type {
+                Some(repeat_interval_type) => repeat_interval_type,
+                None => {
+                    return Err("repeat_interval_type is required for a recurring schedule, use PERMINUTE, HOURLY, DAILY, WEEKLY or MONTHLY".to_string())
+                }
+            };
+
+            if end_at < start_at {
+                return Err(format!(
+                    "end_at ( {} ) must be after start_at ( {} )",
+                    timezone::format(&end_at, tz, DISPLAY_FORMAT),
+                    timezone::format(&start_at, tz, DISPLAY_FORMAT)
+                ));
+            }
+
+            if start_at < now {
+                return Err(format!(
+                    "start_at ( {} ) is in the past, it must be after the current time ( {} )",
+                    timezone::format(&start_at, tz, DISPLAY_FORMAT),
+                    timezone::format(&now, tz, DISPLAY_FORMAT)
+                ));
+            }
+
+            let span = end_at - start_at;
+            if span < Duration::days(MIN_RECURRING_DAYS) {
+                return Err(format!(
+                    "a recurring schedule must span at least {} days, end_at is only {} after start_at",
+                    MIN_RECURRING_DAYS,
+                    humanize(&span)
+                ));
+            }
+
+            let repeat_interval = repeat_interval_seconds(repeat_interval_type);
+
+            Ok(ScheduleTiming {
+                start_at,
+                end_at: Some(end_at),
+                repeat_interval_type: repeat_interval_type.to_string(),
+                repeat_interval,
+                repeat_count: span.num_seconds() / repeat_interval,
+            })
+        }
+
+        /// Number of times the job runs: the first run plus every repeat.
+        pub fn total_occurrences(&self) -> i64 {
+            self.repeat_count + 1
+        }
+
+        /// Run times in UTC, following the same wall-clock rules the worker
+        /// uses to advance `run_at`. At most `limit` entries are returned.
+        pub fn occurrences(&self, tz: &Tz, limit: usize) -> Vec<NaiveDateTime> {
+            let mut occurrences = Vec::new();
+            let mut run_at = self.start_at;
+
+            for _ in 0..self.total_occurrences() {
+                if occurrences.len() >= limit {
+                    break;
+                }
+
+                occurrences.push(run_at);
+                run_at = timezone::add_interval(&run_at, self.repeat_interval, tz);
+            }
+
+            occurrences
+        }
+    }
+
+    fn humanize(duration: &Duration) -> String {
+        let days = duration.num_days();
+        let hours = duration.num_hours() - days * 24;
+
+        match (days, hours) {
+            (0, 0) => format!("{} minutes", duration.num_minutes()),
+            (0, hours) => format!("{} hours", hours),
+            (days, 0) => format!("{} days", days),
+            (days, hours) => format!("{} days {} hours", days, hours),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn jakarta() -> Tz {
+            chrono_tz::Asia::Jakarta
+        }
+
+        /// 09:00 in Jakarta, `days` from today.
+        fn local_in_days(days: i64) -> NaiveDateTime {
+            let local = timezone::utc_to_local(&Utc::now().naive_utc(), &jakarta());
+            let date = (local + Duration::days(days)).date();
+
+            date.and_hms(9, 0, 0)
+        }
+
+        fn compute(
+            repeat_interval_type: Option<&str>,
+            start_at: Option<NaiveDateTime>,
+            end_at: Option<NaiveDateTime>,
+        ) -> Result<ScheduleTiming, String> {
+            ScheduleTiming::compute(true, repeat_interval_type, start_at, end_at, &jakarta())
+        }
+
+        #[test]
+        fn one_off_schedule_runs_soon_and_never_repeats() {
+            let now = Utc::now().naive_utc();
+
+            let timing = ScheduleTiming::compute(false, None, None, None, &jakarta()).unwrap();
+
+            assert!(timing.start_at > now && timing.start_at <= now + Duration::seconds(10));
+            assert_eq!(timing.end_at, None);
+            assert_eq!(timing.repeat_interval_type, "ONCE");
+            assert_eq!(timing.repeat_count, 0);
+            assert_eq!(timing.total_occurrences(), 1);
+        }
+
+        #[test]
+        fn recurring_schedule_counts_its_repeats_in_utc() {
+            let start_at = local_in_days(1);
+            let end_at = local_in_days(11);
+
+            let timing = compute(Some("DAILY"), Some(start_at), Some(end_at)).unwrap();
+
+            // 09:00 in Jakarta is 02:00 UTC
+            assert_eq!(timing.start_at, start_at - Duration::hours(7));
+            assert_eq!(timing.end_at, Some(end_at - Duration::hours(7)));
+            assert_eq!(timing.repeat_interval, Duration::days(1).num_seconds());
+            assert_eq!(timing.repeat_count, 10);
+            assert_eq!(timing.total_occurrences(), 11);
+
+            let occurrences = timing.occurrences(&jakarta(), 3);
+            assert_eq!(occurrences.len(), 3);
+            assert_eq!(occurrences[2], timing.start_at + Duration::days(2));
+        }
+
+        #[test]
+        fn recurring_schedule_needs_start_end_and_interval() {
+            let start_at = Some(local_in_days(1));
+            let end_at = Some(local_in_days(11));
+
+            assert!(compute(Some("DAILY"), None, end_at).is_err());
+            assert!(compute(Some("DAILY"), start_at, None).is_err());
+            assert!(compute(None, start_at, end_at)
+                .unwrap_err()
+                .contains("repeat_interval_type"));
+        }
+
+        #[test]
+        fn recurring_schedule_must_start_in_the_future_and_end_after_it() {
+            let err = compute(Some("DAILY"), Some(local_in_days(11)), Some(local_in_days(1)));
+            assert!(err.unwrap_err().contains("must be after start_at"));
+
+            let err = compute(Some("DAILY"), Some(local_in_days(-1)), Some(local_in_days(10)));
+            assert!(err.unwrap_err().contains("is in the past"));
+        }
+
+        #[test]
+        fn recurring_schedule_must_span_the_minimum_days() {
+            let start_at = local_in_days(1);
+            let end_at = start_at + Duration::days(MIN_RECURRING_DAYS - 1) + Duration::hours(3);
+
+            let err = compute(Some("HOURLY"), Some(start_at), Some(end_at)).unwrap_err();
+
+            assert!(err.contains("only 4 days 3 hours"));
+        }
     }
 }
@@ -0,0 +1,624 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::jobs::handlers::deliver_webhook::DeliverWebhookHandler;
+use crate::jobs::handlers::send_invoice::PAYMENT_DUE_HOURS;
+use crate::jobs::registry::JobHandler;
+use crate::locale::{Locale, Text};
+use crate::models::{
+    invoice::Invoice, job_queue::JobQueue, merchant::Merchant, webhook_delivery::WebhookDelivery,
+    webhook_subscription::WebhookSubscription,
+};
+use crate::templates;
+use crate::utils::timezone;
+
+/// Events merchants can subscribe to.
+pub const EVENT_TYPES: [&str; 7] = [
+    "invoice.created",
+    "invoice.paid",
+    "invoice.expired",
+    "invoice.overdue",
+    "customer.verified",
+    "job.failed",
+    "daily.summary",
+];
+
+/// `webhook` posts the signed JSON event, `slack` and `discord` post a
+/// message to an incoming webhook of the merchant's workspace.
+pub const KINDS: [&str; 3] = ["webhook", "slack", "discord"];
+
+/// Events that have a message for Slack and Discord.
+pub const CHAT_EVENT_TYPES: [&str; 4] = [
+    "invoice.paid",
+    "invoice.overdue",
+    "job.failed",
+    "daily.summary",
+];
+
+/// Local hour daily summaries of the previous day are sent from.
+const SUMMARY_HOUR: u32 = 8;
+
+/// Sent by the test endpoint only, whatever the subscription's events are.
+pub const PING: &str = "ping";
+
+/// How long the merchant's endpoint has to answer.
+const TIMEOUT_SECONDS: u64 = 10;
+
+/// Response bodies are kept in the delivery log up to this many characters.
+const RESPONSE_BODY_LIMIT: usize = 1000;
+
+/// Queues the event for every active subscription of the merchant to it.
+/// Webhooks are best effort, failures to queue are only logged so they never
+/// fail what triggered the event.
+pub async fn emit(db: &PgPool, merchant_id: &Uuid, event_type: &str, data: Value) {
+    let webhook_subscriptions =
+        match WebhookSubscription::get_active_by_event_type(db, merchant_id, event_type).await {
+            Ok(webhook_subscriptions) => webhook_subscriptions,
+            Err(err) => {
+                println!(
+                    "Failed to get webhook subscriptions of merchant {}: {}",
+                    merchant_id, err
+                );
+                return;
+            }
+        };
+
+    for webhook_subscription in webhook_subscriptions.iter() {
+        match enqueue(db, webhook_subscription, event_type, data.clone()).await {
+            Ok(_) => (),
+            Err(err) => println!(
+                "Failed to queue {} for webhook subscription {}: {}",
+                event_type, webhook_subscription.id, err
+            ),
+        }
+    }
+}
+
+/// Records the delivery and queues a `deliver_webhook` job to post it.
+pub async fn enqueue(
+    db: &PgPool,
+    webhook_subscription: &WebhookSubscription,
+    event_type: &str,
+    data: Value,
+) -> Result<WebhookDelivery, sqlx::Error> {
+    let webhook_delivery = create_delivery(db, webhook_subscription, event_type, data).await?;
+
+    let handler = DeliverWebhookHandler;
+
+    JobQueue::create(
+        db,
+        handler.job_type(),
+        Some(json!({
+            "webhook_delivery_id": webhook_delivery.id,
+            "merchant_id": webhook_subscription.merchant_id,
+        })),
+        None,
+        handler.priority(),
+        "pending",
+    )
+    .await?;
+
+    Ok(webhook_delivery)
+}
+
+/// Records the delivery without queueing it, the payload is fixed here so
+/// every attempt posts the same body.
+pub async fn create_delivery(
+    db: &PgPool,
+    webhook_subscription: &WebhookSubscription,
+    event_type: &str,
+    data: Value,
+) -> Result<WebhookDelivery, sqlx::Error> {
+    let payload = match webhook_subscription.kind.as_str() {
+        "slack" | "discord" => chat_payload(db, webhook_subscription, event_type, &data).await?,
+        _ => json!({
+            "type": event_type,
+            "merchant_id": webhook_subscription.merchant_id,
+            "created_at": chrono::Utc::now(),
+            "data": data,
+        }),
+    };
+
+    WebhookDelivery::create(db, webhook_subscription.id, event_type, &payload).await
+}
+
+/// Incoming webhook body of Slack, or of Discord, with the event as a message
+/// in the merchant's locale under the merchant's name.
+async fn chat_payload(
+    db: &PgPool,
+    webhook_subscription: &WebhookSubscription,
+    event_type: &str,
+    data: &Value,
+) -> Result<Value, sqlx::Error> {
+    let merchant = Merchant::get_by_id(db, webhook_subscription.merchant_id).await?;
+    let message = chat_message(merchant.locale(), event_type, data);
+
+    let payload = match webhook_subscription.kind.as_str() {
+        "discord" => json!({ "content": format!("**{}**\n{}", merchant.name, message) }),
+        _ => json!({ "text": format!("*{}*\n{}", merchant.name, message) }),
+    };
+
+    Ok(payload)
+}
+
+fn chat_message(locale: Locale, event_type: &str, data: &Value) -> String {
+    let field = |name: &str| match &data[name] {
+        Value::String(value) => value.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    let amount = |name: &str| locale.format_currency(data[name].as_i64().unwrap_or_default());
+
+    let (text, values) = match event_type {
+        "invoice.paid" | "invoice.overdue" => (
+            if event_type == "invoice.paid" {
+                Text::IntegrationInvoicePaid
+            } else {
+                Text::IntegrationInvoiceOverdue
+            },
+            HashMap::from([
+                ("invoice_number", field("invoice_number")),
+                ("amount", amount("total_amount")),
+            ]),
+        ),
+        "job.failed" => (
+            Text::IntegrationJobFailed,
+            HashMap::from([("job_type", field("job_type")), ("error", field("error"))]),
+        ),
+        "daily.summary" => (
+            Text::IntegrationDailySummary,
+            HashMap::from([
+                (
+                    "date",
+                    match NaiveDate::parse_from_str(&field("date"), "%Y-%m-%d") {
+                        Ok(date) => locale.format_date(&date),
+                        Err(_) => field("date"),
+                    },
+                ),
+                ("paid_count", field("paid_count")),
+                ("paid_amount", amount("paid_amount")),
+                ("unpaid_count", field("unpaid_count")),
+                ("overdue_count", field("overdue_count")),
+            ]),
+        ),
+        PING => (Text::IntegrationPing, HashMap::new()),
+        _ => return event_type.to_string(),
+    };
+
+    templates::render(locale.text(text), &values)
+}
+
+/// Marks unpaid invoices past their due date as overdue and emits
+/// `invoice.overdue` for each of them.
+pub async fn emit_overdue_invoices(db: &PgPool) {
+    let due_before = chrono::Utc::now().naive_utc() - chrono::Duration::hours(PAYMENT_DUE_HOURS);
+
+    let invoices = match Invoice::mark_overdue(db, &due_before).await {
+        Ok(invoices) => invoices,
+        Err(err) => {
+            println!("Failed to mark overdue invoices: {}", err);
+            return;
+        }
+    };
+
+    for invoice in invoices.iter() {
+        emit(db, &invoice.merchant_id, "invoice.overdue", json!(invoice)).await;
+    }
+}
+
+/// Queues the summary of the previous day for subscriptions to
+/// `daily.summary` once their merchant's local time passes `SUMMARY_HOUR`.
+pub async fn emit_daily_summaries(db: &PgPool) {
+    let webhook_subscriptions =
+        match WebhookSubscription::get_all_active_by_event_type(db, "daily.summary").await {
+            Ok(webhook_subscriptions) => webhook_subscriptions,
+            Err(err) => {
+                println!("Failed to get daily summary subscriptions: {}", err);
+                return;
+            }
+        };
+
+    let now = chrono::Utc::now().naive_utc();
+    let mut summaries: HashMap<Uuid, Option<DailySummary>> = HashMap::new();
+
+    for webhook_subscription in webhook_subscriptions.iter() {
+        let merchant_id = webhook_subscription.merchant_id;
+
+        if !summaries.contains_key(&merchant_id) {
+            let summary = daily_summary(db, &merchant_id, &now).await;
+            summaries.insert(merchant_id, summary);
+        }
+
+        let summary = match &summaries[&merchant_id] {
+            Some(summary) => summary,
+            None => continue,
+        };
+
+        match WebhookSubscription::claim_summary(db, webhook_subscription.id, &summary.today_start)
+            .await
+        {
+            Ok(Some(_)) => (),
+            Ok(None) => continue,
+            Err(err) => {
+                println!(
+                    "Failed to claim daily summary of webhook subscription {}: {}",
+                    webhook_subscription.id, err
+                );
+                continue;
+            }
+        }
+
+        match enqueue(
+            db,
+            webhook_subscription,
+            "daily.summary",
+            summary.data.clone(),
+        )
+        .await
+        {
+            Ok(_) => (),
+            Err(err) => println!(
+                "Failed to queue daily.summary for webhook subscription {}: {}",
+                webhook_subscription.id, err
+            ),
+        }
+    }
+}
+
+struct DailySummary {
+    /// Start of the merchant's day in UTC, summaries sent before it were of
+    /// an earlier day.
+    today_start: NaiveDateTime,
+    data: Value,
+}
+
+/// Summary of the merchant's previous day, `None` before `SUMMARY_HOUR` or
+/// when it can't be loaded.
+async fn daily_summary(
+    db: &PgPool,
+    merchant_id: &Uuid,
+    now: &NaiveDateTime,
+) -> Option<DailySummary> {
+    let merchant = match Merchant::get_by_id(db, *merchant_id).await {
+        Ok(merchant) => merchant,
+        Err(err) => {
+            println!(
+                "Failed to get merchant {} to summarize: {}",
+                merchant_id, err
+            );
+            return None;
+        }
+    };
+
+    let tz = merchant.tz();
+    let local_now = timezone::utc_to_local(now, &tz);
+
+    if local_now.hour() < SUMMARY_HOUR {
+        return None;
+    }
+
+    let today = local_now.date();
+    let yesterday = today.pred();
+    let midnight = NaiveTime::from_hms(0, 0, 0);
+    let today_start = timezone::local_to_utc(&today.and_time(midnight), &tz);
+    let yesterday_start = timezone::local_to_utc(&yesterday.and_time(midnight), &tz);
+
+    let invoice_summary =
+        match Invoice::get_summary_by_merchant_id(db, merchant_id, &yesterday_start, &today_start)
+            .await
+        {
+            Ok(invoice_summary) => invoice_summary,
+            Err(err) => {
+                println!(
+                    "Failed to summarize invoices of merchant {}: {}",
+                    merchant_id, err
+                );
+                return None;
+            }
+        };
+
+    Some(DailySummary {
+        today_start,
+        data: json!({
+            "date": yesterday.format("%Y-%m-%d").to_string(),
+            "paid_count": invoice_summary.paid_count,
+            "paid_amount": invoice_summary.paid_amount,
+            "unpaid_count": invoice_summary.unpaid_count,
+            "overdue_count": invoice_summary.overdue_count,
+        }),
+    })
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>` keyed with the subscription
+/// secret, receivers recompute it to check the body came from us and reject
+/// old timestamps against replays.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Outcome of posting a delivery once.
+pub struct Attempt {
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Attempt {
+    pub fn is_delivered(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Posts the delivery payload to the subscription url, signed with the
+/// `X-Webhook-Signature: t=<timestamp>,v1=<signature>` header. Any 2xx
+/// response counts as delivered.
+/// Checks that `url` is https and that its host resolves to public addresses
+/// only, so subscriptions can't reach the network the workers run in.
+/// Returns the host with the address to connect to.
+pub async fn resolve_public(url: &str) -> Result<(String, SocketAddr), String> {
+    let url = reqwest::Url::parse(url).map_err(|err| format!("{} is not a url: {}", url, err))?;
+
+    if url.scheme() != "https" {
+        return Err(format!("{} must use https", url));
+    }
+
+    let host = match url.host_str() {
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        None => return Err(format!("{} has no host", url)),
+    };
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|err| format!("{} can't be resolved: {}", host, err))?
+        .collect::<Vec<SocketAddr>>();
+
+    if addrs.is_empty() {
+        return Err(format!("{} can't be resolved", host));
+    }
+
+    // one private address is enough to refuse, the client may pick any
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(&addr.ip())) {
+        return Err(format!(
+            "{} resolves to {}, which is not public",
+            host,
+            addr.ip()
+        ));
+    }
+
+    Ok((host, addrs[0]))
+}
+
+/// False for loopback, private, link-local, shared, multicast and other
+/// addresses that are not reachable on the internet.
+pub fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // shared address space of carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                // benchmarking
+                || (a == 198 && (b == 18 || b == 19))
+                // reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+
+            // IPv4-mapped and IPv4-compatible addresses
+            if segments[..5] == [0, 0, 0, 0, 0] && (segments[5] == 0 || segments[5] == 0xffff) {
+                if let Some(ipv4) = ip.to_ipv4() {
+                    return is_public_ip(&IpAddr::V4(ipv4));
+                }
+            }
+
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local
+                || (segments[0] & 0xfe00) == 0xfc00
+                // link-local
+                || (segments[0] & 0xffc0) == 0xfe80
+                // documentation
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Connects to the checked address only and doesn't follow redirects, which
+/// could lead anywhere.
+fn client(host: &str, addr: SocketAddr) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .resolve(host, addr)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+pub async fn post(
+    webhook_subscription: &WebhookSubscription,
+    webhook_delivery: &WebhookDelivery,
+) -> Attempt {
+    let body = webhook_delivery.payload.to_string();
+    let timestamp = chrono::Utc::now().timestamp();
+
+    // resolved again for every attempt, the name may point elsewhere by now
+    let client = match resolve_public(&webhook_subscription.url).await {
+        Ok((host, addr)) => client(&host, addr),
+        Err(err) => Err(err),
+    };
+
+    let client = match client {
+        Ok(client) => client,
+        Err(err) => {
+            return Attempt {
+                response_status: None,
+                response_body: None,
+                error: Some(err),
+            }
+        }
+    };
+
+    let result = client
+        .post(&webhook_subscription.url)
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .header("content-type", "application/json")
+        .header("x-webhook-id", webhook_delivery.id.to_string())
+        .header("x-webhook-event", &webhook_delivery.event_type)
+        .header(
+            "x-webhook-signature",
+            format!(
+                "t={},v1={}",
+                timestamp,
+                signature(&webhook_subscription.secret, timestamp, &body)
+            ),
+        )
+        .body(body)
+        .send()
+        .await;
+
+    let response = match result {
+        Ok(response) => response,
+        Err(err) => {
+            return Attempt {
+                response_status: None,
+                response_body: None,
+                error: Some(err.to_string()),
+            }
+        }
+    };
+
+    let status = response.status();
+    let response_body: String = response
+        .text()
+        .await
+        .unwrap_or_default()
+        .chars()
+        .take(RESPONSE_BODY_LIMIT)
+        .collect();
+
+    let error = if status.is_success() {
+        None
+    } else {
+        Some(format!("endpoint responded with {}", status))
+    };
+
+    Attempt {
+        response_status: Some(status.as_u16() as i32),
+        response_body: Some(response_body),
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_public(ip: &str) -> bool {
+        is_public_ip(&ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn signature_is_the_hex_hmac_of_timestamp_and_body() {
+        assert_eq!(
+            signature("whsec_test", 1680000000, r#"{"event":"invoice.paid"}"#),
+            "762f7a674ff1bfd2e02652f8a5d882c323688bcb2eee55890a643b667e05edf4"
+        );
+    }
+
+    #[test]
+    fn signature_changes_with_secret_timestamp_and_body() {
+        let body = r#"{"event":"invoice.paid"}"#;
+        let expected = signature("whsec_test", 1680000000, body);
+
+        assert_ne!(signature("whsec_other", 1680000000, body), expected);
+        assert_ne!(signature("whsec_test", 1680000001, body), expected);
+        assert_ne!(
+            signature("whsec_test", 1680000000, r#"{"event":"invoice.expired"}"#),
+            expected
+        );
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        assert!(is_public("1.1.1.1"));
+        assert!(is_public("203.0.114.1"));
+        assert!(is_public("2606:4700:4700::1111"));
+    }
+
+    #[test]
+    fn internal_addresses_are_refused() {
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "224.0.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip), "{} should not be public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_public_requires_https() {
+        let err = resolve_public("http://1.1.1.1/hook").await.unwrap_err();
+
+        assert!(err.contains("https"));
+    }
+
+    #[tokio::test]
+    async fn resolve_public_refuses_internal_hosts() {
+        assert!(resolve_public("https://127.0.0.1/hook").await.is_err());
+        assert!(resolve_public("https://[::1]:8443/hook").await.is_err());
+        assert!(resolve_public("https://169.254.169.254/latest/meta-data")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_public_keeps_the_port() {
+        let (host, addr) = resolve_public("https://1.1.1.1:8443/hook").await.unwrap();
+
+        assert_eq!(host, "1.1.1.1");
+        assert_eq!(addr, "1.1.1.1:8443".parse::<SocketAddr>().unwrap());
+    }
+}